@@ -0,0 +1,148 @@
+use std::fmt;
+
+use arbitrary::Arbitrary;
+use struct_iterable::Iterable;
+
+use crate::Action;
+
+#[allow(non_camel_case_types)]
+#[derive(Arbitrary, Debug, Iterable, Clone)]
+pub struct Aarch64March {
+    pub arch: ArmArch,
+    bf16: bool,
+    crc: bool,
+    crypto: bool,
+    dotprod: bool,
+    f32mm: bool,
+    f64mm: bool,
+    fp16: bool,
+    fp16fml: bool,
+    i8mm: bool,
+    lse: bool,
+    rcpc: bool,
+    rdma: bool,
+    sb: bool,
+    ssbs: bool,
+    pub sve: bool,
+    pub sve2: bool,
+    sve2_bitperm: bool,
+    pub msve_vector_bits: Option<SveVectorBits>,
+    pub mcpu: Option<ArmCpu>,
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Arbitrary, Debug, Clone, Copy, PartialEq)]
+pub enum ArmArch {
+    armv8_a,
+    armv8_1_a,
+    armv8_2_a,
+    armv8_4_a,
+    armv8_6_a,
+    armv9_a,
+    armv9_2_a,
+}
+
+impl ArmArch {
+    /// SVE2 is mandatory from armv9
+    pub fn implies_sve2(&self) -> bool {
+        matches!(self, ArmArch::armv9_a | ArmArch::armv9_2_a)
+    }
+}
+
+impl fmt::Display for ArmArch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // armv8_2_a -> armv8.2-a
+        let arch = format!("{:?}", self);
+        let arch = arch.strip_suffix("_a").unwrap().replace('_', ".");
+        write!(f, "{arch}-a")
+    }
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Arbitrary, Debug, Clone, Copy, PartialEq)]
+pub enum SveVectorBits {
+    scalable,
+    b128,
+    b256,
+    b512,
+    b1024,
+    b2048,
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Arbitrary, Debug, Clone, Copy)]
+pub enum ArmCpu {
+    a64fx,
+    ampere1,
+    cortex_a76,
+    cortex_a710,
+    cortex_x2,
+    neoverse_n1,
+    neoverse_n2,
+    neoverse_v1,
+    neoverse_v2,
+}
+
+impl Aarch64March {
+    pub fn sanitize(&mut self, _action: &Action) {
+        // Matrix multiply and bitperm extend SVE/SVE2
+        if self.f32mm || self.f64mm {
+            self.sve = true;
+        }
+        if self.sve2_bitperm {
+            self.sve2 = true;
+        }
+        if self.fp16fml {
+            self.fp16 = true;
+        }
+
+        if !self.sve && !self.sve2 && !self.arch.implies_sve2() {
+            self.msve_vector_bits = None;
+        }
+    }
+}
+
+impl fmt::Display for Aarch64March {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut arch = String::new();
+
+        let flags = self
+            .iter()
+            .map(|(field_name, field_value)| {
+                if field_value.is::<bool>() {
+                    let value: bool = *field_value.downcast_ref().unwrap();
+                    if value {
+                        format!("+{}", field_name.replace('_', "-"))
+                    } else {
+                        "".to_string()
+                    }
+                } else if field_value.is::<ArmArch>() {
+                    let value: ArmArch = *field_value.downcast_ref().unwrap();
+                    arch = value.to_string();
+                    "".to_string()
+                } else if field_value.is::<Option<SveVectorBits>>() {
+                    let value: Option<SveVectorBits> = *field_value.downcast_ref().unwrap();
+                    if let Some(value) = value {
+                        let bits = format!("{:?}", value);
+                        let bits = bits.strip_prefix('b').unwrap_or(&bits);
+                        format!(" -msve-vector-bits={bits}")
+                    } else {
+                        "".to_string()
+                    }
+                } else if field_value.is::<Option<ArmCpu>>() {
+                    let value: Option<ArmCpu> = *field_value.downcast_ref().unwrap();
+                    if let Some(value) = value {
+                        let cpu = format!("{:?}", value).replace('_', "-");
+                        format!(" -mcpu={cpu}")
+                    } else {
+                        "".to_string()
+                    }
+                } else {
+                    panic!("Unknown datatype for field: {}", field_name)
+                }
+            })
+            .collect::<Vec<_>>();
+
+        write!(f, "-march={}{}", arch, flags.join(""))
+    }
+}
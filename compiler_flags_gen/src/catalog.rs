@@ -0,0 +1,444 @@
+use std::{collections::BTreeMap, io, path::Path, process::Command};
+
+use arbitrary::{Arbitrary, Unstructured};
+use log::trace;
+
+use crate::Compiler;
+
+/// Options that select the target. These are owned by riscv::March so they
+/// are never taken from the catalog.
+const TARGET_SELECTION_OPTS: &[&str] = &["-march", "-mabi", "-mcpu", "-mtune"];
+
+/// Driver options that happen to start with -m/-g
+const CLANG_DRIVER_OPTS: &[&str] = &[
+    "-gen-cdb-fragment-path",
+    "-gen-reproducer",
+    "-mllvm",
+    "-mmlir",
+    "-module-dependency-dir",
+    "-module-file-info",
+];
+
+/// Range used for numeric options that don't advertise their bounds
+const DEFAULT_NUMERIC_RANGE: (i64, i64) = (0, 256);
+
+/// Most flags arbitrary_flags takes from the catalog
+const MAX_CATALOG_FLAGS: usize = 32;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FlagKind {
+    /// -fname / -fno-name
+    Toggle,
+    /// -fname=choice
+    Enum(Vec<String>),
+    /// -fname=N
+    Numeric { min: i64, max: i64 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatalogFlag {
+    /// Option name without the no- prefix or the =value suffix
+    pub name: String,
+    pub kind: FlagKind,
+}
+
+impl CatalogFlag {
+    fn arbitrary_flag(&self, u: &mut Unstructured) -> arbitrary::Result<String> {
+        Ok(match &self.kind {
+            FlagKind::Toggle => {
+                if bool::arbitrary(u)? {
+                    self.name.clone()
+                } else {
+                    format!("{}no-{}", &self.name[0..2], &self.name[2..])
+                }
+            }
+            FlagKind::Enum(choices) => {
+                let choice = &choices[u.choose_index(choices.len())?];
+                format!("{}={}", self.name, choice)
+            }
+            FlagKind::Numeric { min, max } => {
+                format!("{}={}", self.name, u.int_in_range(*min..=*max)?)
+            }
+        })
+    }
+}
+
+/// Flags supported by an installed compiler. Built from the compiler's own
+/// help output so new options show up without regenerating gcc.rs/llvm.rs.
+#[derive(Debug, Clone)]
+pub struct FlagCatalog {
+    pub flags: Vec<CatalogFlag>,
+}
+
+impl FlagCatalog {
+    pub fn from_compiler(compiler: &Compiler, path: &Path) -> io::Result<Self> {
+        match compiler {
+            Compiler::Gcc => Self::from_gcc(path),
+            Compiler::Llvm => Self::from_clang(path),
+            Compiler::Rustc => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "rustc does not have a flag catalog",
+            )),
+        }
+    }
+
+    /// Run `gcc -Q --help=` for optimizers, target options and params
+    pub fn from_gcc(path: &Path) -> io::Result<Self> {
+        // --help=optimizers,target only lists options that are in both classes
+        let mut help = help_output(path, &["-Q", "--help=optimizers"])?;
+        help.push_str(&help_output(path, &["-Q", "--help=target"])?);
+        help.push_str(&help_output(path, &["-Q", "--help=params"])?);
+        Ok(Self::parse_gcc_help(&help))
+    }
+
+    /// Run `clang --help-hidden`
+    pub fn from_clang(path: &Path) -> io::Result<Self> {
+        Ok(Self::parse_clang_help(&help_output(
+            path,
+            &["--help-hidden"],
+        )?))
+    }
+
+    pub fn parse_gcc_help(help: &str) -> Self {
+        let mut flags = BTreeMap::new();
+
+        // Enum values are listed separately:
+        //   Known ABIs (for use with the -mabi= option):
+        //     ilp32 ilp32d ...
+        let mut known_values: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        let lines = help.lines().collect::<Vec<_>>();
+        for (idx, line) in lines.iter().enumerate() {
+            if !line.starts_with("  ") || line.starts_with("   ") || !line.ends_with(':') {
+                continue;
+            }
+            let Some(values) = lines.get(idx + 1).filter(|l| l.starts_with("    ")) else {
+                continue;
+            };
+            for token in line.split(|c: char| c.is_whitespace() || c == '/') {
+                let opt = token.trim_matches(|c| c == '(' || c == ')' || c == ':');
+                if opt.starts_with('-') && opt.ends_with('=') {
+                    known_values.insert(
+                        opt.trim_end_matches('=').to_string(),
+                        values.split_whitespace().map(|v| v.to_string()).collect(),
+                    );
+                }
+            }
+        }
+
+        for line in help.lines() {
+            if !line.starts_with("  -") {
+                continue;
+            }
+            let mut parts = line.split_whitespace();
+            let opt = parts.next().unwrap();
+            let current_value = parts.next().unwrap_or("");
+
+            // --param=name=<min,max>. Params without a range are skipped since
+            // gcc doesn't tell us what values are valid.
+            if let Some(param) = opt.strip_prefix("--param=") {
+                if let Some((name, range)) = param.split_once("=<") {
+                    flags.insert(
+                        format!("--param={name}="),
+                        CatalogFlag {
+                            name: format!("--param={name}"),
+                            kind: parse_numeric_range(range),
+                        },
+                    );
+                }
+                continue;
+            }
+
+            if !opt.starts_with("-f") && !opt.starts_with("-m") {
+                trace!("Opt: {} not a codegen option", opt);
+                continue;
+            }
+            if current_value.starts_with('-') {
+                trace!("Opt: {} is an alias", opt);
+                continue;
+            }
+
+            let kind = match opt.split_once('=') {
+                None if current_value.starts_with('[') => FlagKind::Toggle,
+                None => {
+                    trace!("Opt: {} is not toggleable", opt);
+                    continue;
+                }
+                Some((_, arg)) if arg.starts_with('[') => FlagKind::Enum(
+                    arg.trim_matches(|c| c == '[' || c == ']')
+                        .split('|')
+                        .map(|v| v.to_string())
+                        .collect(),
+                ),
+                Some((_, arg)) if arg.starts_with('<') => parse_numeric_range(arg),
+                Some((name, _)) => {
+                    if let Some(values) = known_values.get(name) {
+                        FlagKind::Enum(values.clone())
+                    } else if current_value.parse::<i64>().is_ok() {
+                        let (min, max) = DEFAULT_NUMERIC_RANGE;
+                        FlagKind::Numeric { min, max }
+                    } else {
+                        trace!("Opt: {} takes a free-form argument", opt);
+                        continue;
+                    }
+                }
+            };
+
+            insert_flag(&mut flags, opt, kind);
+        }
+
+        FlagCatalog {
+            flags: flags.into_values().collect(),
+        }
+    }
+
+    pub fn parse_clang_help(help: &str) -> Self {
+        let mut flags = BTreeMap::new();
+        let mut enum_values: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+        let lines = help.lines().collect::<Vec<_>>();
+        for (idx, line) in lines.iter().enumerate() {
+            if !line.starts_with("  -") {
+                continue;
+            }
+            let opt = line.split_whitespace().next().unwrap();
+            let rest = line[2 + opt.len()..].trim();
+            if rest.starts_with('<') {
+                trace!("Opt: {} takes a separate argument", opt);
+                continue;
+            }
+            if CLANG_DRIVER_OPTS.contains(&opt) {
+                continue;
+            }
+
+            // Long options have their description on the next line
+            let description = match rest {
+                "" => lines.get(idx + 1).map(|l| l.trim()).unwrap_or(""),
+                description => description,
+            };
+            if description.contains(" only)") && !description.contains("RISC-V") {
+                trace!("Opt: {} is for a different arch", opt);
+                continue;
+            }
+
+            if !opt.starts_with("-f") && !opt.starts_with("-m") && !opt.starts_with("-g") {
+                trace!("Opt: {} not toggleable", opt);
+                continue;
+            }
+            if opt.contains('+') {
+                continue;
+            }
+
+            let kind = match opt.split_once('=') {
+                None if opt.contains('<') => continue,
+                None => FlagKind::Toggle,
+                Some((_, "<N>" | "<n>" | "<number>")) => {
+                    let (min, max) = DEFAULT_NUMERIC_RANGE;
+                    FlagKind::Numeric { min, max }
+                }
+                Some((_, arg)) if arg.contains('<') || arg.contains('=') => {
+                    trace!("Opt: {} takes a free-form argument", opt);
+                    continue;
+                }
+                Some((name, value)) => {
+                    // -flto=auto, -flto=jobserver, ... are listed one per line
+                    enum_values
+                        .entry(name.to_string())
+                        .or_default()
+                        .push(value.to_string());
+                    continue;
+                }
+            };
+
+            insert_flag(&mut flags, opt, kind);
+        }
+
+        for (name, values) in enum_values {
+            insert_flag(&mut flags, &name, FlagKind::Enum(values));
+        }
+
+        FlagCatalog {
+            flags: flags.into_values().collect(),
+        }
+    }
+
+    /// Up to MAX_CATALOG_FLAGS flags, picked by index so that every flag is as
+    /// likely to come up however far into the catalog it is
+    pub fn arbitrary_flags(&self, u: &mut Unstructured) -> arbitrary::Result<String> {
+        if self.flags.is_empty() {
+            return Ok(String::new());
+        }
+        // A flag picked twice keeps its last value
+        let mut flags = BTreeMap::new();
+        for _ in 0..u.int_in_range(0..=MAX_CATALOG_FLAGS)? {
+            let index = u.choose_index(self.flags.len())?;
+            flags.insert(index, self.flags[index].arbitrary_flag(u)?);
+        }
+        Ok(flags.into_values().collect::<Vec<_>>().join(" "))
+    }
+
+    /// Generate flags from a seed. Like arbitrary_flags_from_seed the seed is
+    /// truncated to the bytes that were consumed.
+    pub fn arbitrary_flags_from_seed(&self, seed: &mut Vec<u8>) -> String {
+        let mut unstructured_data = Unstructured::new(seed.as_slice());
+        let flags = self.arbitrary_flags(&mut unstructured_data).unwrap();
+
+        let consumed = seed.len() - unstructured_data.len();
+        seed.truncate(consumed);
+
+        flags
+    }
+}
+
+fn help_output(path: &Path, args: &[&str]) -> io::Result<String> {
+    let output = Command::new(path).args(args).output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "{path:?} {args:?} failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Parse gcc's <min,max> and <number> argument descriptions
+fn parse_numeric_range(arg: &str) -> FlagKind {
+    let (min, max) = arg
+        .trim_start_matches('<')
+        .trim_end_matches('>')
+        .split_once(',')
+        .and_then(|(min, max)| Some((min.parse().ok()?, max.parse().ok()?)))
+        .unwrap_or(DEFAULT_NUMERIC_RANGE);
+    FlagKind::Numeric { min, max }
+}
+
+/// The option a flag sets, the same for its no- form and any value it takes
+/// (-fno-foo, -ffoo=2 -> -ffoo; --param=bar=4 -> --param=bar)
+pub fn option_name(flag: &str) -> String {
+    if let Some(param) = flag.strip_prefix("--param=") {
+        return format!("--param={}", param.split('=').next().unwrap());
+    }
+    flag.split('=')
+        .next()
+        .unwrap()
+        .replacen("-fno-", "-f", 1)
+        .replacen("-mno-", "-m", 1)
+        .replacen("-gno-", "-g", 1)
+}
+
+fn insert_flag(flags: &mut BTreeMap<String, CatalogFlag>, opt: &str, kind: FlagKind) {
+    let name = option_name(opt);
+
+    if TARGET_SELECTION_OPTS.contains(&name.as_str()) {
+        return;
+    }
+
+    // A bare toggle and a valued form of the same option (-falign-loops and
+    // -falign-loops=) are both kept under different keys.
+    let key = match kind {
+        FlagKind::Toggle => name.clone(),
+        _ => format!("{name}="),
+    };
+    flags.entry(key).or_insert(CatalogFlag { name, kind });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flag(name: &str, kind: FlagKind) -> CatalogFlag {
+        CatalogFlag {
+            name: name.to_string(),
+            kind,
+        }
+    }
+
+    #[test]
+    fn gcc_help() {
+        let help = "\
+The following options control optimizations:
+  -faggressive-loop-optimizations 	[enabled]
+  -falign-functions=          	
+  -fschedule-insns            	[disabled]
+  -fvect-cost-model=[unlimited|dynamic|cheap|very-cheap] 	[default]
+  -fno-foo-alias              	-ffoo
+The following options are target specific:
+  -mabi=                      	lp64d
+  -mbranch-cost=N             	3
+  -mtls-dialect=              	trad
+
+  Supported TLS dialects (for use with the -mtls-dialect= option):
+    desc trad
+
+The --param option recognizes the following as parameters:
+  --param=max-unroll-times=<0,65536> 	8
+  --param=some-string=        	
+";
+        assert_eq!(
+            FlagCatalog::parse_gcc_help(help).flags,
+            vec![
+                flag(
+                    "--param=max-unroll-times",
+                    FlagKind::Numeric { min: 0, max: 65536 }
+                ),
+                flag("-faggressive-loop-optimizations", FlagKind::Toggle),
+                flag("-fschedule-insns", FlagKind::Toggle),
+                flag(
+                    "-fvect-cost-model",
+                    FlagKind::Enum(
+                        ["unlimited", "dynamic", "cheap", "very-cheap"]
+                            .map(String::from)
+                            .to_vec()
+                    )
+                ),
+                flag("-mbranch-cost", FlagKind::Numeric { min: 0, max: 256 }),
+                flag(
+                    "-mtls-dialect",
+                    FlagKind::Enum(["desc", "trad"].map(String::from).to_vec())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn clang_help() {
+        let help = "\
+OPTIONS:
+  -fmax-tokens=<n>        Max total number of preprocessed tokens
+  -fno-slp-vectorize      Disable the superword-level parallelism vectorization passes
+  -fslp-vectorize         Enable the superword-level parallelism vectorization passes
+  -fstrict-vtable-pointers
+                          Enable optimizations based on the strict rules for overwriting polymorphic C++ objects
+  -ftrivial-auto-var-init=pattern
+                          Initialize trivial automatic stack variables with a pattern
+  -ftrivial-auto-var-init=zero
+                          Initialize trivial automatic stack variables to zero
+  -flto=<value>           Set LTO mode
+  -mllvm <value>          Additional arguments to forward to LLVM's option processing
+  -mno-relax              Disable linker relaxation (RISC-V only)
+  -mrelax                 Enable linker relaxation (RISC-V only)
+  -msse4.2                Enable SSE4.2 (X86 only)
+  -o <file>               Write output to <file>
+";
+        assert_eq!(
+            FlagCatalog::parse_clang_help(help).flags,
+            vec![
+                flag("-fmax-tokens", FlagKind::Numeric { min: 0, max: 256 }),
+                flag("-fslp-vectorize", FlagKind::Toggle),
+                flag("-fstrict-vtable-pointers", FlagKind::Toggle),
+                flag(
+                    "-ftrivial-auto-var-init",
+                    FlagKind::Enum(["pattern", "zero"].map(String::from).to_vec())
+                ),
+                flag("-mrelax", FlagKind::Toggle),
+            ]
+        );
+    }
+
+    #[test]
+    fn option_names() {
+        assert_eq!(option_name("-fno-foo"), "-ffoo");
+        assert_eq!(option_name("-ffoo=2"), "-ffoo");
+        assert_eq!(option_name("-mno-relax"), "-mrelax");
+        assert_eq!(option_name("--param=bar=4"), "--param=bar");
+    }
+}
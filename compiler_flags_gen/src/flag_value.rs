@@ -0,0 +1,364 @@
+use std::fmt;
+
+use crate::{catalog::option_name, riscv::March, Compiler};
+
+/// Driver options that take their argument as the next word
+const SEPARATE_ARGUMENT_OPTS: &[&str] = &[
+    "-mllvm",
+    "-Xclang",
+    "-Xlinker",
+    "-Xassembler",
+    "-Xpreprocessor",
+    "--param",
+    "-target",
+    "-include",
+    "-imacros",
+    "-isystem",
+    "-iquote",
+    "-idirafter",
+    "-I",
+    "-D",
+    "-U",
+    "-L",
+    "-l",
+    "-x",
+    "-z",
+];
+
+/// Generated flags split into the parts that compilers spell differently.
+/// gcc and clang share a driver syntax, rustc needs its own mapping.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FlagSetValue {
+    /// -march value, e.g. rv64gcv_zba, x86-64-v3 or armv9-a+sve2
+    pub march: Option<String>,
+    /// -mabi value, e.g. lp64d
+    pub mabi: Option<String>,
+    /// -O level, e.g. 3 or s
+    pub opt_level: Option<String>,
+    /// Every other option (-f*, -m*, -g*, --param=...) in the order given. An
+    /// option that takes its argument separately is kept together with it
+    /// (-mllvm -foo, -Xclang -mbar).
+    pub toggles: Vec<String>,
+}
+
+impl FlagSetValue {
+    /// Split a flag string into its parts. Only the last -march, -mabi and -O
+    /// count so those are rendered first, the other options come back
+    /// unchanged and in the same order.
+    pub fn parse(flags: &str) -> Self {
+        let mut value = FlagSetValue::default();
+        value.push_flags(flags);
+        value
+    }
+
+    pub fn push_flags(&mut self, flags: &str) {
+        let mut tokens = flags.split_whitespace();
+        while let Some(flag) = tokens.next() {
+            if let Some(march) = flag.strip_prefix("-march=") {
+                self.march = Some(march.to_string());
+            } else if let Some(mabi) = flag.strip_prefix("-mabi=") {
+                self.mabi = Some(mabi.to_string());
+            } else if let Some(opt_level) = flag.strip_prefix("-O") {
+                self.opt_level = Some(opt_level.to_string());
+            } else if SEPARATE_ARGUMENT_OPTS.contains(&flag) {
+                match tokens.next() {
+                    Some(argument) => self.toggles.push(format!("{flag} {argument}")),
+                    None => self.toggles.push(flag.to_string()),
+                }
+            } else {
+                self.toggles.push(flag.to_string());
+            }
+        }
+    }
+
+    /// push_flags, leaving out flags for options that are already set
+    pub fn push_new_flags(&mut self, flags: &str) {
+        let set_options = self
+            .gcc_args()
+            .iter()
+            .map(|flag| option_name(flag))
+            .collect::<Vec<_>>();
+        let new_flags = flags
+            .split_whitespace()
+            .filter(|flag| !set_options.contains(&option_name(flag)))
+            .collect::<Vec<_>>();
+        self.push_flags(&new_flags.join(" "));
+    }
+
+    pub fn is_rv32(&self) -> bool {
+        self.march
+            .as_ref()
+            .is_some_and(|march| march.starts_with("rv32"))
+    }
+
+    pub fn render(&self, compiler: &Compiler) -> Vec<String> {
+        match compiler {
+            Compiler::Gcc => self.gcc_args(),
+            Compiler::Llvm => self.clang_args(),
+            Compiler::Rustc => self.rustc_args(),
+        }
+    }
+
+    pub fn gcc_args(&self) -> Vec<String> {
+        self.driver_args()
+    }
+
+    pub fn clang_args(&self) -> Vec<String> {
+        self.driver_args()
+    }
+
+    /// rustc takes target features instead of -march. Options that only exist
+    /// in the C drivers are dropped.
+    pub fn rustc_args(&self) -> Vec<String> {
+        let mut args = vec![];
+        if let Some(opt_level) = &self.opt_level {
+            let opt_level = match opt_level.as_str() {
+                "" | "g" => "1",
+                "fast" => "3",
+                opt_level => opt_level,
+            };
+            args.extend(["-C".to_string(), format!("opt-level={opt_level}")]);
+        }
+
+        if let Some(march) = &self.march {
+            if let Some(target) = self.rustc_target() {
+                args.extend(["--target".to_string(), target.to_string()]);
+            }
+            if self.is_x86() {
+                args.extend(["-C".to_string(), format!("target-cpu={march}")]);
+            }
+            let features = self.llvm_target_features();
+            if !features.is_empty() {
+                args.extend([
+                    "-C".to_string(),
+                    format!("target-feature={}", features.join(",")),
+                ]);
+            }
+        }
+
+        for toggle in &self.toggles {
+            if let Some(opt) = toggle.strip_prefix("-mllvm ") {
+                args.extend(["-C".to_string(), format!("llvm-args={opt}")]);
+            } else if !toggle.starts_with("-f")
+                && !toggle.starts_with("-m")
+                && !toggle.starts_with("-g")
+                && !toggle.starts_with("--param")
+            {
+                args.extend(toggle.split_whitespace().map(|arg| arg.to_string()));
+            }
+        }
+
+        args
+    }
+
+    /// The rustc target triple matching -march
+    pub fn rustc_target(&self) -> Option<&'static str> {
+        let march = self.march.as_ref()?;
+        Some(if march.starts_with("rv32") {
+            "riscv32gc-unknown-linux-gnu"
+        } else if march.starts_with("rv64") {
+            "riscv64gc-unknown-linux-gnu"
+        } else if march.starts_with("arm") {
+            "aarch64-unknown-linux-gnu"
+        } else {
+            "x86_64-unknown-linux-gnu"
+        })
+    }
+
+    /// llc takes the target as a triple plus -mattr features. The driver's
+    /// -O levels are clamped to the 0-3 llc understands and -mllvm options are
+    /// passed through directly.
+    pub fn llc_args(&self) -> Vec<String> {
+        let mut args = vec![];
+        if let Some(triple) = self.llvm_triple() {
+            args.push(format!("-mtriple={triple}"));
+        }
+        if let (true, Some(march)) = (self.is_x86(), &self.march) {
+            args.push(format!("-mcpu={march}"));
+        }
+        let features = self.llvm_target_features();
+        if !features.is_empty() {
+            args.push(format!("-mattr={}", features.join(",")));
+        }
+        if let Some(mabi) = &self.mabi {
+            args.push(format!("-target-abi={mabi}"));
+        }
+        if let Some(opt_level) = &self.opt_level {
+            let opt_level = match opt_level.as_str() {
+                "" | "g" => "1",
+                "s" | "z" => "2",
+                "fast" => "3",
+                opt_level => opt_level,
+            };
+            args.push(format!("-O{opt_level}"));
+        }
+        args.extend(
+            self.toggles
+                .iter()
+                .filter_map(|toggle| toggle.strip_prefix("-mllvm "))
+                .map(|opt| opt.to_string()),
+        );
+        args
+    }
+
+    /// The LLVM target triple matching -march
+    pub fn llvm_triple(&self) -> Option<&'static str> {
+        let march = self.march.as_ref()?;
+        Some(if march.starts_with("rv32") {
+            "riscv32-unknown-linux-gnu"
+        } else if march.starts_with("rv64") {
+            "riscv64-unknown-linux-gnu"
+        } else if march.starts_with("arm") {
+            "aarch64-unknown-linux-gnu"
+        } else {
+            "x86_64-unknown-linux-gnu"
+        })
+    }
+
+    /// x86 -march names a cpu rather than an ISA string
+    fn is_x86(&self) -> bool {
+        self.march
+            .as_ref()
+            .is_some_and(|march| !march.starts_with("rv") && !march.starts_with("arm"))
+    }
+
+    /// LLVM target features for -march. On x86 the ISA toggles are features.
+    fn llvm_target_features(&self) -> Vec<String> {
+        let Some(march) = &self.march else {
+            return vec![];
+        };
+        let mut features = march_target_features(march);
+        if self.is_x86() {
+            features.extend(self.toggles.iter().filter_map(|toggle| {
+                let feature = toggle.strip_prefix("-m")?;
+                if feature.contains('=') || feature.contains(' ') {
+                    None
+                } else if let Some(feature) = feature.strip_prefix("no-") {
+                    Some(format!("-{feature}"))
+                } else {
+                    Some(format!("+{feature}"))
+                }
+            }));
+        }
+        features
+    }
+
+    fn driver_args(&self) -> Vec<String> {
+        let mut args = vec![];
+        if let Some(march) = &self.march {
+            args.push(format!("-march={march}"));
+        }
+        if let Some(mabi) = &self.mabi {
+            args.push(format!("-mabi={mabi}"));
+        }
+        if let Some(opt_level) = &self.opt_level {
+            args.push(format!("-O{opt_level}"));
+        }
+        for toggle in &self.toggles {
+            args.extend(toggle.split_whitespace().map(|t| t.to_string()));
+        }
+        args
+    }
+}
+
+impl From<&March> for FlagSetValue {
+    fn from(march: &March) -> Self {
+        FlagSetValue {
+            march: Some(march.isa_string()),
+            mabi: Some(march.abi_string().to_string()),
+            ..Default::default()
+        }
+    }
+}
+
+/// gcc syntax, which is also what gets written to compiler_opts.txt
+impl fmt::Display for FlagSetValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.gcc_args().join(" "))
+    }
+}
+
+/// Map an -march string to LLVM target features
+///   rv64gcv_zba -> +m,+a,+f,+d,+c,+v,+zba
+///   armv8.2-a+sve -> +v8.2a,+sve
+fn march_target_features(march: &str) -> Vec<String> {
+    if let Some(isa) = march
+        .strip_prefix("rv32")
+        .or_else(|| march.strip_prefix("rv64"))
+    {
+        let mut extensions = isa.split('_').filter(|ext| !ext.is_empty());
+        let mut features = vec![];
+        if !isa.starts_with('_') {
+            for ext in extensions.next().unwrap_or("").chars() {
+                match ext {
+                    'i' => {}
+                    'g' => features.extend(["+m", "+a", "+f", "+d"].map(|f| f.to_string())),
+                    ext => features.push(format!("+{ext}")),
+                }
+            }
+        }
+        features.extend(extensions.map(|ext| format!("+{ext}")));
+        features
+    } else if let Some(arch) = march.strip_prefix("arm") {
+        let mut parts = arch.split('+');
+        let mut features = vec![];
+        // armv8-a is the baseline
+        match parts.next().map(|arch| arch.replace("-a", "a")) {
+            Some(arch) if arch != "v8a" => features.push(format!("+{arch}")),
+            _ => {}
+        }
+        features.extend(parts.map(|ext| match ext {
+            "rdma" => "+rdm".to_string(),
+            "crypto" => "+aes,+sha2".to_string(),
+            ext => format!("+{ext}"),
+        }));
+        features
+    } else {
+        vec![]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_keeps_order() {
+        let flags =
+            "-march=rv64gc -O2 -mllvm -foo -fno-bar -O3 -march=rv64gcv -mabi=lp64d --param=x=1";
+        let value = FlagSetValue::parse(flags);
+        assert_eq!(
+            value,
+            FlagSetValue {
+                march: Some("rv64gcv".to_string()),
+                mabi: Some("lp64d".to_string()),
+                opt_level: Some("3".to_string()),
+                toggles: ["-mllvm -foo", "-fno-bar", "--param=x=1"]
+                    .map(String::from)
+                    .to_vec(),
+            }
+        );
+        assert_eq!(
+            value.to_string(),
+            "-march=rv64gcv -mabi=lp64d -O3 -mllvm -foo -fno-bar --param=x=1"
+        );
+    }
+
+    #[test]
+    fn parse_round_trips() {
+        let flags = "-march=rv64gcv_zba -mabi=lp64d -O2 -Xclang -disable-llvm-passes -fno-unroll-loops -I include";
+        assert_eq!(FlagSetValue::parse(flags).to_string(), flags);
+    }
+
+    #[test]
+    fn parse_separate_argument_at_end() {
+        assert_eq!(FlagSetValue::parse("-O1 -mllvm").toggles, vec!["-mllvm"]);
+    }
+
+    #[test]
+    fn push_new_flags_skips_set_options() {
+        let mut value = FlagSetValue::parse("-O2 -fno-foo --param=bar=1");
+        value.push_new_flags("-ffoo --param=bar=2 -fbaz");
+        assert_eq!(value.to_string(), "-O2 -fno-foo --param=bar=1 -fbaz");
+    }
+}
@@ -2,7 +2,7 @@ use arbitrary::Arbitrary;
 use std::fmt;
 use struct_iterable::Iterable;
 
-use crate::{Action, GhostOpt, ToggleOpt};
+use crate::{Action, GhostOpt, IntOpt, ToggleOpt};
 
 #[derive(Arbitrary, Debug, Iterable, Clone)]
 pub struct BasicGccFlags {
@@ -73,18 +73,26 @@ impl fmt::Display for BasicGccToggles {
 pub struct AllGccFlags {
     pub toggles: AllGccToggles,
     pub riscv_toggles: GccRiscvToggles,
+    pub valued: GccValuedFlags,
+    pub params: GccParams,
 }
 
 impl AllGccFlags {
     pub fn sanitize(&mut self, action: &Action) {
         self.toggles.sanitize(action);
         self.riscv_toggles.sanitize(action);
+        self.valued.sanitize(action);
+        self.params.sanitize(action);
     }
 }
 
 impl fmt::Display for AllGccFlags {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{} {}", self.toggles, self.riscv_toggles)
+        write!(
+            f,
+            "{} {} {} {}",
+            self.toggles, self.riscv_toggles, self.valued, self.params
+        )
     }
 }
 
@@ -434,6 +442,350 @@ impl fmt::Display for AllGccToggles {
     }
 }
 
+/// Options that take a value
+#[allow(non_camel_case_types)]
+#[derive(Arbitrary, Debug, Iterable, Clone)]
+pub struct GccValuedFlags {
+    falign_functions: IntOpt,
+    falign_jumps: IntOpt,
+    falign_labels: IntOpt,
+    falign_loops: IntOpt,
+    fexcess_precision: Option<ExcessPrecision>,
+    ffp_contract: Option<FpContract>,
+    finline_limit: IntOpt,
+    fira_algorithm: Option<IraAlgorithm>,
+    fira_region: Option<IraRegion>,
+    flifetime_dse: IntOpt,
+    freorder_blocks_algorithm: Option<ReorderBlocksAlgorithm>,
+    fsched_stalled_insns: IntOpt,
+    fsched_stalled_insns_dep: IntOpt,
+    fsimd_cost_model: Option<CostModel>,
+    fstack_reuse: Option<StackReuse>,
+    ftree_parallelize_loops: IntOpt,
+    ftrivial_auto_var_init: Option<TrivialAutoVarInit>,
+    fvect_cost_model: Option<CostModel>,
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Arbitrary, Debug, Clone, Copy)]
+pub enum ExcessPrecision {
+    fast,
+    standard,
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Arbitrary, Debug, Clone, Copy)]
+pub enum FpContract {
+    off,
+    on,
+    fast,
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Arbitrary, Debug, Clone, Copy)]
+pub enum IraAlgorithm {
+    CB,
+    priority,
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Arbitrary, Debug, Clone, Copy)]
+pub enum IraRegion {
+    one,
+    all,
+    mixed,
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Arbitrary, Debug, Clone, Copy)]
+pub enum ReorderBlocksAlgorithm {
+    simple,
+    stc,
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Arbitrary, Debug, Clone, Copy)]
+pub enum CostModel {
+    unlimited,
+    dynamic,
+    cheap,
+    very_cheap,
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Arbitrary, Debug, Clone, Copy)]
+pub enum StackReuse {
+    all,
+    named_vars,
+    none,
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Arbitrary, Debug, Clone, Copy)]
+pub enum TrivialAutoVarInit {
+    uninitialized,
+    pattern,
+    zero,
+}
+
+impl GccValuedFlags {
+    pub fn sanitize(&mut self, action: &Action) {
+        self.falign_functions.bound(0, 65536);
+        self.falign_jumps.bound(0, 65536);
+        self.falign_labels.bound(0, 65536);
+        self.falign_loops.bound(0, 65536);
+        self.finline_limit.bound(0, 1000);
+        self.flifetime_dse.bound(0, 2);
+        self.fsched_stalled_insns.bound(0, 64);
+        self.fsched_stalled_insns_dep.bound(0, 64);
+        self.ftree_parallelize_loops.bound(1, 64);
+
+        // Parallelized loops call into libgomp
+        if matches!(action, Action::Link) || matches!(action, Action::Execute) {
+            self.ftree_parallelize_loops = IntOpt(None);
+        }
+    }
+}
+
+impl fmt::Display for GccValuedFlags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let flags = self
+            .iter()
+            .map(|(field_name, field_value)| {
+                let arg_name = field_name.replace('_', "-");
+                let value = if field_value.is::<IntOpt>() {
+                    let value: IntOpt = *field_value.downcast_ref().unwrap();
+                    value.0.map(|value| value.to_string())
+                } else if field_value.is::<Option<ExcessPrecision>>() {
+                    let value: Option<ExcessPrecision> = *field_value.downcast_ref().unwrap();
+                    value.map(|value| format!("{:?}", value))
+                } else if field_value.is::<Option<FpContract>>() {
+                    let value: Option<FpContract> = *field_value.downcast_ref().unwrap();
+                    value.map(|value| format!("{:?}", value))
+                } else if field_value.is::<Option<IraAlgorithm>>() {
+                    let value: Option<IraAlgorithm> = *field_value.downcast_ref().unwrap();
+                    value.map(|value| format!("{:?}", value))
+                } else if field_value.is::<Option<IraRegion>>() {
+                    let value: Option<IraRegion> = *field_value.downcast_ref().unwrap();
+                    value.map(|value| format!("{:?}", value))
+                } else if field_value.is::<Option<ReorderBlocksAlgorithm>>() {
+                    let value: Option<ReorderBlocksAlgorithm> =
+                        *field_value.downcast_ref().unwrap();
+                    value.map(|value| format!("{:?}", value))
+                } else if field_value.is::<Option<CostModel>>() {
+                    let value: Option<CostModel> = *field_value.downcast_ref().unwrap();
+                    value.map(|value| format!("{:?}", value).replace('_', "-"))
+                } else if field_value.is::<Option<StackReuse>>() {
+                    let value: Option<StackReuse> = *field_value.downcast_ref().unwrap();
+                    value.map(|value| format!("{:?}", value))
+                } else if field_value.is::<Option<TrivialAutoVarInit>>() {
+                    let value: Option<TrivialAutoVarInit> = *field_value.downcast_ref().unwrap();
+                    value.map(|value| format!("{:?}", value))
+                } else {
+                    panic!("Unknown datatype for field: {}", field_name)
+                };
+
+                match value {
+                    Some(value) => format!("-{arg_name}={value}"),
+                    None => "".to_string(),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        write!(f, "{}", flags.join(" "))
+    }
+}
+
+/// --param name=value. Ranges are from `gcc -Q --help=params`, see
+/// parse_gcc::parse_gcc_params.
+#[allow(non_camel_case_types)]
+#[derive(Arbitrary, Debug, Iterable, Clone)]
+pub struct GccParams {
+    align_threshold: IntOpt,
+    avg_loop_niter: IntOpt,
+    // avoid_fma_max_bits: IntOpt,
+    builtin_expect_probability: IntOpt,
+    builtin_string_cmp_inline_length: IntOpt,
+    fsm_scale_path_blocks: IntOpt,
+    fsm_scale_path_stmts: IntOpt,
+    // graphite_allow_codegen_errors: IntOpt,
+    hot_bb_count_ws_permille: IntOpt,
+    inline_heuristics_hint_percent: IntOpt,
+    inline_min_speedup: IntOpt,
+    integer_share_limit: IntOpt,
+    ipa_cp_profile_count_base: IntOpt,
+    ipa_cp_recursion_penalty: IntOpt,
+    ipa_cp_single_call_penalty: IntOpt,
+    ipa_sra_max_replacements: IntOpt,
+    ira_consider_dup_in_all_alts: IntOpt,
+    logical_op_non_short_circuit: IntOpt,
+    lra_inheritance_ebb_probability_cutoff: IntOpt,
+    max_combine_insns: IntOpt,
+    max_cse_path_length: IntOpt,
+    max_fsm_thread_length: IntOpt,
+    max_fsm_thread_path_insns: IntOpt,
+    max_pow_sqrt_depth: IntOpt,
+    max_predicted_iterations: IntOpt,
+    max_rtl_if_conversion_insns: IntOpt,
+    max_rtl_if_conversion_predictable_cost: IntOpt,
+    max_rtl_if_conversion_unpredictable_cost: IntOpt,
+    max_sched_insn_conflict_delay: IntOpt,
+    max_sched_ready_insns: IntOpt,
+    max_slsr_cand_scan: IntOpt,
+    max_ssa_name_query_depth: IntOpt,
+    max_store_chains_to_track: IntOpt,
+    max_stores_to_merge: IntOpt,
+    max_stores_to_track: IntOpt,
+    max_tree_if_conversion_phi_args: IntOpt,
+    min_crossjump_insns: IntOpt,
+    min_loop_cond_split_prob: IntOpt,
+    modref_max_adjustments: IntOpt,
+    modref_max_depth: IntOpt,
+    parloops_min_per_thread: IntOpt,
+    partial_inlining_entry_probability: IntOpt,
+    predictable_branch_outcome: IntOpt,
+    prefetch_dynamic_strides: IntOpt,
+    // profile_func_internal_id: IntOpt,
+    ranger_logical_depth: IntOpt,
+    relation_block_limit: IntOpt,
+    rpo_vn_max_loop_depth: IntOpt,
+    sched_pressure_algorithm: IntOpt,
+    sched_spec_prob_cutoff: IntOpt,
+    sched_state_edge_prob_cutoff: IntOpt,
+    selsched_max_sched_times: IntOpt,
+    sink_frequency_threshold: IntOpt,
+    sms_dfa_history: IntOpt,
+    sms_max_ii_factor: IntOpt,
+    sms_min_sc: IntOpt,
+    ssp_buffer_size: IntOpt,
+    stack_clash_protection_guard_size: IntOpt,
+    stack_clash_protection_probe_interval: IntOpt,
+    store_merging_allow_unaligned: IntOpt,
+    store_merging_max_size: IntOpt,
+    switch_conversion_max_branch_ratio: IntOpt,
+    tracer_dynamic_coverage: IntOpt,
+    tracer_dynamic_coverage_feedback: IntOpt,
+    tracer_min_branch_probability: IntOpt,
+    tracer_min_branch_probability_feedback: IntOpt,
+    tracer_min_branch_ratio: IntOpt,
+    uninit_control_dep_attempts: IntOpt,
+    uninlined_function_insns: IntOpt,
+    uninlined_function_time: IntOpt,
+    uninlined_thunk_insns: IntOpt,
+    uninlined_thunk_time: IntOpt,
+    unroll_jam_min_percent: IntOpt,
+    // use_canonical_types: IntOpt,
+    vect_epilogues_nomask: IntOpt,
+    vect_inner_loop_cost_factor: IntOpt,
+    vect_max_peeling_for_alignment: IntOpt,
+    vect_partial_vector_usage: IntOpt,
+}
+
+impl GccParams {
+    pub fn sanitize(&mut self, _action: &Action) {
+        self.align_threshold.bound(1, 65536);
+        self.avg_loop_niter.bound(1, 65536);
+        // self.avoid_fma_max_bits.bound(0, 512);
+        self.builtin_expect_probability.bound(0, 100);
+        self.builtin_string_cmp_inline_length.bound(0, 100);
+        self.fsm_scale_path_blocks.bound(1, 10);
+        self.fsm_scale_path_stmts.bound(1, 10);
+        // self.graphite_allow_codegen_errors.bound(0, 1);
+        self.hot_bb_count_ws_permille.bound(0, 1000);
+        self.inline_heuristics_hint_percent.bound(100, 1000000);
+        self.inline_min_speedup.bound(0, 100);
+        self.integer_share_limit.bound(2, 65536);
+        self.ipa_cp_profile_count_base.bound(0, 100);
+        self.ipa_cp_recursion_penalty.bound(0, 100);
+        self.ipa_cp_single_call_penalty.bound(0, 100);
+        self.ipa_sra_max_replacements.bound(0, 16);
+        self.ira_consider_dup_in_all_alts.bound(0, 1);
+        self.logical_op_non_short_circuit.bound(0, 1);
+        self.lra_inheritance_ebb_probability_cutoff.bound(0, 100);
+        self.max_combine_insns.bound(2, 4);
+        self.max_cse_path_length.bound(1, 65536);
+        self.max_fsm_thread_length.bound(1, 999999);
+        self.max_fsm_thread_path_insns.bound(1, 999999);
+        self.max_pow_sqrt_depth.bound(1, 32);
+        self.max_predicted_iterations.bound(0, 65536);
+        self.max_rtl_if_conversion_insns.bound(0, 99);
+        self.max_rtl_if_conversion_predictable_cost.bound(0, 200);
+        self.max_rtl_if_conversion_unpredictable_cost.bound(0, 200);
+        self.max_sched_insn_conflict_delay.bound(1, 10);
+        self.max_sched_ready_insns.bound(1, 65536);
+        self.max_slsr_cand_scan.bound(1, 999999);
+        self.max_ssa_name_query_depth.bound(1, 10);
+        self.max_store_chains_to_track.bound(1, 65536);
+        self.max_stores_to_merge.bound(2, 65536);
+        self.max_stores_to_track.bound(2, 1048576);
+        self.max_tree_if_conversion_phi_args.bound(2, 65536);
+        self.min_crossjump_insns.bound(1, 65536);
+        self.min_loop_cond_split_prob.bound(0, 100);
+        self.modref_max_adjustments.bound(0, 254);
+        self.modref_max_depth.bound(1, 65536);
+        self.parloops_min_per_thread.bound(2, 65536);
+        self.partial_inlining_entry_probability.bound(0, 100);
+        self.predictable_branch_outcome.bound(0, 50);
+        self.prefetch_dynamic_strides.bound(0, 1);
+        // self.profile_func_internal_id.bound(0, 1);
+        self.ranger_logical_depth.bound(1, 999);
+        self.relation_block_limit.bound(0, 9999);
+        self.rpo_vn_max_loop_depth.bound(2, 65536);
+        self.sched_pressure_algorithm.bound(1, 2);
+        self.sched_spec_prob_cutoff.bound(0, 100);
+        self.sched_state_edge_prob_cutoff.bound(0, 100);
+        self.selsched_max_sched_times.bound(1, 65536);
+        self.sink_frequency_threshold.bound(0, 100);
+        self.sms_dfa_history.bound(0, 16);
+        self.sms_max_ii_factor.bound(1, 16);
+        self.sms_min_sc.bound(1, 2);
+        self.ssp_buffer_size.bound(1, 65536);
+        self.stack_clash_protection_guard_size.bound(12, 30);
+        self.stack_clash_protection_probe_interval.bound(10, 16);
+        self.store_merging_allow_unaligned.bound(0, 1);
+        self.store_merging_max_size.bound(1, 65536);
+        self.switch_conversion_max_branch_ratio.bound(1, 65536);
+        self.tracer_dynamic_coverage.bound(0, 100);
+        self.tracer_dynamic_coverage_feedback.bound(0, 100);
+        self.tracer_min_branch_probability.bound(0, 100);
+        self.tracer_min_branch_probability_feedback.bound(0, 100);
+        self.tracer_min_branch_ratio.bound(0, 100);
+        self.uninit_control_dep_attempts.bound(1, 65536);
+        self.uninlined_function_insns.bound(0, 1000000);
+        self.uninlined_function_time.bound(0, 1000000);
+        self.uninlined_thunk_insns.bound(0, 1000000);
+        self.uninlined_thunk_time.bound(0, 1000000);
+        self.unroll_jam_min_percent.bound(0, 100);
+        // self.use_canonical_types.bound(0, 1);
+        self.vect_epilogues_nomask.bound(0, 1);
+        self.vect_inner_loop_cost_factor.bound(1, 10000);
+        self.vect_max_peeling_for_alignment.bound(0, 64);
+        self.vect_partial_vector_usage.bound(0, 2);
+    }
+}
+
+impl fmt::Display for GccParams {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let flags = self
+            .iter()
+            .map(|(field_name, field_value)| {
+                if field_value.is::<IntOpt>() {
+                    let value: IntOpt = *field_value.downcast_ref().unwrap();
+                    let param_name = field_name.replace('_', "-");
+                    match value.0 {
+                        Some(value) => format!("--param={param_name}={value}"),
+                        None => "".to_string(),
+                    }
+                } else {
+                    panic!("Unknown datatype for field: {}", field_name)
+                }
+            })
+            .collect::<Vec<_>>();
+
+        write!(f, "{}", flags.join(" "))
+    }
+}
+
 #[allow(non_camel_case_types)]
 #[derive(Arbitrary, Debug, Iterable, Clone)]
 pub struct GccRiscvToggles {
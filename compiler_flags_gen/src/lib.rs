@@ -2,19 +2,26 @@ use std::fmt;
 
 use arbitrary::{Arbitrary, Unstructured};
 use llvm::BasicLlvmFlags;
-use rand::RngCore;
+use rand::{Rng, RngCore};
 use riscv::{Mabi, March};
 use serde::{Deserialize, Serialize};
 use struct_iterable::Iterable;
 
+use crate::aarch64::Aarch64March;
+use crate::flag_value::FlagSetValue;
 use crate::gcc::{AllGccFlags, BasicGccFlags};
 use crate::llvm::AllLlvmFlags;
+use crate::x86::X86March;
 
+pub mod aarch64;
+pub mod catalog;
+pub mod flag_value;
 pub mod gcc;
 pub mod llvm;
 pub mod parse_gcc;
 pub mod parse_llvm;
 pub mod riscv;
+pub mod x86;
 
 #[derive(Arbitrary, Debug, Iterable, Clone)]
 pub struct AllFuzzGcc {
@@ -262,6 +269,24 @@ pub enum GhostOpt {
     On,
 }
 
+/// Integer valued option. Arbitrary picks any value and the owning struct's
+/// sanitize bounds it to the range the compiler accepts.
+#[derive(Arbitrary, Debug, Clone, PartialEq, Copy)]
+pub struct IntOpt(pub Option<u32>);
+
+impl IntOpt {
+    pub fn bound(&mut self, min: u32, max: u32) {
+        let range = max as u64 - min as u64 + 1;
+        self.0 = self
+            .0
+            .map(|value| (min as u64 + value as u64 % range) as u32);
+    }
+
+    pub fn bound_power_of_two(&mut self, max_log2: u32) {
+        self.0 = self.0.map(|value| 1 << (value % (max_log2 + 1)));
+    }
+}
+
 #[derive(PartialEq, Deserialize, Serialize, Clone, Debug)]
 pub enum Action {
     Compile,
@@ -277,6 +302,13 @@ pub enum Compiler {
     Rustc,
 }
 
+#[derive(PartialEq, Deserialize, Serialize, Clone, Debug)]
+pub enum Target {
+    Riscv,
+    X86_64,
+    Aarch64,
+}
+
 #[derive(PartialEq, Deserialize, Debug, Clone)]
 pub enum FlagSet {
     MarchAndAllFlags,
@@ -292,10 +324,24 @@ pub fn arbitrary_flags_compatible(
     flag_set: &FlagSet,
     rv64_only: bool,
     count: usize,
-) -> Vec<String> {
+) -> Vec<FlagSetValue> {
+    let mut seed = random_flag_seed();
+    arbitrary_flags_compatible_from_seed(compiler, action, flag_set, rv64_only, count, &mut seed)
+}
+
+pub fn arbitrary_flags_compatible_from_seed(
+    compiler: &Compiler,
+    action: &Action,
+    flag_set: &FlagSet,
+    rv64_only: bool,
+    count: usize,
+    seed: &mut Vec<u8>,
+) -> Vec<FlagSetValue> {
     if count == 1 {
         // No need to worry about ABIs
-        return vec![arbitrary_flags(compiler, action, flag_set, false, None)];
+        return vec![arbitrary_flags_from_seed(
+            compiler, action, flag_set, false, None, seed,
+        )];
     }
 
     // TODO: Generate compatible but different compiler flags
@@ -338,7 +384,7 @@ pub fn arbitrary_flags_compatible(
     //         .map(|_| arbitrary_flags(compiler, action, flag_set, rv64_only, Some(mabi)))
     //         .collect();
 
-    let flags = arbitrary_flags(compiler, action, flag_set, rv64_only, None);
+    let flags = arbitrary_flags_from_seed(compiler, action, flag_set, rv64_only, None, seed);
 
     let temp: Vec<_> = (0..count).map(|_| flags.clone()).collect();
 
@@ -353,64 +399,208 @@ pub fn arbitrary_flags(
     flag_set: &FlagSet,
     rv64_only: bool,
     mabi: Option<Mabi>,
-) -> String {
-    let mut random_bytes = [0u8; 4096];
+) -> FlagSetValue {
+    let mut seed = random_flag_seed();
+    arbitrary_flags_from_seed(compiler, action, flag_set, rv64_only, mabi, &mut seed)
+}
+
+/// Random bytes that the flag structs are built from. The same seed always
+/// produces the same flags, so a seed can be stored and mutated later.
+pub fn random_flag_seed() -> Vec<u8> {
+    let mut random_bytes = vec![0u8; 4096];
     rand::thread_rng().fill_bytes(&mut random_bytes);
-    let mut unstructured_data = Unstructured::new(random_bytes.as_slice());
+    random_bytes
+}
+
+/// Change a single byte of the seed. Each field consumes its own bytes so this
+/// flips roughly one toggle/extension at a time.
+pub fn mutate_flag_seed(seed: &[u8]) -> Vec<u8> {
+    let mut rng = rand::thread_rng();
+    let mut mutated = seed.to_vec();
+    if mutated.is_empty() || rng.gen_ratio(1, 16) {
+        // Extend the seed so fields past the end stop defaulting to zero
+        mutated.push(rng.gen());
+    } else {
+        let idx = rng.gen_range(0..mutated.len());
+        mutated[idx] = rng.gen();
+    }
+    mutated
+}
+
+/// Generate flags from a seed. The seed is truncated to the bytes that were
+/// actually consumed so mutations always land on a field.
+pub fn arbitrary_flags_from_seed(
+    compiler: &Compiler,
+    action: &Action,
+    flag_set: &FlagSet,
+    rv64_only: bool,
+    mabi: Option<Mabi>,
+    seed: &mut Vec<u8>,
+) -> FlagSetValue {
+    let mut unstructured_data = Unstructured::new(seed.as_slice());
 
-    match flag_set {
+    let flags = match flag_set {
         FlagSet::MarchAndAllFlags => match compiler {
             Compiler::Gcc => {
                 let mut flags = AllFuzzGcc::arbitrary(&mut unstructured_data).unwrap();
                 flags.sanitize(action, flag_set, rv64_only, mabi);
-                flags.to_string()
+                let mut value = FlagSetValue::from(&flags.march);
+                value.push_flags(&flags.flags.to_string());
+                value
             }
             Compiler::Llvm | Compiler::Rustc => {
                 let mut flags = AllFuzzLlvm::arbitrary(&mut unstructured_data).unwrap();
                 flags.sanitize(action, flag_set, rv64_only, mabi);
-                flags.to_string()
+                let mut value = FlagSetValue::from(&flags.march);
+                value.push_flags(&flags.flags.to_string());
+                value
             }
         },
         FlagSet::MarchAndBasicFlags => match compiler {
             Compiler::Gcc => {
                 let mut flags = BasicFuzzGcc::arbitrary(&mut unstructured_data).unwrap();
                 flags.sanitize(action, flag_set, rv64_only, mabi);
-                flags.to_string()
+                let mut value = FlagSetValue::from(&flags.march);
+                value.push_flags(&flags.flags.to_string());
+                value
             }
             Compiler::Llvm | Compiler::Rustc => {
                 let mut flags = BasicFuzzLlvm::arbitrary(&mut unstructured_data).unwrap();
                 flags.sanitize(action, flag_set, rv64_only, mabi);
-                flags.to_string()
+                let mut value = FlagSetValue::from(&flags.march);
+                value.push_flags(&flags.flags.to_string());
+                value
             }
         },
         FlagSet::AllFlags => match compiler {
             Compiler::Gcc => {
                 let mut flags = AllGccFlags::arbitrary(&mut unstructured_data).unwrap();
                 flags.sanitize(action);
-                flags.to_string()
+                FlagSetValue::parse(&flags.to_string())
             }
             Compiler::Llvm | Compiler::Rustc => {
                 let mut flags = AllLlvmFlags::arbitrary(&mut unstructured_data).unwrap();
                 flags.sanitize(action);
-                flags.to_string()
+                FlagSetValue::parse(&flags.to_string())
             }
         },
         FlagSet::BasicFlags => match compiler {
             Compiler::Gcc => {
                 let mut flags = BasicGccFlags::arbitrary(&mut unstructured_data).unwrap();
                 flags.sanitize(action);
-                flags.to_string()
+                FlagSetValue::parse(&flags.to_string())
             }
             Compiler::Llvm | Compiler::Rustc => {
                 let mut flags = BasicLlvmFlags::arbitrary(&mut unstructured_data).unwrap();
                 flags.sanitize(action);
-                flags.to_string()
+                FlagSetValue::parse(&flags.to_string())
             }
         },
         FlagSet::March => {
             let mut flags = riscv::March::arbitrary(&mut unstructured_data).unwrap();
             flags.sanitize(compiler, action, flag_set, rv64_only, mabi);
-            flags.to_string()
+            FlagSetValue::from(&flags)
+        }
+    };
+
+    let consumed = seed.len() - unstructured_data.len();
+    seed.truncate(consumed);
+
+    flags
+}
+
+pub fn arbitrary_target_flags(
+    target: &Target,
+    compiler: &Compiler,
+    action: &Action,
+    flag_set: &FlagSet,
+) -> FlagSetValue {
+    let mut seed = random_flag_seed();
+    arbitrary_target_flags_from_seed(target, compiler, action, flag_set, &mut seed)
+}
+
+/// Generate flags for any target. RISC-V uses the riscv::March based flag
+/// structs, other targets pair their own -march flags with the target
+/// independent toggles.
+pub fn arbitrary_target_flags_from_seed(
+    target: &Target,
+    compiler: &Compiler,
+    action: &Action,
+    flag_set: &FlagSet,
+    seed: &mut Vec<u8>,
+) -> FlagSetValue {
+    if *target == Target::Riscv {
+        return arbitrary_flags_from_seed(compiler, action, flag_set, false, None, seed);
+    }
+
+    let mut unstructured_data = Unstructured::new(seed.as_slice());
+
+    let mut value = match flag_set {
+        FlagSet::March | FlagSet::MarchAndAllFlags | FlagSet::MarchAndBasicFlags => match target {
+            Target::X86_64 => {
+                let mut march = X86March::arbitrary(&mut unstructured_data).unwrap();
+                march.sanitize(action);
+                FlagSetValue::parse(&march.to_string())
+            }
+            Target::Aarch64 => {
+                let mut march = Aarch64March::arbitrary(&mut unstructured_data).unwrap();
+                march.sanitize(action);
+                FlagSetValue::parse(&march.to_string())
+            }
+            Target::Riscv => unreachable!(),
+        },
+        FlagSet::AllFlags | FlagSet::BasicFlags => FlagSetValue::default(),
+    };
+
+    let flags = match flag_set {
+        FlagSet::MarchAndAllFlags | FlagSet::AllFlags => match compiler {
+            Compiler::Gcc => {
+                let mut flags = AllGccFlags::arbitrary(&mut unstructured_data).unwrap();
+                flags.sanitize(action);
+                format!("{} {} {}", flags.toggles, flags.valued, flags.params)
+            }
+            Compiler::Llvm | Compiler::Rustc => {
+                let mut flags = AllLlvmFlags::arbitrary(&mut unstructured_data).unwrap();
+                flags.sanitize(action);
+                strip_riscv_only_flags(&flags.to_string())
+            }
+        },
+        FlagSet::MarchAndBasicFlags | FlagSet::BasicFlags => match compiler {
+            Compiler::Gcc => {
+                let mut flags = BasicGccFlags::arbitrary(&mut unstructured_data).unwrap();
+                flags.sanitize(action);
+                flags.toggles.to_string()
+            }
+            Compiler::Llvm | Compiler::Rustc => {
+                let mut flags = BasicLlvmFlags::arbitrary(&mut unstructured_data).unwrap();
+                flags.sanitize(action);
+                strip_riscv_only_flags(&flags.toggles.to_string())
+            }
+        },
+        FlagSet::March => "".to_string(),
+    };
+
+    let consumed = seed.len() - unstructured_data.len();
+    seed.truncate(consumed);
+
+    value.push_flags(&flags);
+    value
+}
+
+/// The llvm flag structs were generated for RISC-V and include a few options
+/// that other targets reject.
+fn strip_riscv_only_flags(flags: &str) -> String {
+    let mut kept = vec![];
+    let mut tokens = flags.split_whitespace().peekable();
+    while let Some(flag) = tokens.next() {
+        if flag == "-mllvm" && tokens.peek().is_some_and(|opt| opt.starts_with("-riscv-")) {
+            tokens.next();
+            continue;
+        }
+        if flag.starts_with("-ffixed-x") || flag.ends_with("save-restore") {
+            continue;
         }
+        kept.push(flag);
     }
+    kept.join(" ")
 }
@@ -2,7 +2,7 @@ use arbitrary::Arbitrary;
 use std::fmt;
 use struct_iterable::Iterable;
 
-use crate::{Action, GhostOpt, ToggleOpt};
+use crate::{Action, GhostOpt, IntOpt, ToggleOpt};
 
 #[derive(Arbitrary, Debug, Iterable, Clone)]
 pub struct BasicLlvmFlags {
@@ -530,6 +530,30 @@ pub struct AllLlvmFlags {
     // Manually added
     E: GhostOpt,
     //     fpic: ToggleOpt,
+
+    // Valued options
+    falign_loops: IntOpt,
+    ffp_contract: Option<FpContract>,
+    ffp_model: Option<FpModel>,
+    fstrict_flex_arrays: IntOpt,
+    cl_opts: LlvmClOpts,
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Arbitrary, Debug, Clone, Copy)]
+pub enum FpContract {
+    fast,
+    on,
+    off,
+    fast_honor_pragmas,
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Arbitrary, Debug, Clone, Copy)]
+pub enum FpModel {
+    precise,
+    strict,
+    fast,
 }
 
 impl AllLlvmFlags {
@@ -738,6 +762,11 @@ impl AllLlvmFlags {
 
         // Not expected to work on RISC-V
         self.fglobal_isel = ToggleOpt::Hidden;
+
+        // N must be a power of two
+        self.falign_loops.bound_power_of_two(16);
+        self.fstrict_flex_arrays.bound(0, 3);
+        self.cl_opts.sanitize(_action);
     }
 }
 
@@ -773,6 +802,31 @@ impl fmt::Display for AllLlvmFlags {
                         GhostOpt::Hidden => "".to_string(),
                         GhostOpt::On => format!("-{}", arg_name),
                     }
+                } else if field_value.is::<IntOpt>() {
+                    let value: IntOpt = *field_value.downcast_ref().unwrap();
+                    let arg_name = field_name.replace('_', "-");
+                    match value.0 {
+                        Some(value) => format!("-{arg_name}={value}"),
+                        None => "".to_string(),
+                    }
+                } else if field_value.is::<Option<FpContract>>() {
+                    let value: Option<FpContract> = *field_value.downcast_ref().unwrap();
+                    if let Some(value) = value {
+                        let contract = format!("{:?}", value).replace('_', "-");
+                        format!("-ffp-contract={contract}")
+                    } else {
+                        "".to_string()
+                    }
+                } else if field_value.is::<Option<FpModel>>() {
+                    let value: Option<FpModel> = *field_value.downcast_ref().unwrap();
+                    if let Some(value) = value {
+                        format!("-ffp-model={:?}", value)
+                    } else {
+                        "".to_string()
+                    }
+                } else if field_value.is::<LlvmClOpts>() {
+                    let value: &LlvmClOpts = field_value.downcast_ref().unwrap();
+                    value.to_string()
                 } else {
                     panic!("Unknown datatype for field: {}", field_name)
                 }
@@ -789,6 +843,74 @@ impl fmt::Display for AllLlvmFlags {
     }
 }
 
+/// RISC-V backend cl::opts passed with -mllvm
+#[allow(non_camel_case_types)]
+#[derive(Arbitrary, Debug, Iterable, Clone)]
+pub struct LlvmClOpts {
+    riscv_disable_regalloc_hints: ToggleOpt,
+    riscv_disable_using_constant_pool_for_large_ints: ToggleOpt,
+    riscv_enable_copy_propagation: ToggleOpt,
+    riscv_enable_copyelim: ToggleOpt,
+    riscv_enable_dead_defs: ToggleOpt,
+    riscv_enable_global_merge: ToggleOpt,
+    riscv_enable_machine_combiner: ToggleOpt,
+    riscv_enable_sink_fold: ToggleOpt,
+    riscv_enable_subreg_liveness: ToggleOpt,
+    riscv_use_aa: ToggleOpt,
+    riscv_lower_ext_max_web_size: IntOpt,
+    riscv_lower_fp_repeated_divisors: IntOpt,
+    riscv_lower_fpimm_cost: IntOpt,
+    riscv_max_build_ints_cost: IntOpt,
+    riscv_min_jump_table_entries: IntOpt,
+    riscv_v_fixed_length_vector_lmul_max: IntOpt,
+    riscv_v_register_bit_width_lmul: IntOpt,
+    riscv_v_slp_max_vf: IntOpt,
+}
+
+impl LlvmClOpts {
+    pub fn sanitize(&mut self, _action: &Action) {
+        self.riscv_lower_ext_max_web_size.bound(0, 32);
+        self.riscv_lower_fp_repeated_divisors.bound(0, 8);
+        self.riscv_lower_fpimm_cost.bound(0, 8);
+        self.riscv_max_build_ints_cost.bound(0, 16);
+        self.riscv_min_jump_table_entries.bound(1, 16);
+        // LMULs are asserted to be a power of two <= 8
+        self.riscv_v_fixed_length_vector_lmul_max
+            .bound_power_of_two(3);
+        self.riscv_v_register_bit_width_lmul.bound_power_of_two(3);
+        self.riscv_v_slp_max_vf.bound(0, 16);
+    }
+}
+
+impl fmt::Display for LlvmClOpts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let flags = self
+            .iter()
+            .map(|(field_name, field_value)| {
+                let arg_name = field_name.replace('_', "-");
+                if field_value.is::<ToggleOpt>() {
+                    let value: ToggleOpt = *field_value.downcast_ref().unwrap();
+                    match value {
+                        ToggleOpt::Hidden => "".to_string(),
+                        ToggleOpt::Off => format!("-mllvm -{}=false", arg_name),
+                        ToggleOpt::On => format!("-mllvm -{}=true", arg_name),
+                    }
+                } else if field_value.is::<IntOpt>() {
+                    let value: IntOpt = *field_value.downcast_ref().unwrap();
+                    match value.0 {
+                        Some(value) => format!("-mllvm -{arg_name}={value}"),
+                        None => "".to_string(),
+                    }
+                } else {
+                    panic!("Unknown datatype for field: {}", field_name)
+                }
+            })
+            .collect::<Vec<_>>();
+
+        write!(f, "{}", flags.join(" "))
+    }
+}
+
 #[allow(non_camel_case_types)]
 #[derive(Arbitrary, Debug, Iterable, Clone)]
 pub struct LlvmRiscvToggles {
@@ -1,8 +1,12 @@
 use argh::FromArgs;
 use env_logger::Env;
 use log::info;
+use std::path::PathBuf;
 
-use compiler_flags_gen::{arbitrary_flags, Action, Compiler, FlagSet};
+use compiler_flags_gen::{
+    arbitrary_flags, arbitrary_target_flags, catalog::FlagCatalog, random_flag_seed, Action,
+    Compiler, FlagSet, Target,
+};
 
 #[derive(FromArgs)]
 #[argh(description = "Generate random valid compiler flags
@@ -28,6 +32,14 @@ struct FlagGen {
     /// emit flags valid for execution
     #[argh(switch, short = 'e')]
     execute: bool,
+
+    /// emit flags from the help output of this compiler instead of the built-in flag structs
+    #[argh(option)]
+    catalog: Option<PathBuf>,
+
+    /// target to emit flags for ('riscv'/'x86-64'/'aarch64'), defaults to riscv
+    #[argh(option)]
+    target: Option<String>,
 }
 
 fn main() {
@@ -55,6 +67,15 @@ fn main() {
         Compiler::Llvm
     };
 
+    if let Some(compiler_path) = &args.catalog {
+        info!("\tcatalog:\t{:?}", compiler_path);
+        let catalog = FlagCatalog::from_compiler(&compiler, compiler_path)
+            .expect("Failed to build flag catalog");
+        let flags = catalog.arbitrary_flags_from_seed(&mut random_flag_seed());
+        println!("{}", flags);
+        return;
+    }
+
     assert!(
         (args.compile || args.execute),
         "Must specify an action (Compile or Execute)."
@@ -75,7 +96,18 @@ fn main() {
         Some(_) | None => panic!("Must specify something to emit ('march'/'march-and-all-flags'/'march-and-basic-flags'/'basic-flags'/'all-flags')."),
     };
 
-    let flags = arbitrary_flags(&compiler, &action, &flag_set, false, None);
+    let target = match args.target.as_deref() {
+        Some("riscv") | None => Target::Riscv,
+        Some("x86-64") => Target::X86_64,
+        Some("aarch64") => Target::Aarch64,
+        Some(target) => panic!("Unknown target: {target} ('riscv'/'x86-64'/'aarch64')."),
+    };
+
+    let flags = if target == Target::Riscv {
+        arbitrary_flags(&compiler, &action, &flag_set, false, None)
+    } else {
+        arbitrary_target_flags(&target, &compiler, &action, &flag_set)
+    };
 
     println!("{}", flags);
 }
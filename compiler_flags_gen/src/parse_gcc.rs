@@ -257,3 +257,44 @@ pub fn parse_riscv() {
         println!("\t{}: ,", enum_opt.replacen('-', "", 1).replace('-', "_"));
     }
 }
+
+/// Print the GccParams struct and its sanitize body from the output of
+/// `gcc -Q --help=params`. Only params with an advertised range are kept since
+/// gcc rejects values outside of it.
+pub fn parse_gcc_params(help: &str) {
+    let mut params = help
+        .lines()
+        .filter_map(|line| {
+            let opt = line.split_whitespace().next()?;
+            let (name, range) = opt.strip_prefix("--param=")?.split_once('=')?;
+            if !range.starts_with('<') {
+                trace!("Param: {} has no range", name);
+                return None;
+            }
+            if name.starts_with("asan-")
+                || name.starts_with("hwasan-")
+                || name.starts_with("tsan-")
+                || name.starts_with("lto-")
+            {
+                trace!("Param: {} does not affect codegen", name);
+                return None;
+            }
+            let (min, max) = range
+                .trim_matches(|c| c == '<' || c == '>')
+                .split_once(',')?;
+            Some((name.replace('-', "_"), min.to_string(), max.to_string()))
+        })
+        .collect::<Vec<_>>();
+    params.sort();
+    params.dedup();
+
+    println!("struct GccParams {{");
+    for (name, _, _) in &params {
+        println!("\t{}: IntOpt,", name);
+    }
+    println!("}}");
+
+    for (name, min, max) in &params {
+        println!("\tself.{}.bound({}, {});", name, min, max);
+    }
+}
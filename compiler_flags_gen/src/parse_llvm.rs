@@ -77,6 +77,21 @@ pub fn parse_llvm() {
     toggleable.sort();
     toggleable.dedup();
 
+    // Numeric options are emitted as IntOpts, bounds have to be added to sanitize
+    let mut valued = sorted_opts
+        .iter()
+        .filter_map(|line| {
+            let opt = line.split_whitespace().next()?;
+            let (name, arg) = opt.split_once('=')?;
+            if !matches!(arg, "<N>" | "<n>" | "<number>") {
+                return None;
+            }
+            Some(name)
+        })
+        .collect::<Vec<_>>();
+    valued.sort();
+    valued.dedup();
+
     println!("struct LlvmFlags {{");
     for toggle_opt in &toggleable {
         println!(
@@ -84,6 +99,12 @@ pub fn parse_llvm() {
             toggle_opt.replacen('-', "", 1).replace('-', "_")
         );
     }
+    for valued_opt in &valued {
+        println!(
+            "\t{}: IntOpt,",
+            valued_opt.replacen('-', "", 1).replace('-', "_")
+        );
+    }
     println!("}}");
 }
 
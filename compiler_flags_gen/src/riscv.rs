@@ -798,10 +798,9 @@ impl Mabi {
     }
 }
 
-impl fmt::Display for March {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut abi = None;
-
+impl March {
+    /// The -march string, e.g. rv64gcv_zba
+    pub fn isa_string(&self) -> String {
         let flags = self
             .iter()
             .map(|(field_name, field_value)| {
@@ -836,7 +835,6 @@ impl fmt::Display for March {
                     }
                 } else if field_value.is::<Mabi>() {
                     let value: Mabi = *field_value.downcast_ref().unwrap();
-                    abi = Some(value);
                     if value.rv32() {
                         "rv32".to_string()
                     } else {
@@ -851,19 +849,32 @@ impl fmt::Display for March {
             })
             .collect::<Vec<_>>();
 
-        let abi = match abi {
-            Some(Mabi::ilp32) => "ilp32",
-            Some(Mabi::ilp32d) => "ilp32d",
-            Some(Mabi::ilp32e) => "ilp32e",
-            Some(Mabi::ilp32f) => "ilp32f",
-            Some(Mabi::lp64) => "lp64",
-            Some(Mabi::lp64d) => "lp64d",
-            Some(Mabi::lp64e) => "lp64e",
-            Some(Mabi::lp64f) => "lp64f",
-            None => panic!(),
-        };
+        flags.join("")
+    }
 
-        write!(f, "-march={} -mabi={}", flags.join(""), abi)
+    /// The -mabi string, e.g. lp64d
+    pub fn abi_string(&self) -> &'static str {
+        match self.mabi {
+            Mabi::ilp32 => "ilp32",
+            Mabi::ilp32d => "ilp32d",
+            Mabi::ilp32e => "ilp32e",
+            Mabi::ilp32f => "ilp32f",
+            Mabi::lp64 => "lp64",
+            Mabi::lp64d => "lp64d",
+            Mabi::lp64e => "lp64e",
+            Mabi::lp64f => "lp64f",
+        }
+    }
+}
+
+impl fmt::Display for March {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "-march={} -mabi={}",
+            self.isa_string(),
+            self.abi_string()
+        )
     }
 }
 
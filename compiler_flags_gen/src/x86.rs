@@ -0,0 +1,162 @@
+use std::fmt;
+
+use arbitrary::Arbitrary;
+use struct_iterable::Iterable;
+
+use crate::{Action, ToggleOpt};
+
+#[allow(non_camel_case_types)]
+#[derive(Arbitrary, Debug, Iterable, Clone)]
+pub struct X86March {
+    pub march: X86Cpu,
+    pub mtune: Option<X86Cpu>,
+    madx: ToggleOpt,
+    maes: ToggleOpt,
+    mavx: ToggleOpt,
+    mavx2: ToggleOpt,
+    mavx512bw: ToggleOpt,
+    mavx512cd: ToggleOpt,
+    mavx512dq: ToggleOpt,
+    mavx512f: ToggleOpt,
+    mavx512vbmi: ToggleOpt,
+    mavx512vl: ToggleOpt,
+    mavx512vnni: ToggleOpt,
+    mbmi: ToggleOpt,
+    mbmi2: ToggleOpt,
+    mf16c: ToggleOpt,
+    mfma: ToggleOpt,
+    mlzcnt: ToggleOpt,
+    mmovbe: ToggleOpt,
+    mpclmul: ToggleOpt,
+    mpopcnt: ToggleOpt,
+    msse3: ToggleOpt,
+    msse4_1: ToggleOpt,
+    msse4_2: ToggleOpt,
+    mssse3: ToggleOpt,
+    mprefer_vector_width: Option<VectorWidth>,
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Arbitrary, Debug, Clone, Copy, PartialEq)]
+pub enum X86Cpu {
+    x86_64,
+    x86_64_v2,
+    x86_64_v3,
+    x86_64_v4,
+    alderlake,
+    broadwell,
+    haswell,
+    icelake_client,
+    icelake_server,
+    sapphirerapids,
+    skylake,
+    skylake_avx512,
+    tigerlake,
+    znver1,
+    znver2,
+    znver3,
+    znver4,
+}
+
+impl X86Cpu {
+    /// Whether the cpu has AVX-512. Used to avoid running binaries that the
+    /// host (or qemu) can't execute.
+    pub fn has_avx512(&self) -> bool {
+        matches!(
+            self,
+            X86Cpu::x86_64_v4
+                | X86Cpu::icelake_client
+                | X86Cpu::icelake_server
+                | X86Cpu::sapphirerapids
+                | X86Cpu::skylake_avx512
+                | X86Cpu::tigerlake
+                | X86Cpu::znver4
+        )
+    }
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Arbitrary, Debug, Clone, Copy)]
+pub enum VectorWidth {
+    none,
+    w128,
+    w256,
+    w512,
+}
+
+impl X86March {
+    pub fn sanitize(&mut self, action: &Action) {
+        // The x86-64 levels are only valid for -march
+        if matches!(
+            self.mtune,
+            Some(X86Cpu::x86_64 | X86Cpu::x86_64_v2 | X86Cpu::x86_64_v3 | X86Cpu::x86_64_v4)
+        ) {
+            self.mtune = None;
+        }
+
+        // Executed binaries must run on the runner, which is assumed to be
+        // x86-64-v3 (AVX2) capable.
+        if matches!(action, Action::Execute) {
+            if self.march.has_avx512() {
+                self.march = X86Cpu::x86_64_v3;
+            }
+            for avx512 in [
+                &mut self.mavx512bw,
+                &mut self.mavx512cd,
+                &mut self.mavx512dq,
+                &mut self.mavx512f,
+                &mut self.mavx512vbmi,
+                &mut self.mavx512vl,
+                &mut self.mavx512vnni,
+            ] {
+                if *avx512 == ToggleOpt::On {
+                    *avx512 = ToggleOpt::Hidden;
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for X86March {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let flags = self
+            .iter()
+            .map(|(field_name, field_value)| {
+                if field_value.is::<ToggleOpt>() {
+                    let value: ToggleOpt = *field_value.downcast_ref().unwrap();
+                    // -msse4.1/-msse4.2
+                    let arg_name = field_name.replace("sse4_", "sse4.").replace('_', "-");
+                    match value {
+                        ToggleOpt::Hidden => "".to_string(),
+                        ToggleOpt::Off => format!("-{}no-{}", &arg_name[0..1], &arg_name[1..]),
+                        ToggleOpt::On => format!("-{}", arg_name),
+                    }
+                } else if field_value.is::<X86Cpu>() {
+                    let value: X86Cpu = *field_value.downcast_ref().unwrap();
+                    let cpu = format!("{:?}", value).replace('_', "-");
+                    format!("-march={cpu}")
+                } else if field_value.is::<Option<X86Cpu>>() {
+                    let value: Option<X86Cpu> = *field_value.downcast_ref().unwrap();
+                    if let Some(value) = value {
+                        let cpu = format!("{:?}", value).replace('_', "-");
+                        format!("-mtune={cpu}")
+                    } else {
+                        "".to_string()
+                    }
+                } else if field_value.is::<Option<VectorWidth>>() {
+                    let value: Option<VectorWidth> = *field_value.downcast_ref().unwrap();
+                    if let Some(value) = value {
+                        let width = format!("{:?}", value).replace('w', "");
+                        format!("-mprefer-vector-width={width}")
+                    } else {
+                        "".to_string()
+                    }
+                } else {
+                    panic!("Unknown datatype for field: {}", field_name)
+                }
+            })
+            .collect::<Vec<_>>();
+
+        write!(f, "{}", flags.join(" "))
+    }
+}
@@ -0,0 +1,149 @@
+use argh::FromArgs;
+use compiler_fuzz::reproduce::{copy_reduction, read_fail_info, reproduces, write_fail_info};
+use compiler_fuzz::{FailInfo, ReductionConfig};
+use env_logger::Env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use xshell::{cmd, Shell};
+
+#[derive(FromArgs)]
+#[argh(description = "Bisect a reduced failure against compiler commits
+Use RUST_LOG=off to turn off logging")]
+struct BisectArgs {
+    /// directory produced by reduce
+    #[argh(positional)]
+    reduction_directory: PathBuf,
+
+    /// git checkout of the compiler to bisect
+    #[argh(positional)]
+    source_directory: PathBuf,
+
+    /// command that builds and installs the compiler, run from the checkout
+    #[argh(option)]
+    build: String,
+
+    /// revision that doesn't fail
+    #[argh(option)]
+    good: String,
+
+    /// revision that fails (default: HEAD)
+    #[argh(option)]
+    bad: Option<String>,
+
+    /// built compiler to test instead of the one in fail_info.yaml. For
+    /// runtime fails only the slow compiler is replaced.
+    #[argh(option)]
+    compiler: Option<PathBuf>,
+}
+
+fn main() -> anyhow::Result<()> {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+
+    let args: BisectArgs = argh::from_env();
+
+    let reduction_dir = &fs::canonicalize(&args.reduction_directory)?;
+    let source_dir = &fs::canonicalize(&args.source_directory)?;
+
+    let sh = Shell::new()?;
+    let mut fail_info = read_fail_info(&sh, reduction_dir)?;
+
+    // The interestingness tests write their output next to the testcase, so
+    // run them on a copy to keep the reduction's files intact
+    let config = ReductionConfig {
+        compiler: args.compiler.clone(),
+        ..Default::default()
+    };
+    let work_temp_dir = copy_reduction(&sh, reduction_dir, &config)?;
+    let work_dir = work_temp_dir.path();
+
+    let first_bad_commit = bisect(&args, source_dir, work_dir)?;
+
+    let Some(first_bad_commit) = first_bad_commit else {
+        anyhow::bail!("Bisection could not narrow the failure down to one commit");
+    };
+    println!("First bad commit: {first_bad_commit}");
+
+    match &mut fail_info {
+        FailInfo::Ice(ice_fail_info) => {
+            ice_fail_info.first_bad_commit = Some(first_bad_commit.clone())
+        }
+        FailInfo::Execution(exec_fail_info) => {
+            exec_fail_info.first_bad_commit = Some(first_bad_commit.clone())
+        }
+        FailInfo::Runtime(runtime_fail_info) => {
+            runtime_fail_info.first_bad_commit = Some(first_bad_commit.clone())
+        }
+    }
+    write_fail_info(&sh, reduction_dir, &fail_info)?;
+
+    let bug_report_path = reduction_dir.join("bug_report.txt");
+    if bug_report_path.exists() {
+        let bug_report = sh.read_file(&bug_report_path)?;
+        sh.write_file(
+            &bug_report_path,
+            format!("{bug_report}\nFirst bad commit: {first_bad_commit}\n"),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Run git bisect, building the compiler at each step. Returns the first bad
+/// commit with its subject.
+fn bisect(args: &BisectArgs, source_dir: &Path, work_dir: &Path) -> anyhow::Result<Option<String>> {
+    let sh = Shell::new()?;
+    sh.change_dir(source_dir);
+
+    let good = &args.good;
+    let bad = args.bad.as_deref().unwrap_or("HEAD");
+    let build = &args.build;
+
+    cmd!(sh, "git bisect start {bad} {good}").run()?;
+    let _reset = BisectReset { sh: &sh };
+
+    let first_bad_commit = loop {
+        let commit = cmd!(sh, "git rev-parse --short HEAD").read()?;
+        let verdict = if cmd!(sh, "bash -c {build}").run().is_err() {
+            println!("{commit}: build failed, skipping");
+            "skip"
+        } else {
+            match reproduces(work_dir) {
+                Ok(Some(true)) => "bad",
+                Ok(Some(false)) => "good",
+                Ok(None) => "skip",
+                Err(error) => {
+                    println!("{commit}: interestingness test failed to run: {error}");
+                    "skip"
+                }
+            }
+        };
+        println!("{commit}: {verdict}");
+
+        let output = cmd!(sh, "git bisect {verdict}").read()?;
+        println!("{output}");
+        if output.contains("is the first bad commit") {
+            let commit = output.split_whitespace().next().unwrap();
+            let format = "--format=%H %s";
+            break Some(cmd!(sh, "git log -1 {format} {commit}").read()?);
+        }
+        if output.contains("only 'skip'ped commits left") {
+            break None;
+        }
+    };
+
+    Ok(first_bad_commit)
+}
+
+/// Ends the bisection however `bisect` returns, so an error part way through
+/// doesn't leave the checkout at some commit in the middle
+struct BisectReset<'a> {
+    sh: &'a Shell,
+}
+
+impl Drop for BisectReset<'_> {
+    fn drop(&mut self) {
+        if let Err(error) = cmd!(self.sh, "git bisect reset").run() {
+            println!("git bisect reset failed: {error}");
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use argh::FromArgs;
+use compiler_fuzz::finds_db::{read_finds, FindRecord, ReductionStatus, FINDS_DB};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+#[derive(FromArgs)]
+#[argh(description = "List, filter and group the finds recorded by the fuzzers")]
+struct FindsArgs {
+    /// finds directory or its finds.yaml
+    #[argh(positional)]
+    finds: PathBuf,
+
+    /// only finds of this kind (Ice, Execution or Runtime)
+    #[argh(option)]
+    kind: Option<String>,
+
+    /// only finds whose fail type contains this
+    #[argh(option)]
+    fail_type: Option<String>,
+
+    /// only finds whose signature contains this
+    #[argh(option)]
+    signature: Option<String>,
+
+    /// only finds whose compiler version contains this
+    #[argh(option)]
+    compiler_version: Option<String>,
+
+    /// only finds from this runner id
+    #[argh(option)]
+    runner_id: Option<u32>,
+
+    /// only finds that haven't been reduced
+    #[argh(switch)]
+    unreduced: bool,
+
+    /// group finds by signature, fail-type, compiler-version, kind or
+    /// runner-id instead of listing them
+    #[argh(option)]
+    group_by: Option<String>,
+}
+
+fn main() -> anyhow::Result<()> {
+    let args: FindsArgs = argh::from_env();
+
+    let db_path = if args.finds.is_dir() {
+        args.finds.join(FINDS_DB)
+    } else {
+        args.finds.clone()
+    };
+
+    let records = read_finds(&db_path)?
+        .into_iter()
+        .filter(|record| matches_filters(&args, record))
+        .collect::<Vec<_>>();
+
+    let Some(group_by) = &args.group_by else {
+        for record in &records {
+            println!(
+                "{} {:>2} {:?} {} [{}] {} {}",
+                record.time,
+                record
+                    .runner_id
+                    .map(|id| id.to_string())
+                    .unwrap_or_default(),
+                record.kind,
+                record.fail_type,
+                match &record.reduction_status {
+                    ReductionStatus::Unreduced => "unreduced".to_string(),
+                    ReductionStatus::Reduced(reduction_dir) =>
+                        format!("reduced: {}", reduction_dir.display()),
+                },
+                record.dump_dir.display(),
+                record.signature,
+            );
+        }
+        println!("{} finds", records.len());
+        return Ok(());
+    };
+
+    let mut groups: HashMap<String, Vec<&FindRecord>> = HashMap::new();
+    for record in &records {
+        let key = match group_by.as_str() {
+            "signature" => record.signature.clone(),
+            "fail-type" => record.fail_type.clone(),
+            "compiler-version" => record.compiler_version.clone().unwrap_or_default(),
+            "kind" => format!("{:?}", record.kind),
+            "runner-id" => record
+                .runner_id
+                .map(|id| id.to_string())
+                .unwrap_or_default(),
+            _ => anyhow::bail!("Unknown --group-by {group_by}"),
+        };
+        groups.entry(key).or_default().push(record);
+    }
+
+    let mut groups = groups.into_iter().collect::<Vec<_>>();
+    groups.sort_by(|(a_key, a), (b_key, b)| b.len().cmp(&a.len()).then(a_key.cmp(b_key)));
+    for (key, group) in &groups {
+        println!("{:>4} {key}", group.len());
+        for record in group {
+            println!("       {}", record.dump_dir.display());
+        }
+    }
+    println!("{} groups, {} finds", groups.len(), records.len());
+
+    Ok(())
+}
+
+fn matches_filters(args: &FindsArgs, record: &FindRecord) -> bool {
+    let contains = |filter: &Option<String>, value: &str| {
+        filter.as_ref().is_none_or(|filter| value.contains(filter))
+    };
+    args.kind
+        .as_ref()
+        .is_none_or(|kind| format!("{:?}", record.kind).eq_ignore_ascii_case(kind))
+        && contains(&args.fail_type, &record.fail_type)
+        && contains(&args.signature, &record.signature)
+        && contains(
+            &args.compiler_version,
+            record.compiler_version.as_deref().unwrap_or_default(),
+        )
+        && args.runner_id.is_none_or(|id| record.runner_id == Some(id))
+        && (!args.unreduced || matches!(record.reduction_status, ReductionStatus::Unreduced))
+}
@@ -1,8 +1,20 @@
 use anyhow::Context;
 use argh::FromArgs;
-use compiler_fuzz::compile::{get_compile_flags, run_compiler};
-use compiler_fuzz::execute::execute_program;
-use compiler_fuzz::generate::{get_generator_flags, run_generator};
+use compiler_fuzz::build_identity::{
+    compiler_identity, current_build_identities, generator_identity, runner_identities,
+    set_build_identities,
+};
+use compiler_fuzz::compile::{
+    get_flag_values, get_seeded_flag_values, render_compile_flags, run_compiler,
+};
+use compiler_fuzz::coverage::{collect_edges, push_coverage_env, testcase_hash, FlagCorpus};
+use compiler_fuzz::execute::{execute_program, vector_sweep_runners};
+use compiler_fuzz::finds_db::record_find;
+use compiler_fuzz::generate::{
+    current_corpus_testcase, current_generator_args, get_generator_flags, run_generator,
+};
+use compiler_fuzz::generator_stats::GeneratorStats;
+use compiler_fuzz::reduction::ub_check;
 use env_logger::Env;
 use std::fs::{self, File};
 use std::io::Read;
@@ -10,11 +22,12 @@ use std::path::{Path, PathBuf};
 use std::time::Instant;
 use xshell::{cmd, Shell};
 
-use compiler_flags_gen::Action;
+use compiler_flags_gen::{flag_value::FlagSetValue, random_flag_seed, Action, Compiler};
 
 use compiler_fuzz::{
-    CompileConfig, FailInfo, FuzzCompiler, FuzzConfig, FuzzGenerator, RunConfig, RuntimeFailInfo,
-    RuntimeFailType, Stats,
+    detect_compiler, llvm_tool, BuildIdentities, CompileConfig, CompilerArguments, FailInfo,
+    FuzzCompiler, FuzzConfig, FuzzGenerator, LlvmIrSource, RunConfig, RuntimeFailInfo,
+    RuntimeFailType, Stats, UbCheckResult,
 };
 
 #[derive(FromArgs)]
@@ -99,6 +112,18 @@ fn validate_generator(generator: &FuzzGenerator) -> anyhow::Result<()> {
         FuzzGenerator::Csmith(csmith_config) => &csmith_config.path,
         FuzzGenerator::Yarpgen(yarpgen_config) => &yarpgen_config.path,
         FuzzGenerator::Fixed(fixed_config) => &fixed_config.path,
+        FuzzGenerator::Corpus(corpus_config) => &corpus_config.path,
+        FuzzGenerator::LlvmIr(llvm_ir_config) => match &llvm_ir_config.source {
+            LlvmIrSource::LlvmStress(llvm_stress_config) => &llvm_stress_config.path,
+            LlvmIrSource::Csmith(csmith_ir_config) => &csmith_ir_config.csmith.path,
+        },
+        FuzzGenerator::Mutate(mutate_config) => {
+            // Seeds are just files
+            for seed in &mutate_config.seeds {
+                anyhow::ensure!(seed.exists(), "Seed({:?}) does not exist!", seed);
+            }
+            return Ok(());
+        }
         // TODO: Add c2rust support with csmith/yarpgen
         FuzzGenerator::Rustsmith(rustsmith_config) => &rustsmith_config.path,
     };
@@ -114,6 +139,15 @@ fn validate_generator(generator: &FuzzGenerator) -> anyhow::Result<()> {
         return Ok(());
     }
 
+    if matches!(generator, FuzzGenerator::Corpus(..)) {
+        assert!(
+            generator_path.is_dir(),
+            "Corpus({:?}) is not a directory!",
+            generator_path
+        );
+        return Ok(());
+    }
+
     let sh = Shell::new()?;
 
     let generator_path_str: &str = generator_path.to_str().unwrap();
@@ -133,26 +167,101 @@ fn validate_generator(generator: &FuzzGenerator) -> anyhow::Result<()> {
         ),
         FuzzGenerator::Yarpgen(_yarpgen_config) => {}
         FuzzGenerator::Fixed(_fixed_config) => {}
+        FuzzGenerator::Corpus(_corpus_config) => {}
         FuzzGenerator::Rustsmith(_rustsmith_config) => {}
+        FuzzGenerator::Mutate(_mutate_config) => {}
+        FuzzGenerator::LlvmIr(llvm_ir_config) => match &llvm_ir_config.source {
+            LlvmIrSource::LlvmStress(_llvm_stress_config) => {}
+            LlvmIrSource::Csmith(csmith_ir_config) => {
+                assert!(
+                    csmith_ir_config.csmith.include_dir.exists(),
+                    "Generator include dir({:?}) does not exist!",
+                    csmith_ir_config.csmith.include_dir
+                );
+                assert!(
+                    csmith_ir_config.clang.exists(),
+                    "Generator clang({:?}) does not exist!",
+                    csmith_ir_config.clang
+                );
+            }
+        },
     };
 
     Ok(())
 }
 
+/// Cross compiled rust is linked with the riscv toolchain
+const RUSTC_RISCV_LINKER: &str =
+    "/scratch/tc-testing/tc-compiler-fuzz-trunk/build-gcv/bin/riscv64-unknown-linux-gnu-gcc";
+
+/// rustc arguments for generated flags. Native builds use -C opt-level=3,
+/// cross compiled builds -C opt-level=1 with the generated target features.
+fn get_rustc_flags(flag_value: &mut FlagSetValue) -> Vec<String> {
+    let cross_compile = flag_value.march.is_some();
+    flag_value.opt_level = Some(if cross_compile { "1" } else { "3" }.to_string());
+
+    let mut flags = flag_value.rustc_args();
+    if cross_compile {
+        flags.extend(["-C".to_string(), format!("linker={RUSTC_RISCV_LINKER}")]);
+    }
+    flags
+}
+
 fn ice_fuzzer(config: &CompileConfig, finds_dir: &Path, id: Option<u32>) -> anyhow::Result<()> {
     validate_compiler(&config.compiler, false)?;
     validate_generator(&config.generator)?;
+    if matches!(config.generator, FuzzGenerator::LlvmIr(..)) {
+        assert!(
+            matches!(config.action, Action::Compile | Action::Assemble),
+            "LLVM IR testcases can only be compiled or assembled"
+        );
+        for tool in ["opt", "llc"] {
+            let tool_path = llvm_tool(&config.compiler.path, tool);
+            assert!(
+                tool_path.exists(),
+                "{tool}({:?}) does not exist next to the compiler!",
+                tool_path
+            );
+        }
+    }
+    if config.coverage.is_some() {
+        assert!(
+            matches!(config.compiler.arguments, CompilerArguments::Generated(..)),
+            "Coverage guided fuzzing requires generated compiler arguments"
+        );
+    }
 
     let id_str = id.map(|x| x.to_string()).unwrap_or("".to_string());
 
     // Fuzz
     let sh = Shell::new()?;
 
+    let mut compilers = vec![compiler_identity(&sh, &config.compiler.path)?];
+    if matches!(config.generator, FuzzGenerator::LlvmIr(..)) {
+        for tool in ["opt", "llc"] {
+            compilers.push(compiler_identity(
+                &sh,
+                &llvm_tool(&config.compiler.path, tool),
+            )?);
+        }
+    }
+    set_build_identities(BuildIdentities {
+        compilers,
+        runners: vec![],
+        generator: generator_identity(&sh, &config.generator)?,
+    });
+
     // Create temp directory
     let dir = sh.create_temp_dir().unwrap();
     let temp_dir = dir.path();
     sh.change_dir(temp_dir);
 
+    // Kept outside of temp_dir so coverage data isn't copied into finds
+    let coverage_temp_dir = sh.create_temp_dir()?;
+    let coverage_dir = &coverage_temp_dir.path().join("coverage");
+    let mut corpus = FlagCorpus::new();
+    let mut testcase = 0;
+
     let assemble_link_flags = match config.action {
         Action::Compile => vec!["-S"],
         Action::Assemble => vec!["-c"],
@@ -170,7 +279,9 @@ fn ice_fuzzer(config: &CompileConfig, finds_dir: &Path, id: Option<u32>) -> anyh
         execute_success: 0,
         execute_timeout: 0,
         execute_error: 0,
+        execute_unsupported: 0,
     };
+    let mut generator_stats = GeneratorStats::default();
     let mut testcase_paths: Vec<PathBuf> = vec![];
     loop {
         // Generate new testcase
@@ -178,14 +289,30 @@ fn ice_fuzzer(config: &CompileConfig, finds_dir: &Path, id: Option<u32>) -> anyh
         if match config.generator {
             // Try n isa strings per testcase
             FuzzGenerator::Csmith(_) => iter % 100 == 0,
+            // Mutants are checked for UB, which is slow
+            FuzzGenerator::Mutate(_) => iter % 10 == 0,
             // New testcase every time
-            FuzzGenerator::Fixed(_) | FuzzGenerator::Yarpgen(_) | FuzzGenerator::Rustsmith(_) => {
-                true
-            }
+            FuzzGenerator::Fixed(_)
+            | FuzzGenerator::Corpus(_)
+            | FuzzGenerator::Yarpgen(_)
+            | FuzzGenerator::Rustsmith(_) => true,
+            FuzzGenerator::LlvmIr(ref llvm_ir_config) => match llvm_ir_config.source {
+                LlvmIrSource::LlvmStress(_) => true,
+                LlvmIrSource::Csmith(_) => iter % 100 == 0,
+            },
         } {
             let generator_timer = Instant::now();
-            testcase_paths = run_generator(&sh, &config.generator)?;
+            testcase_paths = run_generator(&sh, &config.generator, &config.action)?;
             generator_ms += generator_timer.elapsed().as_millis();
+            record_testcase(
+                &sh,
+                &mut generator_stats,
+                &config.generator,
+                &testcase_paths,
+            )?;
+            if config.coverage.is_some() {
+                testcase = testcase_hash(&sh, &testcase_paths)?;
+            }
         }
 
         let generator_flags_strings =
@@ -200,34 +327,29 @@ fn ice_fuzzer(config: &CompileConfig, finds_dir: &Path, id: Option<u32>) -> anyh
             testcase_paths.len() + 1
         };
 
+        let mut seed = if config.coverage.is_some() {
+            corpus.next_seed(testcase)
+        } else {
+            random_flag_seed()
+        };
+
+        let mut flag_values = get_seeded_flag_values(
+            &config.compiler,
+            &config.generator,
+            &config.action,
+            flag_sets,
+            &mut seed,
+        )?;
+        if let Some(corpus_testcase) = current_corpus_testcase(&config.generator) {
+            for flag_value in flag_values.iter_mut() {
+                flag_value.push_flags(&corpus_testcase.options);
+            }
+        }
+
         let flags = if config.compiler.path.to_str().unwrap().contains("rustc") {
-            // TODO: Use feature bits (+zfa,+zabha,etc.)
             assert_eq!(flag_sets, 1);
 
-            let flags = get_compile_flags(
-                &config.compiler,
-                &config.generator,
-                &config.action,
-                vec![],
-                flag_sets,
-            );
-
-            let mut flags = if !flags[0].is_empty() {
-                assert!(flags[0][0].starts_with("-march"));
-                let flags = format!("llvm-args={}", flags[0][0].clone());
-                vec![
-                    "-C".to_string(),
-                    "opt-level=1".to_string(),
-                        "-C".to_string(),
-                        flags,
-                    "--target".to_string(),
-                    "riscv64gc-unknown-linux-gnu".to_string(),
-		    "-C".to_string(),
-		    "linker=/scratch/tc-testing/tc-compiler-fuzz-trunk/build-gcv/bin/riscv64-unknown-linux-gnu-gcc".to_string()
-                ]
-            } else {
-                vec!["-C".to_string(), "opt-level=3".to_string()]
-            };
+            let mut flags = get_rustc_flags(&mut flag_values[0]);
 
             match config.action {
                 Action::Compile => flags.append(&mut vec!["--emit".to_string(), "asm".to_string()]),
@@ -238,22 +360,32 @@ fn ice_fuzzer(config: &CompileConfig, finds_dir: &Path, id: Option<u32>) -> anyh
             }
 
             vec![flags]
+        } else if matches!(config.generator, FuzzGenerator::LlvmIr(..)) {
+            // opt -O3 has already run, so llc only needs the target
+            flag_values
+                .iter_mut()
+                .map(|flag_value| {
+                    flag_value.opt_level.get_or_insert("3".to_string());
+                    let mut flags = flag_value.llc_args();
+                    if matches!(config.action, Action::Assemble) {
+                        flags.push("-filetype=obj".to_string());
+                    }
+                    flags
+                })
+                .collect()
         } else {
-            let mut base_flags = vec!["-O3", "-w"];
+            let mut base_flags = vec!["-w"];
             base_flags.append(&mut assemble_link_flags.clone());
             if matches!(config.action, Action::Assemble)
-                && !config.compiler.path.to_str().unwrap().contains("gcc")
+                && detect_compiler(&config.compiler.path) != Some(Compiler::Gcc)
             {
                 let mut other_flags = vec!["-no-integrated-as"];
                 base_flags.append(&mut other_flags)
             }
-            get_compile_flags(
-                &config.compiler,
-                &config.generator,
-                &config.action,
-                base_flags,
-                flag_sets,
-            )
+            for flag_value in flag_values.iter_mut() {
+                flag_value.opt_level.get_or_insert("3".to_string());
+            }
+            render_compile_flags(&config.compiler, base_flags, &flag_values)
         };
         let flags: Vec<Vec<&str>> = flags
             .iter()
@@ -268,6 +400,11 @@ fn ice_fuzzer(config: &CompileConfig, finds_dir: &Path, id: Option<u32>) -> anyh
                 .collect()
         };
 
+        let coverage_env = match &config.coverage {
+            Some(coverage) => push_coverage_env(&sh, coverage, coverage_dir)?,
+            None => vec![],
+        };
+
         // Compile testcase with flags
         run_compiler(
             &sh,
@@ -275,6 +412,7 @@ fn ice_fuzzer(config: &CompileConfig, finds_dir: &Path, id: Option<u32>) -> anyh
             &mut stats,
             temp_dir,
             finds_dir,
+            id,
             &config.generator,
             &config.action,
             compilers,
@@ -284,17 +422,34 @@ fn ice_fuzzer(config: &CompileConfig, finds_dir: &Path, id: Option<u32>) -> anyh
             "output.o",
         )?;
 
+        drop(coverage_env);
+        if let Some(coverage) = &config.coverage {
+            let edges = collect_edges(&sh, coverage, coverage_dir)?;
+            let new_edges = corpus.add(seed, testcase, edges);
+            if new_edges > 0 {
+                println!(
+                    "{:>2} New coverage: {:>5} edges (corpus size {}, {} edges total)",
+                    id_str,
+                    new_edges,
+                    corpus.entries.len(),
+                    corpus.seen_edges.len()
+                );
+            }
+        }
+
         iter += 1;
         if (iter & (iter - 1)) == 0 {
             println!(
-                "{:>2} Iteration: {:>4} - Avg iter length: {:>4} ms (Generator {:>4} ms, Compiler {:>4} ms) - Timeout %: {:>3.3}",
+                "{:>2} Iteration: {:>4} - Avg iter length: {:>4} ms (Generator {:>4} ms, Compiler {:>4} ms) - Timeout %: {:>3.3} - {}",
 		id_str,
 		iter,
                 program_timer.elapsed().as_millis() / iter,
 		generator_ms / iter,
 		compiler_ms / iter,
-		(stats.compile_timeout as f64 / (stats.compile_timeout + stats.compile_error + stats.compile_success) as f64) * 100.
+		(stats.compile_timeout as f64 / (stats.compile_timeout + stats.compile_error + stats.compile_success) as f64) * 100.,
+		generator_stats.summary(),
             );
+            generator_stats.write(&sh, &generator_stats_path(finds_dir, &id_str))?;
 
             // If we're spending more than 5 ms/iter on rust code, something's going on.
             // Investigate the cause of the regression
@@ -312,6 +467,31 @@ fn ice_fuzzer(config: &CompileConfig, finds_dir: &Path, id: Option<u32>) -> anyh
     }
 }
 
+/// Record a new testcase in the generator stats, warning if the generator
+/// looks stuck. Fixed testcases repeat by design.
+fn record_testcase(
+    sh: &Shell,
+    generator_stats: &mut GeneratorStats,
+    generator: &FuzzGenerator,
+    testcase_paths: &[PathBuf],
+) -> anyhow::Result<()> {
+    if matches!(generator, FuzzGenerator::Fixed(_)) {
+        return Ok(());
+    }
+    if let Some(warning) = generator_stats.record_testcase(sh, testcase_paths)? {
+        println!("{warning}");
+    }
+    Ok(())
+}
+
+fn generator_stats_path(finds_dir: &Path, id_str: &str) -> PathBuf {
+    if id_str.is_empty() {
+        finds_dir.join("generator_stats.yaml")
+    } else {
+        finds_dir.join(format!("generator_stats_{id_str}.yaml"))
+    }
+}
+
 struct RuntimeStats {
     fast_compiler_stats: Stats,
     slow_compiler_stats: Stats,
@@ -325,12 +505,29 @@ fn runtime_diff_fuzzer(
 ) -> anyhow::Result<()> {
     validate_compiler(&config.fast_compiler, true)?;
     validate_compiler(&config.slow_compiler, true)?;
+    assert!(
+        !matches!(config.generator, FuzzGenerator::LlvmIr(..)),
+        "LLVM IR testcases can only be used by compile fuzzers"
+    );
 
     let id_str = id.map(|x| x.to_string()).unwrap_or("".to_string());
 
     // Fuzz
     let sh = Shell::new()?;
 
+    let mut runners = vec![];
+    for compiler in [&config.fast_compiler, &config.slow_compiler] {
+        runners.extend(runner_identities(&sh, compiler.runner.as_ref().unwrap())?);
+    }
+    set_build_identities(BuildIdentities {
+        compilers: vec![
+            compiler_identity(&sh, &config.fast_compiler.path)?,
+            compiler_identity(&sh, &config.slow_compiler.path)?,
+        ],
+        runners,
+        generator: generator_identity(&sh, &config.generator)?,
+    });
+
     // Create temp directory
     let dir = sh.create_temp_dir().unwrap();
     let temp_dir = dir.path();
@@ -351,6 +548,7 @@ fn runtime_diff_fuzzer(
             execute_success: 0,
             execute_timeout: 0,
             execute_error: 0,
+            execute_unsupported: 0,
         },
         slow_compiler_stats: Stats {
             compile_success: 0,
@@ -359,18 +557,32 @@ fn runtime_diff_fuzzer(
             execute_success: 0,
             execute_timeout: 0,
             execute_error: 0,
+            execute_unsupported: 0,
         },
         mismatch: 0,
     };
+    let mut generator_stats = GeneratorStats::default();
     let mut testcase_paths: Vec<PathBuf> = vec![];
+    // Set when ub_check finds UB so the testcase isn't tried with more flags
+    let mut ub_testcase = false;
+    // Only the first reference run of each testcase goes into generator_stats
+    let mut new_testcase = false;
     loop {
         // Generate new testcase
         // This takes a while since it could be csmith running
-        if iter % 10 == 0 {
+        if iter % 10 == 0 || ub_testcase {
             // Try 10 isa strings per testcase
             let generator_timer = Instant::now();
-            testcase_paths = run_generator(&sh, &config.generator)?;
+            testcase_paths = run_generator(&sh, &config.generator, &Action::Execute)?;
             generator_ms += generator_timer.elapsed().as_millis();
+            ub_testcase = false;
+            new_testcase = true;
+            record_testcase(
+                &sh,
+                &mut generator_stats,
+                &config.generator,
+                &testcase_paths,
+            )?;
         }
 
         let fast_generator_flags_strings =
@@ -402,17 +614,20 @@ fn runtime_diff_fuzzer(
                 "-fno-strict-aliasing",
                 "-fwrapv",
                 "-fsigned-char",
-                "-O3",
             ]
         };
 
-        let slow_runner_flags = get_compile_flags(
+        let mut slow_flag_values = get_flag_values(
             &config.slow_compiler,
             &config.generator,
             &Action::Execute,
-            slow_base_flags,
             flag_sets,
-        );
+        )?;
+        if let Some(corpus_testcase) = current_corpus_testcase(&config.generator) {
+            for flag_value in slow_flag_values.iter_mut() {
+                flag_value.push_flags(&corpus_testcase.options);
+            }
+        }
 
         let slow_runner_flags = if config
             .slow_compiler
@@ -421,24 +636,12 @@ fn runtime_diff_fuzzer(
             .unwrap()
             .contains("rustc")
         {
-            if !slow_runner_flags[0].is_empty() {
-                assert!(slow_runner_flags[0][0].starts_with("-march"));
-                let slow_runner_flags = format!("llvm-args={}", slow_runner_flags[0][0].clone());
-                vec![vec![
-                    "-C".to_string(),
-                    "opt-level=1".to_string(),
-                        "-C".to_string(),
-                        slow_runner_flags,
-                    "--target".to_string(),
-                    "riscv64gc-unknown-linux-gnu".to_string(),
-		    "-C".to_string(),
-		    "linker=/scratch/tc-testing/tc-compiler-fuzz-trunk/build-gcv/bin/riscv64-unknown-linux-gnu-gcc".to_string()
-                ]]
-            } else {
-                vec![vec!["-C".to_string(), "opt-level=3".to_string()]]
-            }
+            vec![get_rustc_flags(&mut slow_flag_values[0])]
         } else {
-            slow_runner_flags
+            for flag_value in slow_flag_values.iter_mut() {
+                flag_value.opt_level.get_or_insert("3".to_string());
+            }
+            render_compile_flags(&config.slow_compiler, slow_base_flags, &slow_flag_values)
         };
 
         let slow_runner_flags: Vec<Vec<&str>> = slow_runner_flags
@@ -461,40 +664,43 @@ fn runtime_diff_fuzzer(
                 "-fno-strict-aliasing",
                 "-fwrapv",
                 "-fsigned-char",
-                "-O1",
             ]
         };
-        if slow_runner_flags
-            .iter()
-            .any(|s| s.iter().any(|s| s.contains("march=rv32")))
-        {
+        let rv32 = slow_flag_values.iter().any(FlagSetValue::is_rv32);
+        if rv32 {
             fast_base_flags.append(&mut vec!["-m32", "-malign-double"]);
         }
 
         // Generate flags
-        let fast_runner_flags = get_compile_flags(
+        let mut fast_flag_values = get_flag_values(
             &config.fast_compiler,
             &config.generator,
             &Action::Execute,
-            fast_base_flags,
             flag_sets,
-        );
+        )?;
+        if let Some(corpus_testcase) = current_corpus_testcase(&config.generator) {
+            for flag_value in fast_flag_values.iter_mut() {
+                flag_value.push_flags(&corpus_testcase.options);
+            }
+        }
         let fast_runner_flags = if config
-            .slow_compiler
+            .fast_compiler
             .path
             .to_str()
             .unwrap()
             .contains("rustc")
         {
-            if !fast_runner_flags[0].is_empty() {
-                assert!(fast_runner_flags[0][0].starts_with("-march"));
-                let fast_runner_flags = format!("llvm-args={}", fast_runner_flags[0][0].clone());
-                vec![vec!["-C".to_string(), "opt-level=3".to_string()]]
-            } else {
-                vec![vec!["-C".to_string(), "opt-level=3".to_string()]]
-            }
+            // The fast runner is native so generated target flags don't apply
+            let flag_value = FlagSetValue {
+                opt_level: Some("3".to_string()),
+                ..Default::default()
+            };
+            vec![flag_value.rustc_args()]
         } else {
-            fast_runner_flags
+            for flag_value in fast_flag_values.iter_mut() {
+                flag_value.opt_level.get_or_insert("1".to_string());
+            }
+            render_compile_flags(&config.fast_compiler, fast_base_flags, &fast_flag_values)
         };
         let fast_runner_flags: Vec<Vec<&str>> = fast_runner_flags
             .iter()
@@ -509,12 +715,13 @@ fn runtime_diff_fuzzer(
                 .collect()
         };
 
-        if run_compiler(
+        let fast_compile_failed = run_compiler(
             &sh,
             &mut fast_compiler_ms,
             &mut stats.fast_compiler_stats,
             temp_dir,
             finds_dir,
+            id,
             &config.generator,
             &Action::Execute,
             fast_compilers,
@@ -522,8 +729,13 @@ fn runtime_diff_fuzzer(
             fast_generator_flags,
             &testcase_paths,
             "fast_compiler.out",
-        )? {
+        )?;
+        if new_testcase {
+            generator_stats.record_reference_compile(fast_compile_failed);
+        }
+        if fast_compile_failed {
             // timeout
+            new_testcase = false;
             continue;
         }
 
@@ -542,6 +754,7 @@ fn runtime_diff_fuzzer(
             &mut stats.fast_compiler_stats,
             temp_dir,
             finds_dir,
+            id,
             &fast_compiler_paths,
             &fast_runner_flags_strs,
             &testcase_paths,
@@ -560,6 +773,14 @@ fn runtime_diff_fuzzer(
 
         let fast_stdout = fast_stdout.unwrap();
 
+        if new_testcase {
+            let fast_output = fast_stdout.as_ref().map(|(stdout, _)| stdout.as_str());
+            if let Some(warning) = generator_stats.record_execution(fast_output) {
+                println!("{id_str} {warning}");
+            }
+            new_testcase = false;
+        }
+
         if fast_stdout.is_none() {
             // timeout
             continue;
@@ -586,6 +807,7 @@ fn runtime_diff_fuzzer(
             &mut stats.slow_compiler_stats,
             temp_dir,
             finds_dir,
+            id,
             &config.generator,
             &Action::Execute,
             slow_compilers,
@@ -615,6 +837,7 @@ fn runtime_diff_fuzzer(
             &mut stats.slow_compiler_stats,
             temp_dir,
             finds_dir,
+            id,
             &slow_compiler_paths,
             &slow_runner_flags_strs,
             &testcase_paths,
@@ -631,15 +854,67 @@ fn runtime_diff_fuzzer(
 
         let (fast_stdout, fast_stderr) = fast_stdout.unwrap();
         let (slow_stdout, slow_stderr) = slow_stdout.unwrap();
+        let slow_runner = config.slow_compiler.runner.clone().unwrap();
+        let mut sweep = None;
+
+        if fast_stdout == slow_stdout {
+            // Vector length agnostic code has to print the same thing under
+            // every legal VLEN and agnostic policy
+            let program = temp_dir.join("slow_compiler.out");
+            for sweep_runner in vector_sweep_runners(&slow_runner, &program)? {
+                let sweep_stdout = execute_program(
+                    &sh,
+                    &mut slow_exec_ms,
+                    &mut stats.slow_compiler_stats,
+                    temp_dir,
+                    finds_dir,
+                    id,
+                    &slow_compiler_paths,
+                    &slow_runner_flags_strs,
+                    &testcase_paths,
+                    &config.slow_compiler.architecture,
+                    &config.generator,
+                    &program,
+                    &sweep_runner,
+                )?;
+                if let Some((stdout, stderr)) = sweep_stdout {
+                    if stdout != slow_stdout {
+                        sweep = Some((sweep_runner, stdout, stderr));
+                        break;
+                    }
+                }
+            }
+        }
 
-        if fast_stdout != slow_stdout {
+        if fast_stdout != slow_stdout || sweep.is_some() {
             stats.mismatch += 1;
+            let ub_check_result = if config.ub_check.unwrap_or(false) {
+                // A check that couldn't run is recorded rather than losing
+                // the mismatch
+                Some(
+                    ub_check(&sh, &config.generator, &testcase_paths, rv32)
+                        .unwrap_or_else(|error| UbCheckResult::Failed(format!("{error:#}"))),
+                )
+            } else {
+                None
+            };
             // Save testcase
+            let is_ub = ub_check_result
+                .as_ref()
+                .is_some_and(|result| *result != UbCheckResult::Clean);
+            // Fuzzing continues after UB, so those finds need their own directory
+            let dump_dir = if is_ub {
+                finds_dir.join(format!(
+                    "{}-ub-{iter}",
+                    temp_dir.file_name().unwrap().to_str().unwrap()
+                ))
+            } else {
+                finds_dir.join(temp_dir.file_name().unwrap())
+            };
             println!("Runner id: {id_str}");
-            cmd!(sh, "cp -r {temp_dir} {finds_dir}")
+            cmd!(sh, "cp -r {temp_dir} {dump_dir}")
                 .run()
                 .context("When attempting to save failure to output directory")?;
-            let dump_dir = finds_dir.join(temp_dir.file_name().unwrap());
             if fast_compilers.len() == 1 {
                 sh.write_file(
                     dump_dir.join(format!("fast_compiler_opts.txt")),
@@ -675,44 +950,93 @@ fn runtime_diff_fuzzer(
                 .context("When attempting to save slow_stderr to output directory")?;
             sh.write_file(dump_dir.join("slow_stdout.txt"), &slow_stdout)
                 .context("When attempting to save slow_stdout to output directory")?;
+            if let Some((_, sweep_stdout, sweep_stderr)) = &sweep {
+                sh.write_file(dump_dir.join("sweep_stderr.txt"), sweep_stderr)
+                    .context("When attempting to save sweep_stderr to output directory")?;
+                sh.write_file(dump_dir.join("sweep_stdout.txt"), sweep_stdout)
+                    .context("When attempting to save sweep_stdout to output directory")?;
+            }
+            let fail_info = FailInfo::Runtime(RuntimeFailInfo {
+                fast_compiler: if fast_compilers.len() == 1 {
+                    vec![fast_compilers[0].path.clone()]
+                } else {
+                    (0..testcase_paths.len() + 1)
+                        .map(|_| config.fast_compiler.path.clone())
+                        .collect()
+                },
+                fast_architecture: config.fast_compiler.architecture.clone(),
+                fast_runner: config.fast_compiler.runner.clone().unwrap(),
+                slow_compiler: if slow_compilers.len() == 1 {
+                    vec![config.slow_compiler.path.clone()]
+                } else {
+                    (0..testcase_paths.len() + 1)
+                        .map(|_| config.slow_compiler.path.clone())
+                        .collect()
+                },
+                slow_architecture: config.slow_compiler.architecture.clone(),
+                slow_runner: slow_runner.clone(),
+                sweep_runner: sweep
+                    .as_ref()
+                    .map(|(sweep_runner, _, _)| Box::new(sweep_runner.clone())),
+                testcase: testcase_paths.clone(),
+                ub_check: ub_check_result.clone(),
+                build_identities: current_build_identities(),
+                first_bad_commit: None,
+                fail_type: Some(if sweep.is_some() {
+                    RuntimeFailType::VectorConfigMismatch
+                } else {
+                    RuntimeFailType::Mismatch
+                }),
+                generator: config.generator.clone(),
+                generator_args: current_generator_args(&config.generator),
+                corpus_testcase: current_corpus_testcase(&config.generator)
+                    .map(|testcase| testcase.path),
+            });
             sh.write_file(
                 dump_dir.join("fail_info.yaml"),
-                serde_yaml::to_string(&FailInfo::Runtime(RuntimeFailInfo {
-                    fast_compiler: if fast_compilers.len() == 1 {
-                        vec![fast_compilers[0].path.clone()]
-                    } else {
-                        (0..testcase_paths.len() + 1)
-                            .map(|_| config.fast_compiler.path.clone())
-                            .collect()
-                    },
-                    fast_architecture: config.fast_compiler.architecture.clone(),
-                    fast_runner: config.fast_compiler.runner.clone().unwrap(),
-                    slow_compiler: if slow_compilers.len() == 1 {
-                        vec![config.slow_compiler.path.clone()]
-                    } else {
-                        (0..testcase_paths.len() + 1)
-                            .map(|_| config.slow_compiler.path.clone())
-                            .collect()
-                    },
-                    slow_architecture: config.slow_compiler.architecture.clone(),
-                    slow_runner: config.slow_compiler.runner.clone().unwrap(),
-                    testcase: testcase_paths,
-                    fail_type: Some(RuntimeFailType::Mismatch),
-                    generator: config.generator.clone(),
-                }))
-                .unwrap(),
+                serde_yaml::to_string(&fail_info).unwrap(),
             )
             .context("When attempting to save run info to output directory")?;
+            let slow_flags = slow_runner_flags
+                .iter()
+                .map(|flags| flags.join(" "))
+                .collect::<Vec<_>>()
+                .join(" ; ");
+            record_find(
+                &sh,
+                finds_dir,
+                id,
+                &dump_dir,
+                &fail_info,
+                &slow_flags,
+                sweep
+                    .as_ref()
+                    .map_or(&slow_stderr, |(_, _, sweep_stderr)| sweep_stderr),
+            )
+            .context("When attempting to record the find")?;
+            if is_ub && !matches!(config.generator, FuzzGenerator::Fixed(..)) {
+                // Kept so UB false positives can be looked at, but not worth
+                // stopping for. A fixed testcase would just hit it again.
+                println!("{id_str} Mismatch is UB, dumped to {:?}", dump_dir);
+                ub_testcase = true;
+                continue;
+            }
+            if let Some((sweep_runner, sweep_stdout, _)) = &sweep {
+                panic!(
+                    "Vector config mismatch {} != {}\n Slow runner: {:?}\n Sweep runner: {:?}\n Dumped to {:?}",
+                    slow_stdout, sweep_stdout, slow_runner, sweep_runner, dump_dir
+                );
+            }
             panic!(
-                "Stdout mismatch {} != {}\n Dumped to {:?}",
-                fast_stdout, slow_stdout, dump_dir
+                "Stdout mismatch {} != {}\n Slow runner: {:?}\n Dumped to {:?}",
+                fast_stdout, slow_stdout, slow_runner, dump_dir
             );
         }
 
         iter += 1;
         if (iter & (iter - 1)) == 0 {
             println!(
-                "{:>2} Iteration: {:>4} - Avg iter length: {:>4} ms (Generator {:>4} ms, fast|slow compile {:>4}|{:>4} ms, fast|slow exec {:>4}|{:>4} ms) fast|slow c timeout %: {:>3.3}|{:>3.3} fast|slow exec timeout %: {:>3.3}|{:>3.3}",
+                "{:>2} Iteration: {:>4} - Avg iter length: {:>4} ms (Generator {:>4} ms, fast|slow compile {:>4}|{:>4} ms, fast|slow exec {:>4}|{:>4} ms) fast|slow c timeout %: {:>3.3}|{:>3.3} fast|slow exec timeout %: {:>3.3}|{:>3.3} slow exec unsupported: {} - {}",
 		id_str,
 		iter,
                 program_timer.elapsed().as_millis() / iter,
@@ -725,7 +1049,10 @@ fn runtime_diff_fuzzer(
 		((stats.slow_compiler_stats.compile_timeout as f64) / (stats.slow_compiler_stats.compile_timeout + stats.slow_compiler_stats.compile_error + stats.slow_compiler_stats.compile_success) as f64) * 100.,
 		((stats.fast_compiler_stats.execute_timeout as f64) / (stats.fast_compiler_stats.execute_timeout + stats.fast_compiler_stats.execute_error + stats.fast_compiler_stats.execute_success) as f64) * 100.,
 		((stats.slow_compiler_stats.execute_timeout as f64) / (stats.slow_compiler_stats.execute_timeout + stats.slow_compiler_stats.execute_error + stats.slow_compiler_stats.execute_success) as f64) * 100.,
+		stats.slow_compiler_stats.execute_unsupported,
+		generator_stats.summary(),
             );
+            generator_stats.write(&sh, &generator_stats_path(finds_dir, &id_str))?;
 
             // If we're spending more than 5 ms/iter on rust code, something's going on.
             // Investigate the cause of the regression
@@ -0,0 +1,169 @@
+use argh::FromArgs;
+use compiler_fuzz::reproduce::{
+    copy_reduction, read_fail_info, reproduces, uncategorize, write_fail_info,
+};
+use compiler_fuzz::{csv_field, FailInfo, PathRemap, ReductionConfig};
+use env_logger::Env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use xshell::Shell;
+
+#[derive(FromArgs)]
+#[argh(
+    description = "Rerun reduced failures against a newer compiler to find the fixed ones
+Use RUST_LOG=off to turn off logging"
+)]
+struct RecheckArgs {
+    /// directory of directories produced by reduce
+    #[argh(positional)]
+    reductions_directory: PathBuf,
+
+    /// compiler to test instead of the one in each fail_info.yaml. For
+    /// runtime fails only the slow compiler is replaced.
+    #[argh(option)]
+    compiler: Option<PathBuf>,
+
+    /// replace a path prefix in each fail_info.yaml (old=new), e.g. when the
+    /// finds came from a container
+    #[argh(option)]
+    remap: Vec<PathRemap>,
+
+    /// where to write the summary CSV (default:
+    /// <reductions_directory>/recheck.csv)
+    #[argh(option)]
+    output: Option<PathBuf>,
+}
+
+enum RecheckStatus {
+    StillFailing,
+    Fixed,
+    /// Still fails, but not the way it was categorized
+    FailingDifferently,
+    /// The interestingness test itself broke
+    Unknown,
+}
+
+impl fmt::Display for RecheckStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                RecheckStatus::StillFailing => "still failing",
+                RecheckStatus::Fixed => "fixed",
+                RecheckStatus::FailingDifferently => "failing differently",
+                RecheckStatus::Unknown => "unknown",
+            }
+        )
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+
+    let args: RecheckArgs = argh::from_env();
+
+    let reductions_dir = &fs::canonicalize(&args.reductions_directory)?;
+    let sh = Shell::new()?;
+    let config = ReductionConfig {
+        remap: Some(args.remap),
+        compiler: args.compiler,
+        ..Default::default()
+    };
+
+    let mut reduction_dirs = vec![];
+    for entry in fs::read_dir(reductions_dir)? {
+        let path = entry?.path();
+        if path.join("fail_info.yaml").exists() {
+            reduction_dirs.push(path);
+        }
+    }
+    reduction_dirs.sort();
+
+    let mut csv = String::from("Reduction,Fail type,First bad commit,Status\n");
+    let mut counts = [0; 4];
+    for reduction_dir in &reduction_dirs {
+        let fail_info = read_fail_info(&sh, reduction_dir)?;
+        let status = recheck(&sh, reduction_dir, &config)?;
+        let name = reduction_dir.file_name().unwrap().to_string_lossy();
+        println!("{name}: {status}");
+
+        counts[match status {
+            RecheckStatus::StillFailing => 0,
+            RecheckStatus::Fixed => 1,
+            RecheckStatus::FailingDifferently => 2,
+            RecheckStatus::Unknown => 3,
+        }] += 1;
+
+        let (fail_type, first_bad_commit) = match &fail_info {
+            FailInfo::Ice(ice_fail_info) => (
+                format!("{:?}", ice_fail_info.fail_type),
+                &ice_fail_info.first_bad_commit,
+            ),
+            FailInfo::Execution(exec_fail_info) => (
+                format!("{:?}", exec_fail_info.fail_type),
+                &exec_fail_info.first_bad_commit,
+            ),
+            FailInfo::Runtime(runtime_fail_info) => (
+                format!("{:?}", runtime_fail_info.fail_type),
+                &runtime_fail_info.first_bad_commit,
+            ),
+        };
+        csv.push_str(&format!(
+            "{},{},{},{status}\n",
+            csv_field(&name),
+            csv_field(&fail_type),
+            csv_field(first_bad_commit.as_deref().unwrap_or_default()),
+        ));
+    }
+
+    let output = args
+        .output
+        .unwrap_or_else(|| reductions_dir.join("recheck.csv"));
+    sh.write_file(&output, csv)?;
+
+    println!(
+        "Still failing: {}, fixed: {}, failing differently: {}, unknown: {}",
+        counts[0], counts[1], counts[2], counts[3]
+    );
+    println!("Summary written to {}", output.display());
+
+    Ok(())
+}
+
+fn recheck(
+    sh: &Shell,
+    reduction_dir: &Path,
+    config: &ReductionConfig,
+) -> anyhow::Result<RecheckStatus> {
+    let work_temp_dir = copy_reduction(sh, reduction_dir, config)?;
+    let work_dir = work_temp_dir.path();
+
+    // A missing compiler would otherwise look like a fix
+    let compilers = match read_fail_info(sh, work_dir)? {
+        FailInfo::Ice(ice_fail_info) => ice_fail_info.compilers,
+        FailInfo::Execution(exec_fail_info) => exec_fail_info.compiler,
+        FailInfo::Runtime(runtime_fail_info) => runtime_fail_info.slow_compiler,
+    };
+    for compiler in compilers {
+        anyhow::ensure!(compiler.exists(), "Compiler {compiler:?} doesn't exist");
+    }
+
+    match reproduces(work_dir)? {
+        Some(true) => return Ok(RecheckStatus::StillFailing),
+        Some(false) => (),
+        None => return Ok(RecheckStatus::Unknown),
+    }
+
+    // Not the categorized fail anymore, see if it fails some other way
+    let mut fail_info = read_fail_info(sh, work_dir)?;
+    uncategorize(&mut fail_info);
+    write_fail_info(sh, work_dir, &fail_info)?;
+
+    Ok(match reproduces(work_dir)? {
+        Some(true) => RecheckStatus::FailingDifferently,
+        Some(false) => RecheckStatus::Fixed,
+        None => RecheckStatus::Unknown,
+    })
+}
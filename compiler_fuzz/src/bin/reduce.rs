@@ -1,14 +1,28 @@
+use anyhow::Context;
 use argh::FromArgs;
 use compiler_flags_gen::Action;
+use compiler_fuzz::bug_report::{
+    compiler_identity, gcc_target, labels, march, render_template, report_template, summary,
+    version,
+};
+use compiler_fuzz::execute::{qemu_aarch64_cpu, spike_fail_type, spike_isa, spike_pk};
+use compiler_fuzz::finds_db::record_reduction;
 use compiler_fuzz::generate::get_generator_flags;
+use compiler_fuzz::godbolt::{self, ClientState};
+use compiler_fuzz::qemu::riscv_qemu_cpu;
+use compiler_fuzz::reduction::{apply_reduction_config, crash_signature};
+use compiler_fuzz::reduction_state::{
+    read_reduction_state, write_reduction_state, ReductionPhase, ReductionState,
+};
+use compiler_fuzz::reproduce::{read_fail_info, write_fail_info};
 use compiler_fuzz::{
-    ignorable_warnings, ExecFailInfo, ExecFailType, FailInfo, FuzzGenerator, GccFailType,
-    IceFailInfo, IceFailType, LlvmFailType, QemuFailType, Runner, RuntimeFailInfo, RuntimeFailType,
+    ignorable_warnings_for, is_cpp, is_llvm_ir, llvm_tool, ExecFailInfo, ExecFailType, FailInfo,
+    FuzzGenerator, GccFailType, IceFailInfo, IceFailType, LlvmFailType, PathRemap, QemuFailType,
+    ReductionConfig, Runner, RuntimeFailInfo, RuntimeFailType,
 };
 use env_logger::Env;
 use std::env;
-use std::fs::{self, File};
-use std::io::Read;
+use std::fs;
 use std::iter::once;
 use std::path::{Path, PathBuf};
 use xshell::{cmd, Shell};
@@ -24,13 +38,27 @@ struct FuzzArgs {
     #[argh(positional)]
     reduction_directory: PathBuf,
 
-    /// resume an existing reduction
+    /// resume an existing reduction at its first unfinished phase
     #[argh(switch, short = 'r')]
     resume_existing: bool,
 
+    /// run only this phase of an existing reduction (preprocessed,
+    /// categorized, flags-reduced, source-reduced, ir-extracted or reported)
+    #[argh(option)]
+    phase: Option<ReductionPhase>,
+
     /// skip c reduction
     #[argh(switch, short = 's')]
     skip_c: bool,
+
+    /// reduction config yaml with paths for this machine
+    #[argh(option)]
+    config: Option<PathBuf>,
+
+    /// replace a path prefix in fail_info.yaml (old=new), applied after the
+    /// config's remaps. Paths are only remapped when the reduction starts.
+    #[argh(option)]
+    remap: Vec<PathRemap>,
 }
 
 fn main() -> anyhow::Result<()> {
@@ -43,15 +71,15 @@ fn main() -> anyhow::Result<()> {
         "Cannot reduce in the same directory as the fail!"
     );
 
+    let continuing = args.resume_existing || args.phase.is_some();
+
     // Create reduction directory
     fs::create_dir_all(&args.reduction_directory).unwrap();
     let reduction_dir = &fs::canonicalize(&args.reduction_directory).unwrap();
     let fail_dir = &fs::canonicalize(&args.fail_directory).unwrap();
 
     assert!(
-        !reduction_dir.exists()
-            || reduction_dir.read_dir()?.next().is_none()
-            || args.resume_existing,
+        !reduction_dir.exists() || reduction_dir.read_dir()?.next().is_none() || continuing,
         "Reduction directory({:?}) already has files!",
         reduction_dir
     );
@@ -62,259 +90,475 @@ fn main() -> anyhow::Result<()> {
     let sh = Shell::new()?;
     sh.change_dir(reduction_dir);
 
-    if !args.resume_existing {
+    if !continuing {
         cmd!(sh, "cp -r {fail_dir}/. {reduction_dir}").run()?;
     }
 
-    let mut file = File::open(reduction_dir.join("fail_info.yaml")).unwrap();
-    let mut data = String::new();
-    file.read_to_string(&mut data).unwrap();
-
-    let mut fail_info: FailInfo = serde_yaml::from_str(&data).unwrap();
-
-    if args.resume_existing {
-        for testcase in match &fail_info {
-            FailInfo::Ice(ice_info) => ice_info.testcases.clone(),
-            FailInfo::Execution(exec_info) => exec_info.testcase.clone(),
-            FailInfo::Runtime(runtime_info) => runtime_info.testcase.clone(),
-        } {
-            assert!(
-                &args
-                    .reduction_directory
-                    .join(testcase.to_str().unwrap().to_string() + ".orig")
-                    .exists(),
-                "testcase: {} doesn't exist! Start a fresh reduction",
-                testcase.to_str().unwrap().to_string() + ".orig"
-            )
-        }
+    let mut fail_info = read_fail_info(&sh, reduction_dir)?;
+
+    let mut config: ReductionConfig = match &args.config {
+        Some(config_path) => serde_yaml::from_str(&fs::read_to_string(config_path)?)?,
+        None => ReductionConfig::default(),
+    };
+    if !args.remap.is_empty() {
+        config
+            .remap
+            .get_or_insert_with(Vec::new)
+            .extend(args.remap.iter().cloned());
+    }
+    // The interestingness tests read fail_info.yaml from the reduction
+    // directory, so they see the local paths too. Resumed reductions already
+    // have them, and remapping again isn't safe when the new prefix contains
+    // the old one.
+    if !continuing {
+        apply_reduction_config(&config, &mut fail_info)?;
+        write_fail_info(&sh, reduction_dir, &fail_info)?;
     }
 
-    let testcases = reduce(
-        &sh,
-        &mut fail_info,
-        reduction_dir,
-        args.resume_existing,
-        args.skip_c,
-    )?;
+    let mut state = match read_reduction_state(&sh, reduction_dir)? {
+        Some(state) => state,
+        None if continuing => legacy_reduction_state(reduction_dir, &fail_info),
+        None => ReductionState::default(),
+    };
 
-    // Reload fail info
-    let mut file = File::open(reduction_dir.join("fail_info.yaml")).unwrap();
-    let mut data = String::new();
-    file.read_to_string(&mut data).unwrap();
-    let fail_info: FailInfo = serde_yaml::from_str(&data).unwrap();
+    let phases = match args.phase {
+        Some(phase) => {
+            if let Some(unfinished) = ReductionPhase::ALL
+                .into_iter()
+                .take_while(|earlier| *earlier < phase)
+                .find(|earlier| !state.is_completed(*earlier))
+            {
+                anyhow::bail!("Phase {phase} needs {unfinished} to have finished first");
+            }
+            vec![phase]
+        }
+        None => match state.next_phase() {
+            Some(next_phase) => ReductionPhase::ALL
+                .into_iter()
+                .filter(|phase| *phase >= next_phase)
+                .collect(),
+            None => {
+                println!("Reduction has already been reported");
+                vec![]
+            }
+        },
+    };
+
+    for phase in phases {
+        println!("Reduction phase: {phase}");
+        run_phase(
+            &sh,
+            phase,
+            &mut fail_info,
+            fail_dir,
+            reduction_dir,
+            &config,
+            args.skip_c,
+        )?;
+        write_fail_info(&sh, reduction_dir, &fail_info)?;
 
-    report(&sh, testcases, reduction_dir, fail_info)?;
+        state.complete(phase);
+        write_reduction_state(&sh, reduction_dir, &state)?;
+    }
 
     Ok(())
 }
 
-// TODO: Add reduction config that allows the user to define a mapping from
-// failure compiler to a new path.
-fn reduce(
+/// Reductions from before phases were recorded could only be resumed once
+/// creduce had started, which left the .orig testcases behind
+fn legacy_reduction_state(reduction_dir: &Path, fail_info: &FailInfo) -> ReductionState {
+    for testcase in testcases(fail_info) {
+        let orig = testcase.to_str().unwrap().to_string() + ".orig";
+        assert!(
+            reduction_dir.join(&orig).exists(),
+            "testcase: {orig} doesn't exist! Start a fresh reduction"
+        )
+    }
+
+    let mut state = ReductionState::default();
+    state.complete(ReductionPhase::Preprocessed);
+    state
+}
+
+fn run_phase(
     sh: &Shell,
+    phase: ReductionPhase,
     fail_info: &mut FailInfo,
-    reduction_dir: &PathBuf,
-    resume_existing: bool,
+    fail_dir: &Path,
+    reduction_dir: &Path,
+    config: &ReductionConfig,
     skip_c: bool,
-) -> anyhow::Result<Vec<PathBuf>> {
-    let testcases = &if !resume_existing {
-        preprocess(sh, reduction_dir, fail_info)?
-    } else {
-        match &fail_info {
-            FailInfo::Ice(ice_info) => ice_info.testcases.clone(),
-            FailInfo::Execution(exec_info) => exec_info.testcase.clone(),
-            FailInfo::Runtime(runtime_info) => runtime_info.testcase.clone(),
+) -> anyhow::Result<()> {
+    match phase {
+        ReductionPhase::Preprocessed => preprocess_testcases(sh, reduction_dir, fail_info),
+        ReductionPhase::Categorized => categorize(sh, reduction_dir, fail_info),
+        ReductionPhase::FlagsReduced => reduce_flags(sh, reduction_dir, fail_info),
+        ReductionPhase::SourceReduced => {
+            reduce_source(sh, reduction_dir, fail_info, config, skip_c)
+        }
+        ReductionPhase::IrExtracted => extract_ir(sh, reduction_dir, fail_info, config),
+        ReductionPhase::Reported => {
+            // Only record the reduction once the report is written so a
+            // failed report isn't recorded as reduced
+            report(sh, reduction_dir, fail_info, config)?;
+            record_reduction(fail_dir, reduction_dir, fail_info)
         }
-    };
-
-    for testcase in testcases {
-        assert!(reduction_dir.join(testcase).exists());
     }
+}
 
+fn testcases(fail_info: &FailInfo) -> Vec<PathBuf> {
     match fail_info {
-        FailInfo::Ice(ice_fail_info) => {
-            ice_fail_info.testcases = testcases.to_vec();
-        }
-        FailInfo::Execution(exec_fail_info) => {
-            exec_fail_info.testcase = testcases.to_vec();
-        }
-        FailInfo::Runtime(runtime_fail_info) => {
-            runtime_fail_info.testcase = testcases.to_vec();
-        }
+        FailInfo::Ice(ice_info) => ice_info.testcases.clone(),
+        FailInfo::Execution(exec_info) => exec_info.testcase.clone(),
+        FailInfo::Runtime(runtime_info) => runtime_info.testcase.clone(),
     }
-    sh.write_file("fail_info.yaml", serde_yaml::to_string(&fail_info).unwrap())?;
-
-    // Now we can reduce it using reduce-ice
-    let current_exe = env::current_exe()?;
-    let current_dir = current_exe.parent().unwrap();
+}
 
-    // Get additional files used by generator
-    let additional_files = &match match fail_info {
-        FailInfo::Ice(ice_fail_info) => ice_fail_info.generator.clone(),
-        FailInfo::Execution(exec_fail_info) => exec_fail_info.generator.clone(),
-        FailInfo::Runtime(runtime_fail_info) => runtime_fail_info.generator.clone(),
+/// Additional files used by the generator
+fn additional_files(fail_info: &FailInfo) -> Vec<String> {
+    match match fail_info {
+        FailInfo::Ice(ice_fail_info) => &ice_fail_info.generator,
+        FailInfo::Execution(exec_fail_info) => &exec_fail_info.generator,
+        FailInfo::Runtime(runtime_fail_info) => &runtime_fail_info.generator,
     } {
         FuzzGenerator::Csmith(_) => vec![],
+        FuzzGenerator::Mutate(_) => vec![],
         FuzzGenerator::Yarpgen(_) => vec!["init.h".to_string()],
         FuzzGenerator::Rustsmith(_) => vec![],
         FuzzGenerator::Fixed(_) => vec![],
+        FuzzGenerator::Corpus(_) => vec![],
+        FuzzGenerator::LlvmIr(_) => vec![],
+    }
+}
+
+/// The interestingness test for the fail (reduce_ice, reduce_exec or
+/// reduce_runtime, next to this binary) and the reducible flag files it reads
+fn interestingness_test(fail_info: &FailInfo) -> anyhow::Result<(PathBuf, Vec<String>)> {
+    let current_exe = env::current_exe()?;
+    let current_dir = current_exe.parent().unwrap();
+
+    let testcase_count = testcases(fail_info).len();
+    let reducible_compiler_opts = |prefix: &str| {
+        if testcase_count == 1 {
+            vec![format!("{prefix}reducible_compiler_opts.txt")]
+        } else {
+            (0..testcase_count + 1)
+                .map(|i| format!("{prefix}reducible_compiler_opts_{i}.txt"))
+                .collect()
+        }
     };
 
+    Ok(match fail_info {
+        FailInfo::Ice(_) => (current_dir.join("reduce_ice"), reducible_compiler_opts("")),
+        FailInfo::Execution(_) => (
+            current_dir.join("reduce_exec"),
+            vec!["reducible_compiler_opts.txt".to_string()],
+        ),
+        FailInfo::Runtime(_) => (
+            current_dir.join("reduce_runtime"),
+            [
+                reducible_compiler_opts("fast_"),
+                reducible_compiler_opts("slow_"),
+            ]
+            .concat(),
+        ),
+    })
+}
+
+fn preprocess_testcases(
+    sh: &Shell,
+    reduction_dir: &Path,
+    fail_info: &mut FailInfo,
+) -> anyhow::Result<()> {
+    let original_testcases = testcases(fail_info);
+    let testcases = preprocess(sh, reduction_dir, fail_info)?;
+    let testcases = if is_cpp(&original_testcases) {
+        // Keep the C++ extension so creduce's clang_delta passes parse
+        // the testcase as C++
+        let mut cpp_testcases = vec![];
+        for testcase in testcases {
+            if testcase.extension().is_some_and(|ext| ext == "c") {
+                let cpp_testcase = testcase.with_extension("cpp");
+                sh.copy_file(&testcase, &cpp_testcase)?;
+                cpp_testcases.push(cpp_testcase);
+            } else {
+                cpp_testcases.push(testcase);
+            }
+        }
+        cpp_testcases
+    } else {
+        testcases
+    };
+
+    for testcase in &testcases {
+        assert!(reduction_dir.join(testcase).exists());
+    }
+
     match fail_info {
         FailInfo::Ice(ice_fail_info) => {
-            let reduce_ice = current_dir.join("reduce_ice");
+            ice_fail_info.testcases = testcases;
+        }
+        FailInfo::Execution(exec_fail_info) => {
+            exec_fail_info.testcase = testcases;
+        }
+        FailInfo::Runtime(runtime_fail_info) => {
+            runtime_fail_info.testcase = testcases;
+        }
+    }
 
-            println!("Test run (with REDUCTION_DIR={:?}):", reduction_dir);
+    Ok(())
+}
 
-            let reducible_compiler_opts = &if testcases.len() == 1 {
-                vec!["reducible_compiler_opts.txt".to_string()]
-            } else {
-                (0..testcases.len() + 1)
-                    .map(|i| format!("reducible_compiler_opts_{i}.txt"))
-                    .collect()
-            };
+fn categorize(sh: &Shell, reduction_dir: &Path, fail_info: &mut FailInfo) -> anyhow::Result<()> {
+    if let FailInfo::Ice(ice_fail_info) = fail_info {
+        if is_llvm_ir(&ice_fail_info.testcases) {
+            // Generated IR never went through a frontend, the fuzzer's stderr
+            // is all there is to go on
+            ice_fail_info.fail_type = categorize_ice_fail(sh, reduction_dir, ice_fail_info)?;
+            return Ok(());
+        }
+    }
 
-            let _env_var = sh.push_env("REDUCTION_DIR", reduction_dir);
+    let (test, reducible_compiler_opts) = &interestingness_test(fail_info)?;
+    let testcases = &testcases(fail_info);
 
-            cmd!(
-                sh,
-                "{reduce_ice} {testcases...} {reducible_compiler_opts...}"
-            )
-            .run()?;
+    println!("Test run (with REDUCTION_DIR={:?}):", reduction_dir);
 
-            ice_fail_info.fail_type = categorize_ice_fail(sh, reduction_dir, ice_fail_info)?;
-            sh.write_file("fail_info.yaml", serde_yaml::to_string(&fail_info).unwrap())?;
+    let _env_var = sh.push_env("REDUCTION_DIR", reduction_dir);
 
-            cmd!(
-                sh,
-                "{reduce_ice} {testcases...} {reducible_compiler_opts...} {additional_files...}"
-            )
-            .run()?;
+    cmd!(sh, "{test} {testcases...} {reducible_compiler_opts...}").run()?;
 
-            if !skip_c {
-                cmd!(
-                    sh,
-                    "creduce --timeout 10 {reduce_ice} {testcases...} {reducible_compiler_opts...} {additional_files...}"
-                )
-                .run()?;
-            }
+    match fail_info {
+        FailInfo::Ice(ice_fail_info) => {
+            ice_fail_info.fail_type = categorize_ice_fail(sh, reduction_dir, ice_fail_info)?;
         }
         FailInfo::Execution(exec_fail_info) => {
-            let reduce_exec = current_dir.join("reduce_exec");
-
-            println!("Test run (with REDUCTION_DIR={:?}):", reduction_dir);
+            exec_fail_info.fail_type = categorize_exec_fail(sh, reduction_dir, exec_fail_info)?;
+        }
+        FailInfo::Runtime(runtime_fail_info) => {
+            runtime_fail_info.fail_type = Some(categorize_runtime_fail(
+                sh,
+                reduction_dir,
+                runtime_fail_info,
+            )?);
+        }
+    }
 
-            let _env_var = sh.push_env("REDUCTION_DIR", reduction_dir);
+    Ok(())
+}
 
-            cmd!(sh, "{reduce_exec} {testcases...} reducible_compiler_opts").run()?;
+/// Drop the compiler flags one at a time, keeping each removal the
+/// interestingness test still passes without. Much cheaper than leaving the
+/// flags to creduce, which reduces them alongside the source.
+fn reduce_flags(sh: &Shell, reduction_dir: &Path, fail_info: &FailInfo) -> anyhow::Result<()> {
+    if let FailInfo::Ice(ice_fail_info) = fail_info {
+        if is_llvm_ir(&ice_fail_info.testcases) {
+            // llvm-reduce only reduces the IR
+            return Ok(());
+        }
+    }
 
-            exec_fail_info.fail_type = categorize_exec_fail(sh, reduction_dir, exec_fail_info)?;
-            sh.write_file("fail_info.yaml", serde_yaml::to_string(&fail_info).unwrap())?;
+    let (test, reducible_compiler_opts) = &interestingness_test(fail_info)?;
+    let testcases = &testcases(fail_info);
+    let additional_files = &additional_files(fail_info);
+
+    let _env_var = sh.push_env("REDUCTION_DIR", reduction_dir);
+
+    let interesting = || -> anyhow::Result<bool> {
+        Ok(cmd!(
+            sh,
+            "{test} {testcases...} {reducible_compiler_opts...} {additional_files...}"
+        )
+        .quiet()
+        .ignore_status()
+        .output()?
+        .status
+        .success())
+    };
 
-            cmd!(
-                sh,
-                "{reduce_exec} {testcases...} reducible_compiler_opts.txt {additional_files...}"
-            )
-            .run()?;
+    anyhow::ensure!(
+        interesting()?,
+        "The fail doesn't reproduce before reducing the flags"
+    );
 
-            if !skip_c {
-                cmd!(
-                    sh,
-                    "creduce --timeout 10 {reduce_exec} {testcases...} reducible_compiler_opts.txt {additional_files...}"
-                )
-                .run()?;
+    for opts_file in reducible_compiler_opts {
+        let mut flags = join_split_flags(&sh.read_file(opts_file)?)
+            .split_whitespace()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        let flag_count = flags.len();
+
+        // Back to front, later flags override earlier ones
+        for i in (0..flags.len()).rev() {
+            let flag = flags.remove(i);
+            sh.write_file(opts_file, split_flags_str(&flags.join(" ")))?;
+            if !interesting()? {
+                flags.insert(i, flag);
             }
         }
-        FailInfo::Runtime(runtime_fail_info) => {
-            let reduce_runtime = current_dir.join("reduce_runtime");
+        sh.write_file(opts_file, split_flags_str(&flags.join(" ")))?;
 
-            println!("Test run (with REDUCTION_DIR={:?}):", reduction_dir);
+        println!(
+            "{opts_file}: {flag_count} -> {} flags: {}",
+            flags.len(),
+            flags.join(" ")
+        );
+    }
 
-            let fast_reducible_compiler_opts = &if testcases.len() == 1 {
-                vec!["fast_reducible_compiler_opts.txt".to_string()]
-            } else {
-                (0..testcases.len() + 1)
-                    .map(|i| format!("fast_reducible_compiler_opts_{i}.txt"))
-                    .collect()
-            };
-            let slow_reducible_compiler_opts = &if testcases.len() == 1 {
-                vec!["slow_reducible_compiler_opts.txt".to_string()]
-            } else {
-                (0..testcases.len() + 1)
-                    .map(|i| format!("slow_reducible_compiler_opts_{i}.txt"))
-                    .collect()
-            };
+    // Leave the test's output for the reduced flags behind rather than the
+    // last removal that was tried
+    cmd!(
+        sh,
+        "{test} {testcases...} {reducible_compiler_opts...} {additional_files...}"
+    )
+    .run()?;
 
-            let _env_var = sh.push_env("REDUCTION_DIR", reduction_dir);
+    Ok(())
+}
 
-            cmd!(
-                sh,
-                "{reduce_runtime} {testcases...} {fast_reducible_compiler_opts...} {slow_reducible_compiler_opts...}"
-            )
-            .run()?;
+fn reduce_source(
+    sh: &Shell,
+    reduction_dir: &Path,
+    fail_info: &FailInfo,
+    config: &ReductionConfig,
+    skip_c: bool,
+) -> anyhow::Result<()> {
+    if let FailInfo::Ice(ice_fail_info) = fail_info {
+        if is_llvm_ir(&ice_fail_info.testcases) {
+            // Generated IR never went through a frontend, so there is no
+            // source to creduce
+            return reduce_llvm_ir(sh, reduction_dir, ice_fail_info, config);
+        }
+    }
 
-            runtime_fail_info.fail_type = Some(categorize_runtime_fail(
-                sh,
-                reduction_dir,
-                runtime_fail_info,
-            )?);
-            sh.write_file("fail_info.yaml", serde_yaml::to_string(&fail_info).unwrap())?;
+    let (test, reducible_compiler_opts) = &interestingness_test(fail_info)?;
+    let testcases = &testcases(fail_info);
+    let additional_files = &additional_files(fail_info);
 
-            cmd!(
-                sh,
-                "{reduce_runtime} {testcases...} {fast_reducible_compiler_opts...} {slow_reducible_compiler_opts...} {additional_files...}"
-            )
-            .run()?;
+    let _env_var = sh.push_env("REDUCTION_DIR", reduction_dir);
 
-            if !skip_c {
-                cmd!(
-                    sh,
-                    "creduce --timeout 10 {reduce_runtime} {testcases...} {fast_reducible_compiler_opts...} {slow_reducible_compiler_opts...} {additional_files...}"
-                )
-                .run()?;
-            }
-        }
+    cmd!(
+        sh,
+        "{test} {testcases...} {reducible_compiler_opts...} {additional_files...}"
+    )
+    .run()?;
+
+    if !skip_c {
+        cmd!(
+            sh,
+            "creduce --timeout 10 {test} {testcases...} {reducible_compiler_opts...} {additional_files...}"
+        )
+        .run()?;
     }
 
-    Ok(testcases.to_owned())
+    Ok(())
 }
 
-fn report(
+/// Reconstruct the compiler opts creduce left split up, one set per
+/// compiler
+fn reduced_opts(
     sh: &Shell,
-    testcases: Vec<PathBuf>,
     reduction_dir: &Path,
-    fail_info: FailInfo,
+    testcases: &[PathBuf],
+) -> anyhow::Result<Vec<String>> {
+    if testcases.len() == 1 {
+        Ok(vec![join_split_flags(&sh.read_file(
+            reduction_dir.join("reducible_compiler_opts.txt"),
+        )?)])
+    } else {
+        (0..testcases.len() + 1)
+            .map(|i| {
+                Ok(join_split_flags(&sh.read_file(
+                    reduction_dir.join(format!("reducible_compiler_opts_{i}.txt")),
+                )?))
+            })
+            .collect()
+    }
+}
+
+fn extract_ir(
+    sh: &Shell,
+    reduction_dir: &Path,
+    fail_info: &FailInfo,
+    config: &ReductionConfig,
 ) -> anyhow::Result<()> {
-    // Reconstruct compiler opts
-    let reduced_opts = &if testcases.len() == 1 {
-        let split_opts = sh.read_file(reduction_dir.join("reducible_compiler_opts.txt"))?;
-        let reduced_opts = split_opts.replace("\n\n", " ");
-        let reduced_opts = reduced_opts.replace("\n", "");
+    if let FailInfo::Runtime(_) = fail_info {
+        // Runtime reductions aren't reported yet
+        return Ok(());
+    }
+
+    let reduced_opts = reduced_opts(sh, reduction_dir, &testcases(fail_info))?;
+    if reduced_opts.len() == 1 {
         sh.write_file(
             reduction_dir.join("reduced_compiler_opts.txt"),
-            &reduced_opts,
+            &reduced_opts[0],
         )?;
-        vec![reduced_opts]
     } else {
-        let reduced_opts: Vec<String> = (0..testcases.len() + 1)
-            .map(|i| {
-                let split_opts = sh
-                    .read_file(reduction_dir.join(format!("reducible_compiler_opts_{i}.txt")))
-                    .unwrap();
-                let reduced_opts = split_opts.replace("\n\n", " ");
-                reduced_opts.replace("\n", "")
-            })
-            .collect();
-        let _ = reduced_opts.iter().enumerate().map(|(i, flags)| {
+        for (i, flags) in reduced_opts.iter().enumerate() {
             sh.write_file(
                 reduction_dir.join(format!("reduced_compiler_opts_{i}.txt")),
                 flags,
-            )
-            .unwrap();
-        });
-        reduced_opts
+            )?;
+        }
+    }
+
+    let FailInfo::Ice(fail_info) = fail_info else {
+        // Only compiler crashes can be reduced further as IR
+        return Ok(());
     };
 
+    match fail_info.fail_type {
+        IceFailType::Llvm(Some(LlvmFailType::Llc)) => {
+            // We can reduce llvm further
+            extract_llvm_ir_llc(sh, reduction_dir, fail_info, config)?;
+        }
+        IceFailType::Llvm(Some(LlvmFailType::Frontend)) => {
+            // Nothing to do
+        }
+        IceFailType::Llvm(Some(LlvmFailType::Opt)) => todo!(),
+        IceFailType::Llvm(Some(LlvmFailType::UnrecognizedFileFormat)) => {
+            // Objdump?
+            todo!()
+        }
+        IceFailType::Llvm(Some(LlvmFailType::UnrecognizedOpcode)) => {
+            // Extract asm
+            todo!();
+        }
+        IceFailType::Gcc(Some(GccFailType::UnrecognizedOpcode)) => {
+            // Extract asm
+            todo!();
+        }
+        IceFailType::Gcc(Some(GccFailType::InternalCompilerError)) => {
+            // Nothing to do
+        }
+        IceFailType::Gcc(Some(GccFailType::Lto1Error)) => todo!(),
+        IceFailType::Gcc(Some(GccFailType::UnrecognizedInsn)) => todo!(),
+        IceFailType::Llvm(Some(LlvmFailType::ReservedRequiredRegister)) => todo!(),
+        IceFailType::Llvm(Some(LlvmFailType::OptCrash))
+        | IceFailType::Llvm(Some(LlvmFailType::LlcCrash)) => {
+            // Already reduced with llvm-reduce
+        }
+        IceFailType::Llvm(None) => unreachable!(),
+        IceFailType::Gcc(None) => unreachable!(),
+    }
+
+    Ok(())
+}
+
+fn report(
+    sh: &Shell,
+    reduction_dir: &Path,
+    fail_info: &FailInfo,
+    config: &ReductionConfig,
+) -> anyhow::Result<()> {
+    if let FailInfo::Runtime(_) = fail_info {
+        // Runtime reductions aren't reported yet
+        return Ok(());
+    }
+
+    let testcases = testcases(fail_info);
+    let reduced_opts = &reduced_opts(sh, reduction_dir, &testcases)?;
+
     match fail_info {
         FailInfo::Ice(fail_info) => {
             let assemble_link_flags = match fail_info.action {
@@ -323,7 +567,13 @@ fn report(
                 Action::Link | Action::Execute => "-o testcase.o",
             };
 
-            let compile_commands = if testcases.len() == 1 {
+            let compile_commands = if is_llvm_ir(&testcases) {
+                vec![format!(
+                    "{} {} reduced.ll -o /dev/null",
+                    fail_info.compilers[0].to_str().unwrap(),
+                    reduced_opts[0]
+                )]
+            } else if testcases.len() == 1 {
                 vec![format!(
                     "{} {} -Wall {} {assemble_link_flags}",
                     fail_info.compilers.last().unwrap().to_str().unwrap(),
@@ -366,57 +616,23 @@ fn report(
             sh.write_file(reduction_dir.join("reproduce.sh"), reproduce_sh)?;
             cmd!(sh, "chmod +x reproduce.sh").run()?;
 
-            match fail_info.fail_type {
-                IceFailType::Llvm(Some(LlvmFailType::Llc)) => {
-                    // We can reduce llvm further
-                    extract_llvm_ir_llc(sh, reduction_dir, &fail_info)?;
-                }
-                IceFailType::Llvm(Some(LlvmFailType::Frontend)) => {
-                    // Nothing to do
-                }
-                IceFailType::Llvm(Some(LlvmFailType::Opt)) => todo!(),
-                IceFailType::Llvm(Some(LlvmFailType::UnrecognizedFileFormat)) => {
-                    // Objdump?
-                    todo!()
-                }
-                IceFailType::Llvm(Some(LlvmFailType::UnrecognizedOpcode)) => {
-                    // Extract asm
-                    todo!();
-                }
-                IceFailType::Gcc(Some(GccFailType::UnrecognizedOpcode)) => {
-                    // Extract asm
-                    todo!();
-                }
-                IceFailType::Gcc(Some(GccFailType::InternalCompilerError)) => {
-                    // Nothing to do
-                }
-                IceFailType::Gcc(Some(GccFailType::Lto1Error)) => todo!(),
-                IceFailType::Gcc(Some(GccFailType::UnrecognizedInsn)) => todo!(),
-                IceFailType::Llvm(Some(LlvmFailType::ReservedRequiredRegister)) => todo!(),
-                IceFailType::Llvm(None) => unreachable!(),
-                IceFailType::Gcc(None) => unreachable!(),
-            }
-
-            generate_bug_report(sh, &fail_info)?;
+            generate_bug_report(sh, fail_info, config)?;
         }
         FailInfo::Execution(fail_info) => {
-            let ignorable_warnings = ignorable_warnings();
+            let ignorable_warnings = ignorable_warnings_for(&testcases);
+            let testcase = testcases[0].to_str().unwrap();
 
             let reproduce_sh = match &fail_info.runner {
                 Runner::Native => format!(
-                    "#!/bin/bash\n{:?} {} -fsigned-char -fno-strict-aliasing -fwrapv -Wall {} red.c -o red.out\nred.out",
+                    "#!/bin/bash\n{:?} {} -fsigned-char -fno-strict-aliasing -fwrapv -Wall {} {testcase} -o red.out\nred.out",
                     fail_info.compiler,
                     reduced_opts[0],
                     ignorable_warnings.join(" ")
                 ),
                 Runner::Qemu(qemu_config) => {
                     let qemu_cpu = match &qemu_config.cpu_flags {
-                        compiler_fuzz::RunnerArguments::Fixed(flags) => flags,
-                        compiler_fuzz::RunnerArguments::Generated(generation_script) => &cmd!(
-                            sh,
-                            "{generation_script} --elf-file-path red.out --print-qemu-cpu"
-                        )
-                        .read()?,
+                        compiler_fuzz::RunnerArguments::Fixed(flags) => flags.clone(),
+                        compiler_fuzz::RunnerArguments::Generated => riscv_qemu_cpu(&sh.current_dir().join("red.out"))?.cpu()?,
                     };
 
 		    let qemu = if qemu_cpu.starts_with("rv32") {
@@ -426,16 +642,37 @@ fn report(
 		    };
 
                     format!(
-			"#!/bin/bash\n{:?} {} -fsigned-char -fno-strict-aliasing -fwrapv -Wall {} red.c -Wall -o red.out\nQEMU_CPU={qemu_cpu} {} red.out",
+			"#!/bin/bash\n{:?} {} -fsigned-char -fno-strict-aliasing -fwrapv -Wall {} {testcase} -Wall -o red.out\nQEMU_CPU={qemu_cpu} {} red.out",
 			fail_info.compiler, reduced_opts[0], ignorable_warnings.join(" "), qemu.to_str().unwrap()
 		    )
                 }
+                Runner::QemuAarch64(qemu_config) => {
+                    let qemu_cpu = qemu_aarch64_cpu(&reduced_opts[0..1]);
+                    format!(
+                        "#!/bin/bash\n{:?} {} -fsigned-char -fno-strict-aliasing -fwrapv -Wall {} {testcase} -Wall -o red.out\nQEMU_CPU={qemu_cpu} {} red.out",
+                        fail_info.compiler,
+                        reduced_opts[0],
+                        ignorable_warnings.join(" "),
+                        qemu_config.path.to_str().unwrap()
+                    )
+                }
+                Runner::Spike(spike_config) => {
+                    let isa = spike_isa(&sh.current_dir().join("red.out"), &reduced_opts[0..1])?;
+                    format!(
+                        "#!/bin/bash\n{:?} {} -fsigned-char -fno-strict-aliasing -fwrapv -Wall {} {testcase} -Wall -o red.out\n{} --isa={isa} {} red.out",
+                        fail_info.compiler,
+                        reduced_opts[0],
+                        ignorable_warnings.join(" "),
+                        spike_config.path.to_str().unwrap(),
+                        spike_pk(spike_config, &isa).to_str().unwrap()
+                    )
+                }
             };
 
             sh.write_file(reduction_dir.join("reproduce.sh"), reproduce_sh)?;
             cmd!(sh, "chmod +x reproduce.sh").run()?;
         }
-        FailInfo::Runtime(_) => todo!(),
+        FailInfo::Runtime(_) => unreachable!(),
     }
 
     Ok(())
@@ -444,7 +681,7 @@ fn report(
 fn categorize_runtime_fail(
     sh: &Shell,
     reduction_dir: &Path,
-    _fail_info: &RuntimeFailInfo,
+    fail_info: &RuntimeFailInfo,
 ) -> anyhow::Result<RuntimeFailType> {
     assert!(reduction_dir.join("fast_exec_stdout.txt").exists());
     assert!(reduction_dir.join("slow_exec_stdout.txt").exists());
@@ -452,6 +689,16 @@ fn categorize_runtime_fail(
     let fast_stdout = sh.read_file(reduction_dir.join("fast_exec_stdout.txt"))?;
     let slow_stdout = sh.read_file(reduction_dir.join("slow_exec_stdout.txt"))?;
 
+    if let Some(RuntimeFailType::VectorConfigMismatch) = fail_info.fail_type {
+        // The slow binary under its two vector configs, the fast one may agree
+        // with either
+        let sweep_stdout = sh.read_file(reduction_dir.join("sweep_exec_stdout.txt"))?;
+        if slow_stdout != sweep_stdout {
+            return Ok(RuntimeFailType::VectorConfigMismatch);
+        }
+        panic!("Could not categorize failure!\nslow_stdout:\n{slow_stdout}\nsweep_stdout:\n{sweep_stdout}");
+    }
+
     if fast_stdout != slow_stdout {
         Ok(RuntimeFailType::Mismatch)
     } else {
@@ -484,6 +731,17 @@ fn categorize_exec_fail(
                 panic!("Could not categorize failure!\nstderr:\n{stderr}\nstdout:\n{stdout}");
             }
         }
+        ExecFailType::Spike(_) => {
+            let stderr = sh.read_file(reduction_dir.join("exec_stderr.txt"))?;
+            let stdout = sh.read_file(reduction_dir.join("exec_stdout.txt"))?;
+
+            match spike_fail_type(&stdout, &stderr) {
+                Some(spike_fail_type) => Ok(ExecFailType::Spike(Some(spike_fail_type))),
+                None => {
+                    panic!("Could not categorize failure!\nstderr:\n{stderr}\nstdout:\n{stdout}")
+                }
+            }
+        }
         ExecFailType::Native(_) => todo!(),
     }
 }
@@ -495,7 +753,7 @@ fn categorize_ice_fail(
 ) -> anyhow::Result<IceFailType> {
     assert!(reduction_dir.join("stderr.txt").exists());
 
-    match fail_info.fail_type {
+    match &fail_info.fail_type {
         IceFailType::Gcc(_) => {
             let stderr = sh.read_file(reduction_dir.join("stderr.txt"))?;
 
@@ -511,6 +769,10 @@ fn categorize_ice_fail(
                 panic!("Could not categorize failure!\nstderr:\n{stderr}");
             }
         }
+        // The fuzzer records which tool crashed on generated IR
+        IceFailType::Llvm(Some(fail_type @ (LlvmFailType::OptCrash | LlvmFailType::LlcCrash))) => {
+            Ok(IceFailType::Llvm(Some(fail_type.clone())))
+        }
         IceFailType::Llvm(_) => {
             let stderr = sh.read_file(reduction_dir.join("stderr.txt"))?;
 
@@ -535,7 +797,11 @@ fn categorize_ice_fail(
     }
 }
 
-fn generate_bug_report(sh: &Shell, fail_info: &IceFailInfo) -> anyhow::Result<()> {
+fn generate_bug_report(
+    sh: &Shell,
+    fail_info: &IceFailInfo,
+    config: &ReductionConfig,
+) -> anyhow::Result<()> {
     let compiler = &fail_info.compilers;
     assert_eq!(
         compiler.len(),
@@ -543,48 +809,148 @@ fn generate_bug_report(sh: &Shell, fail_info: &IceFailInfo) -> anyhow::Result<()
         "Cannot currently support multiple files!"
     );
     let compiler = &compiler[0];
+    let compiler_name = compiler.file_name().unwrap().to_str().unwrap();
     let testcase_path = &fail_info.testcases[0];
+    let testcase_name = testcase_path.to_str().unwrap();
 
     let flags = sh.read_file("reduced_compiler_opts.txt")?;
-    let testcase = sh.read_file(testcase_path)?;
+    let flags = flags.trim();
+    let stderr = sh.read_file("stderr.txt")?;
+    let c_language = if is_cpp(&fail_info.testcases) {
+        "cpp"
+    } else {
+        "c"
+    };
 
-    match fail_info.fail_type {
-        IceFailType::Llvm(Some(LlvmFailType::Llc)) => {
-            let llvmir = sh.read_file("reduced.ll")?;
+    let (testcase, language, command, reduced_ir, ir_command) = match fail_info.fail_type {
+        IceFailType::Llvm(Some(LlvmFailType::Llc)) => (
+            sh.read_file(testcase_path)?,
+            c_language,
+            format!("{compiler_name} {flags} {testcase_name}"),
+            sh.read_file("reduced.ll")?,
+            "llc reduced.ll".to_string(),
+        ),
+        IceFailType::Gcc(Some(GccFailType::InternalCompilerError))
+        | IceFailType::Gcc(Some(GccFailType::UnrecognizedInsn))
+        | IceFailType::Gcc(Some(GccFailType::Lto1Error)) => (
+            sh.read_file(testcase_path)?,
+            c_language,
+            format!("{compiler_name} {flags} {testcase_name} -c -S -o /dev/null"),
+            String::new(),
+            String::new(),
+        ),
+        IceFailType::Llvm(Some(LlvmFailType::OptCrash))
+        | IceFailType::Llvm(Some(LlvmFailType::LlcCrash)) => (
+            sh.read_file("reduced.ll")?,
+            "llvm",
+            format!("{compiler_name} {flags} reduced.ll -o /dev/null"),
+            String::new(),
+            String::new(),
+        ),
+        IceFailType::Gcc(Some(GccFailType::UnrecognizedOpcode)) => todo!(),
+        IceFailType::Llvm(_) | IceFailType::Gcc(None) => todo!(),
+    };
 
-            let bug_report = format!("C Testcase:\n```c\n{}\n```\n\nCommand/backtrace:\n```\n{} {}\n\nReduced LLVM IR:\n```llvm ir\n{}\n```\n\nCommand/backtrace:\n```\n{} {} reduced.ll\n```\n\nFound via fuzzer.\n",testcase, compiler.to_str().unwrap(), flags, llvmir, "./llc","Placeholder");
+    let source_path = match language {
+        "llvm" => Path::new("reduced.ll"),
+        _ => testcase_path.as_path(),
+    };
+    let compiler_id = config.godbolt_compiler.clone().unwrap_or_else(|| {
+        godbolt::compiler_id(
+            compiler,
+            &fail_info.architecture,
+            flags,
+            compiler_identity(fail_info, compiler).map(|identity| identity.version.as_str()),
+        )
+    });
+    let client_state = ClientState::new(
+        godbolt::language(source_path),
+        &testcase,
+        &compiler_id,
+        flags,
+    );
+    sh.write_file("godbolt.json", serde_json::to_string_pretty(&client_state)?)?;
+
+    let template = report_template(config, &fail_info.fail_type)?;
+    let bug_report = render_template(
+        &template,
+        &[
+            ("summary", summary(&fail_info.architecture, &stderr)),
+            ("target", gcc_target(&fail_info.architecture).to_string()),
+            ("labels", labels(fail_info).join(", ")),
+            ("testcase_language", language.to_string()),
+            ("testcase", testcase.trim_end().to_string()),
+            ("command", command),
+            ("stderr", stderr.trim_end().to_string()),
+            ("reduced_ir", reduced_ir.trim_end().to_string()),
+            ("ir_command", ir_command),
+            ("godbolt_link", client_state.link()?),
+            ("march", march(flags)),
+            ("version", version(fail_info, compiler)),
+            (
+                "first_bad_commit",
+                fail_info.first_bad_commit.clone().unwrap_or_default(),
+            ),
+        ],
+    );
 
-            println!("{bug_report}");
+    println!("{bug_report}");
 
-            sh.write_file("bug_report.txt", bug_report)?;
+    sh.write_file("bug_report.txt", bug_report)?;
 
-            Ok(())
-        }
-        IceFailType::Gcc(Some(GccFailType::InternalCompilerError))
-        | IceFailType::Gcc(Some(GccFailType::UnrecognizedInsn))
-        | IceFailType::Gcc(Some(GccFailType::Lto1Error)) => {
-            let compiler = compiler.to_str().unwrap();
+    Ok(())
+}
 
-            let bug_report = format!(
-		    "Testcase:\n{}\n\nCommand/backtrace:\n{} {} {} -c -S -o /dev/null\n{}\n\nFound via fuzzer.\n",
-		    testcase, compiler, flags, testcase_path.to_str().unwrap(), "Placeholder"
-		);
+/// Reduce generated IR with llvm-reduce. The interestingness test reruns the
+/// tool that crashed (opt or llc) and checks for the same crash message.
+fn reduce_llvm_ir(
+    sh: &Shell,
+    reduction_dir: &Path,
+    fail_info: &IceFailInfo,
+    config: &ReductionConfig,
+) -> anyhow::Result<()> {
+    assert_eq!(
+        fail_info.compilers.len(),
+        1,
+        "Cannot reduce IR from multiple testcases!"
+    );
+    let tool = &fail_info.compilers[0];
+    let testcase = &fail_info.testcases[0];
+    let llvm_reduce = match &config.toolchain {
+        Some(toolchain) => toolchain.join("llvm-reduce"),
+        None => llvm_tool(tool, "llvm-reduce"),
+    };
 
-            println!("{bug_report}");
+    let flags = sh.read_file(reduction_dir.join("compiler_opts.txt"))?;
+    let stderr = sh.read_file(reduction_dir.join("stderr.txt"))?;
+    let signature = crash_signature(&stderr).replace('\'', "'\\''");
 
-            sh.write_file("bug_report.txt", bug_report)?;
+    let reduce_sh = format!(
+        "#!/bin/bash\n{} {flags} $1 -o /dev/null 2>&1 | grep -q -F '{signature}'\n",
+        tool.to_str().unwrap()
+    );
+    sh.write_file(reduction_dir.join("min_ir.sh"), reduce_sh)?;
 
-            Ok(())
-        }
-        IceFailType::Gcc(Some(GccFailType::UnrecognizedOpcode)) => todo!(),
-        IceFailType::Llvm(_) | IceFailType::Gcc(None) => todo!(),
-    }
+    cmd!(sh, "chmod +x min_ir.sh").run()?;
+
+    cmd!(
+        sh,
+        "{llvm_reduce} --test min_ir.sh {testcase} -o reduced.ll"
+    )
+    .run()?;
+
+    Ok(())
 }
 
+/// Environment variable naming the LLVM tools directory for llc failures when
+/// the reduction config has no toolchain
+const LLVM_TOOLCHAIN_VAR: &str = "LLVM_TOOLCHAIN";
+
 fn extract_llvm_ir_llc(
     sh: &Shell,
     reduction_dir: &Path,
     fail_info: &IceFailInfo,
+    config: &ReductionConfig,
 ) -> anyhow::Result<()> {
     let compiler = &fail_info.compilers;
 
@@ -605,22 +971,31 @@ fn extract_llvm_ir_llc(
     )
     .run()?;
 
-    cmd!(
-        sh,
-        "/scratch/tc-testing/tc-compiler-fuzz-trunk/build-gcv/build-llvm-linux/bin/llvm-dis red.bc"
-    )
-    .run()?;
+    let toolchain = match &config.toolchain {
+        Some(toolchain) => toolchain.clone(),
+        None => env::var_os(LLVM_TOOLCHAIN_VAR)
+            .map(PathBuf::from)
+            .with_context(|| {
+                format!(
+                    "Reducing an llc failure needs the LLVM tools (llvm-dis, llc, llvm-reduce): set `toolchain' in the reduction config or {LLVM_TOOLCHAIN_VAR}"
+                )
+            })?,
+    };
+    let llvm_dis = toolchain.join("llvm-dis");
+    let llc = toolchain.join("llc");
+    let llvm_reduce = toolchain.join("llvm-reduce");
 
-    let reduce_sh = "#!/bin/bash\n/scratch/tc-testing/tc-compiler-fuzz-trunk/build-gcv/build-llvm-linux/bin/llc $1 2>&1 | grep -e \"LLVM ERROR\" -e \"Cannot select\"".to_string();
+    cmd!(sh, "{llvm_dis} red.bc").run()?;
+
+    let reduce_sh = format!(
+        "#!/bin/bash\n{} $1 2>&1 | grep -e \"LLVM ERROR\" -e \"Cannot select\"",
+        llc.to_str().unwrap()
+    );
     sh.write_file(reduction_dir.join("min_ir.sh"), reduce_sh)?;
 
     cmd!(sh, "chmod +x min_ir.sh").run()?;
 
-    cmd!(
-        sh,
-        "/scratch/tc-testing/tc-compiler-fuzz-trunk/build-gcv/build-llvm-linux/bin/llvm-reduce --test min_ir.sh red.ll"
-    )
-    .run()?;
+    cmd!(sh, "{llvm_reduce} --test min_ir.sh red.ll").run()?;
 
     Ok(())
 }
@@ -648,17 +1023,27 @@ fn split_flags(
         .map(|s| s.to_string())
         .collect::<Vec<_>>();
 
-    // Split up compiler opts to make it easy for creduce to minimize
-    let split_flags = compiler_flags_str
+    sh.write_file(
+        reduction_dir.join(output_file),
+        split_flags_str(&compiler_flags_str),
+    )?;
+
+    Ok(compiler_flags)
+}
+
+/// Split up compiler opts to make it easy for creduce to minimize
+fn split_flags_str(flags: &str) -> String {
+    flags
         .chars()
         .map(|c| c.to_string())
         .map(|c| if c == " " { "\n".to_string() } else { c })
         .collect::<Vec<_>>()
-        .join("\n");
-
-    sh.write_file(reduction_dir.join(output_file), split_flags)?;
+        .join("\n")
+}
 
-    Ok(compiler_flags)
+/// Undo `split_flags_str`
+fn join_split_flags(split_flags: &str) -> String {
+    split_flags.replace("\n\n", " ").replace("\n", "")
 }
 
 fn preprocess(
@@ -677,7 +1062,7 @@ fn preprocess(
                 let compiler = &fail_info.compilers;
 
                 match fail_info.generator {
-                    FuzzGenerator::Csmith(_) => {
+                    FuzzGenerator::Csmith(_) | FuzzGenerator::Mutate(_) => {
                         let compiler_flags = split_flags(
                             sh,
                             reduction_dir,
@@ -692,21 +1077,22 @@ fn preprocess(
                             "Csmith should only have one file/compiler!"
                         );
                         let compiler = &compiler[0];
+                        let testcase = &fail_info.testcases[0];
 
                         let mut run_command: std::process::Command = cmd!(
 				sh,
-				"{compiler} {generator_flags...} {compiler_flags...} csmith_testcase.c -E -o preprocessed.c"
+				"{compiler} {generator_flags...} {compiler_flags...} {testcase} -E -o preprocessed.c"
 			)
                         .into();
 
                         let command_output = run_command.output()?;
                         if !command_output.status.success() {
                             // Initial compile failed, just copy it over, header and all
-                            cmd!(sh, "cp csmith_testcase.c preprocessed.c").run()?;
+                            cmd!(sh, "cp {testcase} preprocessed.c").run()?;
                         }
                         Ok(vec![PathBuf::from("preprocessed.c")])
                     }
-                    FuzzGenerator::Fixed(_) => {
+                    FuzzGenerator::Fixed(_) | FuzzGenerator::Corpus(_) => {
                         let _ = split_flags(
                             sh,
                             reduction_dir,
@@ -715,7 +1101,8 @@ fn preprocess(
                             None,
                         )?;
 
-                        cmd!(sh, "cp fixed_testcase.c preprocessed.c").run()?;
+                        let testcase = &fail_info.testcases[0];
+                        cmd!(sh, "cp {testcase} preprocessed.c").run()?;
                         Ok(vec![PathBuf::from("preprocessed.c")])
                     }
                     FuzzGenerator::Yarpgen(_) => {
@@ -729,6 +1116,17 @@ fn preprocess(
 
                         Ok(fail_info.testcases.clone())
                     }
+                    FuzzGenerator::LlvmIr(_) => {
+                        let _ = split_flags(
+                            sh,
+                            reduction_dir,
+                            PathBuf::from("compiler_opts.txt"),
+                            PathBuf::from("reducible_compiler_opts.txt"),
+                            None,
+                        )?;
+
+                        Ok(fail_info.testcases.clone())
+                    }
                     FuzzGenerator::Rustsmith(_) => todo!(),
                 }
             } else {
@@ -749,22 +1147,28 @@ fn preprocess(
                     .collect::<Vec<_>>();
 
                 match fail_info.generator {
+                    FuzzGenerator::Mutate(_) => {
+                        panic!("Mutated testcases should only have one file/compiler!");
+                    }
                     FuzzGenerator::Csmith(_) => {
                         panic!("Csmith should only have one file/compiler!");
                     }
-                    FuzzGenerator::Fixed(_) => {
+                    FuzzGenerator::Fixed(_) | FuzzGenerator::Corpus(_) => {
                         todo!();
                     }
                     FuzzGenerator::Yarpgen(_) => {
                         // Copy them over so the original files aren't clobbered
                         // when resuming a partial reduction.
-                        cmd!(sh, "cp driver.c preprocessed_driver.c").run()?;
-                        cmd!(sh, "cp func.c preprocessed_func.c").run()?;
-
-                        Ok(vec![
-                            PathBuf::from("preprocessed_driver.c"),
-                            PathBuf::from("preprocessed_func.c"),
-                        ])
+                        let mut preprocessed = vec![];
+                        for testcase in &fail_info.testcases {
+                            let copy = format!("preprocessed_{}", testcase.to_str().unwrap());
+                            cmd!(sh, "cp {testcase} {copy}").run()?;
+                            preprocessed.push(PathBuf::from(copy));
+                        }
+                        Ok(preprocessed)
+                    }
+                    FuzzGenerator::LlvmIr(_) => {
+                        unreachable!("LLVM IR testcases are always a single file")
                     }
                     FuzzGenerator::Rustsmith(_) => todo!(),
                 }
@@ -788,24 +1192,25 @@ fn preprocess(
 
                 // TODO: If fails, just copy it over
                 match fail_info.generator {
-                    FuzzGenerator::Csmith(_) => {
+                    FuzzGenerator::Csmith(_) | FuzzGenerator::Mutate(_) => {
                         assert_eq!(
                             compiler.len(),
                             1,
                             "Csmith should only have one file/compiler!"
                         );
                         let compiler = &compiler[0];
+                        let testcase = &fail_info.testcase[0];
 
                         let mut run_command: std::process::Command = cmd!(
 				sh,
-				"{compiler} {generator_flags...} {compiler_flags...} csmith_testcase.c -E -o raw_preprocessed.c"
+				"{compiler} {generator_flags...} {compiler_flags...} {testcase} -E -o raw_preprocessed.c"
 			    )
                         .into();
 
                         let command_output = run_command.output()?;
                         if !command_output.status.success() {
                             // Initial compile failed, just copy it over, header and all
-                            cmd!(sh, "cp csmith_testcase.c preprocessed.c").run()?;
+                            cmd!(sh, "cp {testcase} preprocessed.c").run()?;
                         } else {
                             let bash = r#"cat raw_preprocessed.c | tac | sed '/__attribute__ ((__malloc__ (/,/extern/d' | tac > temp.c && mv temp.c preprocessed.c"#;
                             cmd!(sh, "bash -c {bash}").run()?;
@@ -813,8 +1218,9 @@ fn preprocess(
 
                         Ok(vec![PathBuf::from("preprocessed.c")])
                     }
-                    FuzzGenerator::Fixed(_) => {
-                        cmd!(sh, "cp fixed_testcase.c preprocessed.c").run()?;
+                    FuzzGenerator::Fixed(_) | FuzzGenerator::Corpus(_) => {
+                        let testcase = &fail_info.testcase[0];
+                        cmd!(sh, "cp {testcase} preprocessed.c").run()?;
                         Ok(vec![PathBuf::from("preprocessed.c")])
                     }
                     FuzzGenerator::Yarpgen(_) => {
@@ -822,6 +1228,7 @@ fn preprocess(
                         Ok(fail_info.testcase.clone())
                     }
                     FuzzGenerator::Rustsmith(_) => todo!(),
+                    FuzzGenerator::LlvmIr(_) => unreachable!("LLVM IR testcases are only compiled"),
                 }
             } else {
                 // Multiple
@@ -841,16 +1248,18 @@ fn preprocess(
                     .collect::<Vec<_>>();
 
                 match fail_info.generator {
+                    FuzzGenerator::Mutate(_) => {
+                        panic!("Mutated testcases should only have one file/compiler!");
+                    }
                     FuzzGenerator::Csmith(_) => {
                         panic!("Csmith should only have one file/compiler!");
                     }
-                    FuzzGenerator::Fixed(_) => {
+                    FuzzGenerator::Fixed(_) | FuzzGenerator::Corpus(_) => {
                         todo!();
                     }
-                    FuzzGenerator::Yarpgen(_) => {
-                        Ok(vec![PathBuf::from("driver.c"), PathBuf::from("func.c")])
-                    }
+                    FuzzGenerator::Yarpgen(_) => Ok(fail_info.testcase.clone()),
                     FuzzGenerator::Rustsmith(_) => todo!(),
+                    FuzzGenerator::LlvmIr(_) => unreachable!("LLVM IR testcases are only compiled"),
                 }
             }
         }
@@ -862,6 +1271,7 @@ fn preprocess(
 
                 assert!(fail_info.slow_compiler.len() == 1);
                 let compiler = &fail_info.slow_compiler[0];
+                let testcase = &fail_info.testcase[0];
 
                 let _ = split_flags(
                     sh,
@@ -881,27 +1291,29 @@ fn preprocess(
 
                 // TODO: If fails, just copy it over
                 match fail_info.generator {
-                    FuzzGenerator::Csmith(_) => {
+                    FuzzGenerator::Csmith(_) | FuzzGenerator::Mutate(_) => {
                         let mut run_command: std::process::Command = cmd!(
 				    sh,
-				    "{compiler} {slow_generator_flags...} {compiler_flags...} csmith_testcase.c -E -o raw_preprocessed.c"
+				    "{compiler} {slow_generator_flags...} {compiler_flags...} {testcase} -E -o raw_preprocessed.c"
 				)
 			    .into();
 
                         let command_output = run_command.output()?;
                         if !command_output.status.success() {
                             // Initial compile failed, just copy it over, header and all
-                            cmd!(sh, "cp csmith_testcase.c preprocessed.c").run()?;
+                            cmd!(sh, "cp {testcase} preprocessed.c").run()?;
                         } else {
                             let bash = r#"cat raw_preprocessed.c | tac | sed '/__attribute__ ((__malloc__ (/,/extern/d' | tac > temp.c && mv temp.c preprocessed.c"#;
                             cmd!(sh, "bash -c {bash}").run()?;
                         }
                     }
-                    FuzzGenerator::Fixed(_) => {
-                        cmd!(sh, "cp fixed_testcase.c raw_preprocessed.c").run()?;
+                    FuzzGenerator::Fixed(_) | FuzzGenerator::Corpus(_) => {
+                        let testcase = &fail_info.testcase[0];
+                        cmd!(sh, "cp {testcase} raw_preprocessed.c").run()?;
                     }
                     FuzzGenerator::Yarpgen(_) => todo!(),
                     FuzzGenerator::Rustsmith(_) => todo!(),
+                    FuzzGenerator::LlvmIr(_) => unreachable!("LLVM IR testcases are only compiled"),
                 }
 
                 let bash = r#"cat raw_preprocessed.c | tac | sed '/__attribute__ ((__malloc__ (/,/extern/d' | tac | sed -E '/typedef.+_Float/d' > temp.c && mv temp.c preprocessed.c"#;
@@ -941,16 +1353,18 @@ fn preprocess(
                     .collect::<Vec<_>>();
 
                 match fail_info.generator {
+                    FuzzGenerator::Mutate(_) => {
+                        panic!("Mutated testcases should only have one file/compiler!");
+                    }
                     FuzzGenerator::Csmith(_) => {
                         panic!("Csmith should only have one file/compiler!");
                     }
-                    FuzzGenerator::Fixed(_) => {
+                    FuzzGenerator::Fixed(_) | FuzzGenerator::Corpus(_) => {
                         todo!();
                     }
-                    FuzzGenerator::Yarpgen(_) => {
-                        Ok(vec![PathBuf::from("driver.c"), PathBuf::from("func.c")])
-                    }
+                    FuzzGenerator::Yarpgen(_) => Ok(fail_info.testcase.clone()),
                     FuzzGenerator::Rustsmith(_) => todo!(),
+                    FuzzGenerator::LlvmIr(_) => unreachable!("LLVM IR testcases are only compiled"),
                 }
             }
         }
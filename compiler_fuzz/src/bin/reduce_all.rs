@@ -0,0 +1,353 @@
+use argh::FromArgs;
+use compiler_fuzz::finds_db::{read_finds, FindKind, FindRecord, ReductionStatus, FINDS_DB};
+use compiler_fuzz::reproduce::read_fail_info;
+use compiler_fuzz::{csv_field, is_llvm_ir, FailInfo, PathRemap};
+use env_logger::Env;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::{env, thread};
+use xshell::{cmd, Shell};
+
+#[derive(FromArgs)]
+#[argh(description = "Reduce one find per bug from a finds directory
+Use RUST_LOG=off to turn off logging")]
+struct ReduceAllArgs {
+    /// finds directory written by the fuzzers
+    #[argh(positional)]
+    finds_directory: PathBuf,
+
+    /// directory to put the reductions in, one directory per find
+    #[argh(positional)]
+    output_directory: PathBuf,
+
+    /// number of reductions to run at once (default: 1)
+    #[argh(option)]
+    jobs: Option<usize>,
+
+    /// seconds a reduction may run before it is stopped (default: 7200).
+    /// Running reduce_all again resumes it.
+    #[argh(option)]
+    timeout: Option<u64>,
+
+    /// reduction config yaml passed on to reduce
+    #[argh(option)]
+    config: Option<PathBuf>,
+
+    /// replace a path prefix in fail_info.yaml (old=new), passed on to reduce
+    #[argh(option)]
+    remap: Vec<PathRemap>,
+
+    /// skip c reduction
+    #[argh(switch, short = 's')]
+    skip_c: bool,
+}
+
+enum ReductionResult {
+    Reduced,
+    /// Reduced, but reduce doesn't write reports for this kind of find yet
+    ReducedWithoutReport,
+    TimedOut,
+    Failed,
+    /// Another find of the same bug has been reduced
+    AlreadyReduced,
+    /// Another find of the same bug was reduced in this run instead
+    Duplicate(String),
+}
+
+impl fmt::Display for ReductionResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReductionResult::Reduced => write!(f, "reduced"),
+            ReductionResult::ReducedWithoutReport => write!(f, "reduced (no report)"),
+            ReductionResult::TimedOut => write!(f, "timed out"),
+            ReductionResult::Failed => write!(f, "failed"),
+            ReductionResult::AlreadyReduced => write!(f, "already reduced"),
+            ReductionResult::Duplicate(representative) => {
+                write!(f, "duplicate of {representative}")
+            }
+        }
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+
+    let args: ReduceAllArgs = argh::from_env();
+
+    let finds_dir = &fs::canonicalize(&args.finds_directory)?;
+    fs::create_dir_all(&args.output_directory)?;
+    let output_dir = &fs::canonicalize(&args.output_directory)?;
+
+    let db_path = finds_dir.join(FINDS_DB);
+    if !db_path.exists() {
+        // Finds from before the finds database have nothing to group them by
+        println!(
+            "No {FINDS_DB} in {}, reduce its finds one at a time with reduce",
+            finds_dir.display()
+        );
+        return Ok(());
+    }
+    let records = read_finds(&db_path)?;
+
+    let mut frequency: HashMap<(&str, &str), usize> = HashMap::new();
+    for key in records.iter().filter_map(dedupe_key) {
+        *frequency.entry(key).or_default() += 1;
+    }
+    let reduced_keys = records
+        .iter()
+        .filter(|record| matches!(record.reduction_status, ReductionStatus::Reduced(_)))
+        .filter_map(dedupe_key)
+        .collect::<HashSet<_>>();
+
+    // ICEs reduce quickest and are the easiest to report, then the most
+    // common signatures of each kind
+    let mut unreduced = records
+        .iter()
+        .filter(|record| matches!(record.reduction_status, ReductionStatus::Unreduced))
+        .collect::<Vec<_>>();
+    unreduced.sort_by_key(|record| {
+        (
+            kind_priority(&record.kind),
+            Reverse(dedupe_key(record).map_or(1, |key| frequency[&key])),
+        )
+    });
+
+    let mut queue = vec![];
+    let mut representatives: HashMap<(&str, &str), &FindRecord> = HashMap::new();
+    let mut results = vec![];
+    for record in unreduced {
+        let Some(key) = dedupe_key(record) else {
+            queue.push(record);
+            continue;
+        };
+        if reduced_keys.contains(&key) {
+            results.push((record, ReductionResult::AlreadyReduced));
+        } else if let Some(representative) = representatives.get(&key) {
+            results.push((
+                record,
+                ReductionResult::Duplicate(find_name(representative)),
+            ));
+        } else {
+            representatives.insert(key, record);
+            queue.push(record);
+        }
+    }
+
+    println!(
+        "{} finds, reducing {} of them with {} jobs",
+        records.len(),
+        queue.len(),
+        args.jobs.unwrap_or(1)
+    );
+
+    let next = AtomicUsize::new(0);
+    let reduction_results = Mutex::new(vec![]);
+    thread::scope(|scope| {
+        for _ in 0..args.jobs.unwrap_or(1).max(1) {
+            scope.spawn(|| {
+                while let Some(record) = queue.get(next.fetch_add(1, Ordering::SeqCst)) {
+                    let result = reduce_find(&args, record, output_dir).unwrap_or_else(|error| {
+                        println!("{}: {error}", find_name(record));
+                        ReductionResult::Failed
+                    });
+                    println!("{}: {result}", find_name(record));
+                    reduction_results.lock().unwrap().push((*record, result));
+                }
+            });
+        }
+    });
+    results.extend(reduction_results.into_inner().unwrap());
+
+    let mut csv = String::from("Find,Kind,Signature,Result,Reduced size,Fail type\n");
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for record in &records {
+        let (reduction_dir, result) = match &record.reduction_status {
+            ReductionStatus::Reduced(reduction_dir) => {
+                (Some(reduction_dir.clone()), ReductionResult::AlreadyReduced)
+            }
+            ReductionStatus::Unreduced => {
+                let position = results
+                    .iter()
+                    .position(|(result_record, _)| result_record.dump_dir == record.dump_dir)
+                    .unwrap();
+                let (_, result) = results.swap_remove(position);
+                let reduction_dir = match result {
+                    ReductionResult::Reduced
+                    | ReductionResult::ReducedWithoutReport
+                    | ReductionResult::TimedOut
+                    | ReductionResult::Failed => Some(output_dir.join(find_name(record))),
+                    ReductionResult::AlreadyReduced | ReductionResult::Duplicate(_) => None,
+                };
+                (reduction_dir, result)
+            }
+        };
+
+        let (size, fail_type) = match &reduction_dir {
+            Some(reduction_dir) => reduction_summary(reduction_dir),
+            None => (None, None),
+        };
+        csv.push_str(&format!(
+            "{},{:?},{},{result},{},{}\n",
+            csv_field(&find_name(record)),
+            record.kind,
+            csv_field(&record.signature),
+            size.map(|size| size.to_string()).unwrap_or_default(),
+            csv_field(&fail_type.unwrap_or_else(|| record.fail_type.clone())),
+        ));
+
+        let status = match result {
+            ReductionResult::Duplicate(_) => "duplicate".to_string(),
+            result => result.to_string(),
+        };
+        *counts.entry(status).or_default() += 1;
+    }
+
+    let output = output_dir.join("reduce_all.csv");
+    fs::write(&output, csv)?;
+
+    let mut counts = counts.into_iter().collect::<Vec<_>>();
+    counts.sort();
+    println!(
+        "{}",
+        counts
+            .iter()
+            .map(|(status, count)| format!("{status}: {count}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    println!("Summary written to {}", output.display());
+
+    Ok(())
+}
+
+/// What makes two finds the same bug: the compiler's fail type and the
+/// signature. Only ICE signatures name a particular failure, execution and
+/// runtime ones describe a symptom (a signal, or nothing at all for a
+/// mismatch), so those finds and ICEs without a signature are never grouped.
+fn dedupe_key(record: &FindRecord) -> Option<(&str, &str)> {
+    if !matches!(record.kind, FindKind::Ice) || record.signature.trim().is_empty() {
+        return None;
+    }
+    // reduce refines the fail type, e.g. Gcc(None) to Gcc(Some(..)), so only
+    // the part the fuzzer already knew is compared
+    let fail_type = record.fail_type.split('(').next().unwrap_or_default();
+    Some((fail_type, record.signature.as_str()))
+}
+
+fn kind_priority(kind: &FindKind) -> u8 {
+    match kind {
+        FindKind::Ice => 0,
+        FindKind::Execution => 1,
+        FindKind::Runtime => 2,
+    }
+}
+
+fn find_name(record: &FindRecord) -> String {
+    record
+        .dump_dir
+        .file_name()
+        .unwrap()
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Run reduce on the find under a timeout, resuming an earlier reduction of it
+/// if there is one. reduce's output goes to `<find>.log` next to the reduction.
+fn reduce_find(
+    args: &ReduceAllArgs,
+    record: &FindRecord,
+    output_dir: &Path,
+) -> anyhow::Result<ReductionResult> {
+    let sh = Shell::new()?;
+    let reduce = env::current_exe()?.parent().unwrap().join("reduce");
+    let name = find_name(record);
+    let dump_dir = &record.dump_dir;
+    let reduction_dir = output_dir.join(&name);
+    let timeout = args.timeout.unwrap_or(7200).to_string();
+
+    let mut reduce_args = vec![];
+    if reduction_dir.exists() {
+        reduce_args.push("-r".to_string());
+    }
+    if args.skip_c {
+        reduce_args.push("-s".to_string());
+    }
+    if let Some(config) = &args.config {
+        reduce_args.push("--config".to_string());
+        reduce_args.push(config.to_str().unwrap().to_string());
+    }
+    for remap in &args.remap {
+        reduce_args.push("--remap".to_string());
+        reduce_args.push(format!(
+            "{}={}",
+            remap.from.to_str().unwrap(),
+            remap.to.to_str().unwrap()
+        ));
+    }
+
+    println!("{name}: reducing into {}", reduction_dir.display());
+
+    let log = File::create(output_dir.join(format!("{name}.log")))?;
+    let mut reduce_command: std::process::Command = cmd!(
+        sh,
+        "timeout -k 10 {timeout} {reduce} {reduce_args...} {dump_dir} {reduction_dir}"
+    )
+    .into();
+    let status = reduce_command
+        .stdout(log.try_clone()?)
+        .stderr(log)
+        .status()?;
+
+    Ok(match status.code() {
+        Some(0) if matches!(record.kind, FindKind::Runtime) => {
+            ReductionResult::ReducedWithoutReport
+        }
+        Some(0) => ReductionResult::Reduced,
+        Some(124) => ReductionResult::TimedOut,
+        _ => ReductionResult::Failed,
+    })
+}
+
+/// Size in bytes of the reduced testcases and the fail type reduce
+/// categorized, for whatever the reduction got to
+fn reduction_summary(reduction_dir: &Path) -> (Option<u64>, Option<String>) {
+    let Ok(sh) = Shell::new() else {
+        return (None, None);
+    };
+    let Ok(fail_info) = read_fail_info(&sh, reduction_dir) else {
+        return (None, None);
+    };
+
+    let (testcases, fail_type) = match &fail_info {
+        FailInfo::Ice(ice_fail_info) => (
+            ice_fail_info.testcases.clone(),
+            format!("{:?}", ice_fail_info.fail_type),
+        ),
+        FailInfo::Execution(exec_fail_info) => (
+            exec_fail_info.testcase.clone(),
+            format!("{:?}", exec_fail_info.fail_type),
+        ),
+        FailInfo::Runtime(runtime_fail_info) => (
+            runtime_fail_info.testcase.clone(),
+            format!("{:?}", runtime_fail_info.fail_type),
+        ),
+    };
+    // IR crashes are reduced by llvm-reduce into reduced.ll
+    let testcases = if is_llvm_ir(&testcases) && reduction_dir.join("reduced.ll").exists() {
+        vec![PathBuf::from("reduced.ll")]
+    } else {
+        testcases
+    };
+
+    let size = testcases
+        .iter()
+        .map(|testcase| fs::metadata(reduction_dir.join(testcase)).map(|metadata| metadata.len()))
+        .sum::<Result<u64, _>>()
+        .ok();
+    (size, Some(fail_type))
+}
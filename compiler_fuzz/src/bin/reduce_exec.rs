@@ -1,6 +1,10 @@
-use compiler_fuzz::reduction::check_for_ub;
+use compiler_fuzz::execute::{
+    qemu_aarch64_cpu, spike_fail_type, spike_isa, spike_pk, spike_rejected_isa,
+};
+use compiler_fuzz::qemu::riscv_qemu_cpu;
+use compiler_fuzz::reduction::{check_for_ub, exit_on_check_error, NOT_INTERESTING};
 use compiler_fuzz::{
-    ignorable_warnings, ExecFailInfo, ExecFailType, FailInfo, QemuFailType, Runner,
+    ignorable_warnings_for, ExecFailInfo, ExecFailType, FailInfo, QemuFailType, Runner,
 };
 use std::fs::File;
 use std::io::Read;
@@ -72,15 +76,21 @@ fn produce_fail(fail_info: &ExecFailInfo, working_dir: &PathBuf) -> anyhow::Resu
         let compiler_flags = compiler_flags_str.split_whitespace();
 
         let rv32 = compiler_flags_str.contains("-march=rv32");
-        check_for_ub(&sh, &fail_info.generator, &fail_info.testcase, rv32)?;
+        exit_on_check_error(check_for_ub(
+            &sh,
+            &fail_info.generator,
+            &fail_info.testcase,
+            rv32,
+        ));
 
         let compiler = &compilers[0];
 
-        let ignorable_warnings = ignorable_warnings();
+        let ignorable_warnings = ignorable_warnings_for(&fail_info.testcase);
+        let testcase = &fail_info.testcase[0];
 
         let mut compile_command: std::process::Command = cmd!(
         sh,
-		"{compiler} {compiler_flags...} red.c -fsigned-char -fno-strict-aliasing -fwrapv -Wall -Wformat {ignorable_warnings...} -o testcase.o"
+		"{compiler} {compiler_flags...} {testcase} -fsigned-char -fno-strict-aliasing -fwrapv -Wall -Wformat {ignorable_warnings...} -o testcase.o"
 	)
 	.into();
 
@@ -97,16 +107,16 @@ fn produce_fail(fail_info: &ExecFailInfo, working_dir: &PathBuf) -> anyhow::Resu
                 // Make sure there aren't warnings
                 if stderr.contains("warning:") {
                     println!("Unexpected warning! {stderr}");
-                    exit(1);
+                    exit(NOT_INTERESTING);
                 }
             }
             124 => {
                 println!("Timeout!");
-                exit(1);
+                exit(NOT_INTERESTING);
             }
             _ => {
                 println!("Unrecognized compilation failure: {}", stderr);
-                exit(1);
+                exit(NOT_INTERESTING);
             }
         };
 
@@ -114,12 +124,10 @@ fn produce_fail(fail_info: &ExecFailInfo, working_dir: &PathBuf) -> anyhow::Resu
             Runner::Native => cmd!(sh, "timeout -k 0.1 2 testcase.o 1").quiet().into(),
             Runner::Qemu(qemu_config) => {
                 let qemu_cpu = match &qemu_config.cpu_flags {
-                    compiler_fuzz::RunnerArguments::Fixed(flags) => flags,
-                    compiler_fuzz::RunnerArguments::Generated(generation_script) => &cmd!(
-                        sh,
-                        "{generation_script} --elf-file-path testcase.o --print-qemu-cpu"
-                    )
-                    .read()?,
+                    compiler_fuzz::RunnerArguments::Fixed(flags) => flags.clone(),
+                    compiler_fuzz::RunnerArguments::Generated => {
+                        riscv_qemu_cpu(&sh.current_dir().join("testcase.o"))?.cpu()?
+                    }
                 };
 
                 println!("{}", qemu_cpu);
@@ -135,6 +143,30 @@ fn produce_fail(fail_info: &ExecFailInfo, working_dir: &PathBuf) -> anyhow::Resu
                     .quiet()
                     .into()
             }
+            Runner::QemuAarch64(qemu_config) => {
+                let qemu = &qemu_config.path;
+                cmd!(sh, "timeout -k 0.1 2 {qemu} testcase.o 1")
+                    .env(
+                        "QEMU_CPU",
+                        qemu_aarch64_cpu(std::slice::from_ref(&compiler_flags_str)),
+                    )
+                    .quiet()
+                    .into()
+            }
+            Runner::Spike(spike_config) => {
+                let spike = &spike_config.path;
+                let isa = spike_isa(
+                    &sh.current_dir().join("testcase.o"),
+                    std::slice::from_ref(&compiler_flags_str),
+                )?;
+                let pk = spike_pk(spike_config, &isa);
+                cmd!(
+                    sh,
+                    "timeout -k 0.1 10 {spike} --isa={isa} {pk} testcase.o 1"
+                )
+                .quiet()
+                .into()
+            }
         };
 
         let command_output = run_command.output()?;
@@ -142,15 +174,46 @@ fn produce_fail(fail_info: &ExecFailInfo, working_dir: &PathBuf) -> anyhow::Resu
         let stdout = String::from_utf8(command_output.stdout)?;
         let stderr = String::from_utf8(command_output.stderr)?;
         let signal = command_output.status.signal().unwrap_or(0).to_string();
-        sh.write_file("exec_stdout.txt", stdout)?;
+        sh.write_file("exec_stdout.txt", &stdout)?;
         sh.write_file("exec_stderr.txt", &stderr)?;
         sh.write_file("exec_signal.txt", &signal)?;
 
+        if let ExecFailType::Spike(expected_fail_type) = &fail_info.fail_type {
+            // pk reports traps itself, so spike failures are told apart by
+            // their output rather than the exit code or signal
+            if matches!(command_output.status.code(), Some(0) | Some(124))
+                || command_output.status.signal() == Some(9)
+            {
+                println!("Unexpected execution pass or timeout");
+                exit(NOT_INTERESTING);
+            }
+            if spike_rejected_isa(&stderr) {
+                println!("Spike rejected the ISA string:\n{stderr}");
+                exit(NOT_INTERESTING);
+            }
+            let actual_fail_type = spike_fail_type(&stdout, &stderr);
+            match (expected_fail_type, &actual_fail_type) {
+                (None, _) => {
+                    // Has not been categorized, any fail will do
+                    println!("Uncategorized spike fail. Stderr:\n{stderr}");
+                    return Ok(());
+                }
+                (Some(expected), Some(actual)) if expected == actual => {
+                    println!("Success");
+                    return Ok(());
+                }
+                _ => {
+                    println!("Unexpected spike fail {actual_fail_type:?} stdout: {stdout}");
+                    exit(NOT_INTERESTING);
+                }
+            }
+        }
+
         match command_output.status.code() {
             Some(0) => {
                 // This shouldn't pass!
                 println!("Unexpected execution pass");
-                exit(1);
+                exit(NOT_INTERESTING);
             }
             Some(1) => match fail_info.fail_type {
                 ExecFailType::Qemu(Some(QemuFailType::ErrorMsg)) => {
@@ -165,23 +228,24 @@ fn produce_fail(fail_info: &ExecFailInfo, working_dir: &PathBuf) -> anyhow::Resu
                     return Ok(());
                 }
                 ExecFailType::Native(_)
+                | ExecFailType::Spike(_)
                 | ExecFailType::Qemu(Some(QemuFailType::IllegalInsn))
                 | ExecFailType::Qemu(Some(QemuFailType::Segfault)) => {
                     println!("Unexpected exec exit code 1 stderr: {stderr}");
-                    exit(1)
+                    exit(NOT_INTERESTING)
                 }
             },
             Some(124) => {
                 // Timeout
                 println!("Exec Timeout!");
-                exit(1);
+                exit(NOT_INTERESTING);
             }
             None => {
                 match command_output.status.signal() {
                     Some(9) => {
                         // Killed by timeout -k
                         println!("Exec Timeout!");
-                        exit(1);
+                        exit(NOT_INTERESTING);
                     }
                     Some(4) => match fail_info.fail_type {
                         ExecFailType::Qemu(Some(QemuFailType::IllegalInsn)) => {
@@ -194,10 +258,11 @@ fn produce_fail(fail_info: &ExecFailInfo, working_dir: &PathBuf) -> anyhow::Resu
                             return Ok(());
                         }
                         ExecFailType::Native(_)
+                        | ExecFailType::Spike(_)
                         | ExecFailType::Qemu(Some(QemuFailType::ErrorMsg))
                         | ExecFailType::Qemu(Some(QemuFailType::Segfault)) => {
                             println!("Unexpected illegal insn");
-                            exit(1)
+                            exit(NOT_INTERESTING)
                         }
                     },
                     Some(11) => match fail_info.fail_type {
@@ -211,27 +276,28 @@ fn produce_fail(fail_info: &ExecFailInfo, working_dir: &PathBuf) -> anyhow::Resu
                             return Ok(());
                         }
                         ExecFailType::Native(_)
+                        | ExecFailType::Spike(_)
                         | ExecFailType::Qemu(Some(QemuFailType::ErrorMsg))
                         | ExecFailType::Qemu(Some(QemuFailType::IllegalInsn)) => {
                             println!("Unexpected segfault");
-                            exit(1)
+                            exit(NOT_INTERESTING)
                         }
                     },
                     Some(i) => {
                         // Killed by timeout -k
                         println!("Unknown exec signal: {i}");
-                        exit(1);
+                        exit(NOT_INTERESTING);
                     }
                     None => unreachable!("If the exit code is None, the signal must be set!"),
                 }
             }
             Some(_) => {
                 println!("Unrecognized compilation failure: {}", stderr);
-                exit(1);
+                exit(NOT_INTERESTING);
             }
         }
         println!("Unrecognized failure: {}", stderr);
-        exit(1);
+        exit(NOT_INTERESTING);
     } else {
         let testcase_paths = &fail_info.testcase;
         let flags = (0..fail_info.testcase.len() + 1)
@@ -274,7 +340,12 @@ fn produce_fail(fail_info: &ExecFailInfo, working_dir: &PathBuf) -> anyhow::Resu
         let rv32 = flags
             .iter()
             .any(|x| x.iter().any(|s| s.contains("-march=rv32")));
-        check_for_ub(&sh, &fail_info.generator, &fail_info.testcase, rv32)?;
+        exit_on_check_error(check_for_ub(
+            &sh,
+            &fail_info.generator,
+            &fail_info.testcase,
+            rv32,
+        ));
 
         todo!();
     }
@@ -1,6 +1,7 @@
 use compiler_flags_gen::Action;
+use compiler_fuzz::reduction::NOT_INTERESTING;
 use compiler_fuzz::{
-    ignorable_warnings, FailInfo, GccFailType, IceFailInfo, IceFailType, LlvmFailType,
+    ignorable_warnings_for, FailInfo, GccFailType, IceFailInfo, IceFailType, LlvmFailType,
 };
 use std::fs::File;
 use std::io::Read;
@@ -62,7 +63,7 @@ fn produce_fail(sh: &Shell, fail_info: &IceFailInfo, working_dir: &Path) -> anyh
 
     let compilers = &fail_info.compilers;
 
-    let ignorable_warnings = &ignorable_warnings();
+    let ignorable_warnings = &ignorable_warnings_for(&fail_info.testcases);
 
     let command_output = if compilers.len() == 1 {
         let mut file = File::open(working_dir.join("reducible_compiler_opts.txt")).unwrap();
@@ -153,11 +154,11 @@ fn produce_fail(sh: &Shell, fail_info: &IceFailInfo, working_dir: &Path) -> anyh
 
             if !command_output.status.success() {
                 println!("Intermediate command failed! stderr: {stderr}");
-                exit(1)
+                exit(NOT_INTERESTING)
             }
             if stderr.contains("warning:") {
                 println!("Unexpected warning with intermediate command! {stderr}");
-                exit(1);
+                exit(NOT_INTERESTING);
             }
         }
 
@@ -184,17 +185,17 @@ fn produce_fail(sh: &Shell, fail_info: &IceFailInfo, working_dir: &Path) -> anyh
 
     if stderr.contains("warning:") {
         println!("Unexpected warning with command! {stderr}");
-        exit(1);
+        exit(NOT_INTERESTING);
     }
 
     match command_output.status.code().unwrap() {
         0 => {
             println!("This shouldn't pass!");
-            exit(1);
+            exit(NOT_INTERESTING);
         }
         124 => {
             println!("Timeout!");
-            exit(1);
+            exit(NOT_INTERESTING);
         }
         _ => {
             match fail_info.fail_type {
@@ -236,6 +237,10 @@ fn produce_fail(sh: &Shell, fail_info: &IceFailInfo, working_dir: &Path) -> anyh
                         return Ok(());
                     }
                 }
+                IceFailType::Llvm(Some(LlvmFailType::OptCrash))
+                | IceFailType::Llvm(Some(LlvmFailType::LlcCrash)) => {
+                    unreachable!("Generated IR is reduced with llvm-reduce")
+                }
                 IceFailType::Llvm(None) => {
                     // Has not been categorized, any fail will do.
                     return Ok(());
@@ -272,7 +277,7 @@ fn produce_fail(sh: &Shell, fail_info: &IceFailInfo, working_dir: &Path) -> anyh
                 }
             }
             println!("Unrecognized failure: {}", stderr);
-            exit(1);
+            exit(NOT_INTERESTING);
         }
     }
 }
@@ -1,6 +1,10 @@
+use compiler_fuzz::execute::{qemu_aarch64_cpu, spike_isa, spike_pk};
 use compiler_fuzz::generate::get_generator_flags;
-use compiler_fuzz::reduction::{check_for_ub, compile_clean_code};
-use compiler_fuzz::{ignorable_warnings, FailInfo, Runner, RuntimeFailInfo, RuntimeFailType};
+use compiler_fuzz::qemu::riscv_qemu_cpu;
+use compiler_fuzz::reduction::{
+    check_for_ub, compile_clean_code, exit_on_check_error, NOT_INTERESTING,
+};
+use compiler_fuzz::{ignorable_warnings_for, FailInfo, Runner, RuntimeFailInfo, RuntimeFailType};
 use std::fs::File;
 use std::io::Read;
 use std::os::unix::process::ExitStatusExt;
@@ -69,27 +73,37 @@ fn execute_code(
     ignorable_warnings: &Vec<String>,
     fail_type: &Option<RuntimeFailType>,
 ) -> anyhow::Result<String> {
-    compile_clean_code(
+    exit_on_check_error(compile_clean_code(
         sh,
         compilers,
         testcases,
         compiler_flags,
         PathBuf::from(format!("{file_prefix}_testcase.o")),
         ignorable_warnings,
-    )?;
+    ));
 
+    run_testcase(sh, runner, file_prefix, compiler_flags, fail_type)
+}
+
+/// Run `{file_prefix}_testcase.o` under `runner`
+fn run_testcase(
+    sh: &Shell,
+    runner: &Runner,
+    file_prefix: &str,
+    compiler_flags: &[Vec<String>],
+    fail_type: &Option<RuntimeFailType>,
+) -> anyhow::Result<String> {
     let mut run_command: std::process::Command = match &runner {
         Runner::Native => cmd!(sh, "timeout -k 0.1 2 ./{file_prefix}_testcase.o 1")
             .quiet()
             .into(),
         Runner::Qemu(qemu_config) => {
             let qemu_cpu = match &qemu_config.cpu_flags {
-                compiler_fuzz::RunnerArguments::Fixed(flags) => flags,
-                compiler_fuzz::RunnerArguments::Generated(generation_script) => &cmd!(
-                    sh,
-                    "{generation_script} --elf-file-path {file_prefix}_testcase.o --print-qemu-cpu"
-                )
-                .read()?,
+                compiler_fuzz::RunnerArguments::Fixed(flags) => flags.clone(),
+                compiler_fuzz::RunnerArguments::Generated => {
+                    riscv_qemu_cpu(&sh.current_dir().join(format!("{file_prefix}_testcase.o")))?
+                        .cpu()?
+                }
             };
 
             let qemu = if qemu_cpu.starts_with("rv32") {
@@ -103,6 +117,30 @@ fn execute_code(
                 .quiet()
                 .into()
         }
+        Runner::QemuAarch64(qemu_config) => {
+            let qemu = &qemu_config.path;
+            let compiler_flags = compiler_flags
+                .iter()
+                .map(|flags| flags.join(" "))
+                .collect::<Vec<_>>();
+            cmd!(sh, "timeout -k 0.1 2 {qemu} {file_prefix}_testcase.o 1")
+                .env("QEMU_CPU", qemu_aarch64_cpu(&compiler_flags))
+                .quiet()
+                .into()
+        }
+        Runner::Spike(spike_config) => {
+            let spike = &spike_config.path;
+            let program = sh.current_dir().join(format!("{file_prefix}_testcase.o"));
+            let compiler_flags = compiler_flags
+                .iter()
+                .map(|flags| flags.join(" "))
+                .collect::<Vec<_>>();
+            let isa = spike_isa(&program, &compiler_flags)?;
+            let pk = spike_pk(spike_config, &isa);
+            cmd!(sh, "timeout -k 0.1 10 {spike} --isa={isa} {pk} {program} 1")
+                .quiet()
+                .into()
+        }
     };
 
     println!("Run cmd: {:?}", run_command);
@@ -118,7 +156,9 @@ fn execute_code(
 
     match command_output.status.code() {
         Some(0) => match fail_type {
-            Some(RuntimeFailType::Mismatch) => Ok(stdout),
+            Some(RuntimeFailType::Mismatch) | Some(RuntimeFailType::VectorConfigMismatch) => {
+                Ok(stdout)
+            }
             None => {
                 // Unknown, any fail will do
                 Ok(stdout)
@@ -126,35 +166,35 @@ fn execute_code(
         },
         Some(1) => {
             println!("Unexpected execution fail");
-            exit(1);
+            exit(NOT_INTERESTING);
         }
         Some(124) => {
             // Timeout
             println!("Exec Timeout!");
-            exit(1);
+            exit(NOT_INTERESTING);
         }
         None => {
             match command_output.status.signal() {
                 Some(9) => {
                     // Killed by timeout -k
                     println!("Exec Timeout!");
-                    exit(1);
+                    exit(NOT_INTERESTING);
                 }
                 Some(4) => {
                     println!("Unexpected illegal insn");
-                    exit(1);
+                    exit(NOT_INTERESTING);
                 }
                 Some(i) => {
                     // Killed by timeout -k
                     println!("Unknown exec signal: {i}");
-                    exit(1);
+                    exit(NOT_INTERESTING);
                 }
                 None => unreachable!("If the exit code is None, the signal must be set!"),
             }
         }
         Some(_) => {
             println!("Unrecognized compilation failure: {}", stderr);
-            exit(1);
+            exit(NOT_INTERESTING);
         }
     }
 }
@@ -167,6 +207,7 @@ fn produce_fail(fail_info: &RuntimeFailInfo, working_dir: &PathBuf) -> anyhow::R
     let artifacts = vec![
         PathBuf::from(format!("fast_testcase.o")),
         PathBuf::from(format!("slow_testcase.o")),
+        PathBuf::from("sweep_testcase.o"),
     ];
     for artifact in artifacts {
         sh.remove_path(artifact)?;
@@ -189,7 +230,12 @@ fn produce_fail(fail_info: &RuntimeFailInfo, working_dir: &PathBuf) -> anyhow::R
         let compiler_flags_str = compiler_flags_str.replace("\n", "");
 
         let rv32 = compiler_flags_str.contains("-march=rv32");
-        check_for_ub(&sh, &fail_info.generator, &fail_info.testcase, rv32)?;
+        exit_on_check_error(check_for_ub(
+            &sh,
+            &fail_info.generator,
+            &fail_info.testcase,
+            rv32,
+        ));
 
         let rv32_flags = if rv32 { "-m32 -malign-double" } else { "" };
 
@@ -210,12 +256,12 @@ fn produce_fail(fail_info: &RuntimeFailInfo, working_dir: &PathBuf) -> anyhow::R
             &fail_info.fail_type,
         )?;
 
-        let slow_compiler_flags = compiler_flags_str
+        let slow_compiler_flags = vec![compiler_flags_str
             .split_whitespace()
             .map(|s| s.to_string())
-            .collect::<Vec<String>>();
+            .collect::<Vec<String>>()];
 
-        let ignorable_warnings = ignorable_warnings();
+        let ignorable_warnings = ignorable_warnings_for(&fail_info.testcase);
 
         let slow_stdout = execute_code(
             &sh,
@@ -223,34 +269,20 @@ fn produce_fail(fail_info: &RuntimeFailInfo, working_dir: &PathBuf) -> anyhow::R
             &fail_info.testcase,
             &fail_info.slow_runner,
             "slow",
-            &vec![slow_compiler_flags],
+            &slow_compiler_flags,
             &ignorable_warnings,
             &fail_info.fail_type,
         )?;
 
         println!("fast: {fast_stdout}\nslow: {slow_stdout}");
 
-        match fail_info.fail_type {
-            Some(RuntimeFailType::Mismatch) => {
-                if fast_stdout != slow_stdout {
-                    println!("Success");
-                    exit(0);
-                } else {
-                    println!("Failure: No mismatch found");
-                    exit(1);
-                }
-            }
-            None => {
-                // Unknown, any error will do
-                if fast_stdout != slow_stdout {
-                    println!("Success");
-                    exit(0);
-                } else {
-                    println!("Failure: No mismatch found");
-                    exit(1);
-                }
-            }
-        }
+        exit_on_mismatch(
+            &sh,
+            fail_info,
+            &fast_stdout,
+            &slow_stdout,
+            &slow_compiler_flags,
+        )
     } else {
         // Multiple
         assert!(fail_info.fast_compiler.len() > 1 && fail_info.fast_compiler.len() > 1);
@@ -362,7 +394,7 @@ fn produce_fail(fail_info: &RuntimeFailInfo, working_dir: &PathBuf) -> anyhow::R
             &vec!["-w".to_string()],
             &fail_info.fail_type,
         )?;
-        let ignorable_warnings = ignorable_warnings();
+        let ignorable_warnings = ignorable_warnings_for(&fail_info.testcase);
 
         let slow_stdout = execute_code(
             &sh,
@@ -377,26 +409,46 @@ fn produce_fail(fail_info: &RuntimeFailInfo, working_dir: &PathBuf) -> anyhow::R
 
         println!("fast: {fast_stdout}\nslow: {slow_stdout}");
 
-        match fail_info.fail_type {
-            Some(RuntimeFailType::Mismatch) => {
-                if fast_stdout != slow_stdout {
-                    println!("Success");
-                    exit(0);
-                } else {
-                    println!("Failure: No mismatch found");
-                    exit(1);
-                }
-            }
-            None => {
-                // Unknown, any error will do
-                if fast_stdout != slow_stdout {
-                    println!("Success");
-                    exit(0);
-                } else {
-                    println!("Failure: No mismatch found");
-                    exit(1);
-                }
-            }
+        exit_on_mismatch(&sh, fail_info, &fast_stdout, &slow_stdout, &slow_flags)
+    }
+}
+
+/// Exit with whether the testcase still shows the mismatch it was found with.
+/// For a vector config mismatch that is the slow binary printing something
+/// else under sweep_runner than under slow_runner, whatever the fast binary
+/// printed.
+fn exit_on_mismatch(
+    sh: &Shell,
+    fail_info: &RuntimeFailInfo,
+    fast_stdout: &str,
+    slow_stdout: &str,
+    slow_compiler_flags: &[Vec<String>],
+) -> anyhow::Result<()> {
+    let mismatch = match fail_info.fail_type {
+        Some(RuntimeFailType::VectorConfigMismatch) => {
+            let Some(sweep_runner) = &fail_info.sweep_runner else {
+                anyhow::bail!("Vector config mismatch without a sweep_runner");
+            };
+            sh.copy_file("slow_testcase.o", "sweep_testcase.o")?;
+            let sweep_stdout = run_testcase(
+                sh,
+                sweep_runner,
+                "sweep",
+                slow_compiler_flags,
+                &fail_info.fail_type,
+            )?;
+            println!("sweep: {sweep_stdout}");
+            slow_stdout != sweep_stdout
         }
+        // Unknown, any mismatch will do
+        Some(RuntimeFailType::Mismatch) | None => fast_stdout != slow_stdout,
+    };
+
+    if mismatch {
+        println!("Success");
+        exit(0);
+    } else {
+        println!("Failure: No mismatch found");
+        exit(NOT_INTERESTING);
     }
 }
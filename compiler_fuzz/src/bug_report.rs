@@ -0,0 +1,230 @@
+//! Bug reports for reduced ICEs: a plain text form for GCC's Bugzilla and a
+//! markdown issue for LLVM's GitHub. Templates fill in `{{name}}` and drop the
+//! lines from `{{#name}}` to `{{/name}}` when `name` is empty. The reduction
+//! config can point at templates to use instead of the built in ones.
+
+use std::{fs, path::Path};
+
+use crate::{
+    finds_db::find_signature, Architecture, BuildIdentity, IceFailInfo, IceFailType,
+    ReductionConfig,
+};
+
+pub const GCC_BUGZILLA_TEMPLATE: &str = include_str!("../templates/gcc-bugzilla.txt");
+pub const LLVM_GITHUB_TEMPLATE: &str = include_str!("../templates/llvm-github.md");
+
+pub fn report_template(
+    config: &ReductionConfig,
+    fail_type: &IceFailType,
+) -> anyhow::Result<String> {
+    let (user_template, template) = match fail_type {
+        IceFailType::Gcc(_) => (&config.gcc_report_template, GCC_BUGZILLA_TEMPLATE),
+        IceFailType::Llvm(_) => (&config.llvm_report_template, LLVM_GITHUB_TEMPLATE),
+    };
+    Ok(match user_template {
+        Some(user_template) => fs::read_to_string(user_template)?,
+        None => template.to_string(),
+    })
+}
+
+pub fn render_template(template: &str, values: &[(&str, String)]) -> String {
+    let value = |name: &str| {
+        values
+            .iter()
+            .find(|(value_name, _)| *value_name == name)
+            .map(|(_, value)| value.as_str())
+            .unwrap_or_default()
+    };
+
+    // Drop empty blocks first so their values don't need to be escaped
+    let mut skipping: Vec<&str> = vec![];
+    let mut lines = vec![];
+    for line in template.lines() {
+        let marker = line.trim();
+        if let Some(name) = marker
+            .strip_prefix("{{#")
+            .and_then(|marker| marker.strip_suffix("}}"))
+        {
+            if !skipping.is_empty() || value(name).is_empty() {
+                skipping.push(name);
+            }
+            continue;
+        }
+        if let Some(name) = marker
+            .strip_prefix("{{/")
+            .and_then(|marker| marker.strip_suffix("}}"))
+        {
+            if skipping.last() == Some(&name) {
+                skipping.pop();
+            }
+            continue;
+        }
+        if skipping.is_empty() {
+            lines.push(line);
+        }
+    }
+
+    // Substitute in one pass so `{{name}}` inside a value stays as it is
+    let template = lines.join("\n") + "\n";
+    let mut report = String::with_capacity(template.len());
+    let mut rest = template.as_str();
+    while let Some(start) = rest.find("{{") {
+        report.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after.find("}}") {
+            Some(end) if values.iter().any(|(name, _)| *name == &after[..end]) => {
+                report.push_str(value(&after[..end]));
+                rest = &after[end + 2..];
+            }
+            _ => {
+                report.push_str("{{");
+                rest = after;
+            }
+        }
+    }
+    report.push_str(rest);
+    report
+}
+
+/// One line summary in the style of existing reports, e.g.
+/// `RISC-V: ICE in foo, at bar.cc:12`
+pub fn summary(architecture: &Architecture, stderr: &str) -> String {
+    let signature = find_signature(stderr).replace("internal compiler error:", "ICE");
+    format!("{}: {signature}", target_name(architecture))
+}
+
+pub fn target_name(architecture: &Architecture) -> &'static str {
+    match architecture {
+        Architecture::X86 => "x86",
+        Architecture::Riscv => "RISC-V",
+        Architecture::Aarch64 => "AArch64",
+    }
+}
+
+/// Bugzilla keywords for GCC, GitHub labels for LLVM
+pub fn labels(fail_info: &IceFailInfo) -> Vec<String> {
+    match fail_info.fail_type {
+        IceFailType::Gcc(_) => vec!["ice-on-valid-code".to_string()],
+        IceFailType::Llvm(_) => vec![
+            llvm_backend_label(&fail_info.architecture).to_string(),
+            "crash-on-valid".to_string(),
+        ],
+    }
+}
+
+/// The label LLVM uses for issues in a target's backend
+pub fn llvm_backend_label(architecture: &Architecture) -> &'static str {
+    match architecture {
+        Architecture::X86 => "backend:X86",
+        Architecture::Riscv => "backend:RISC-V",
+        Architecture::Aarch64 => "backend:AArch64",
+    }
+}
+
+/// Bugzilla's target triplet field
+pub fn gcc_target(architecture: &Architecture) -> &'static str {
+    match architecture {
+        Architecture::X86 => "x86_64-*-*",
+        Architecture::Riscv => "riscv*-*-*",
+        Architecture::Aarch64 => "aarch64*-*-*",
+    }
+}
+
+/// The last -march in the flags, which is the one the compiler used
+pub fn march(flags: &str) -> String {
+    flags
+        .split_whitespace()
+        .rev()
+        .find_map(|flag| flag.strip_prefix("-march="))
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Version lines of the compiler that crashed, from the build identities the
+/// fuzzer recorded
+pub fn version(fail_info: &IceFailInfo, compiler: &Path) -> String {
+    let Some(identity) = compiler_identity(fail_info, compiler) else {
+        return "unknown".to_string();
+    };
+
+    let mut lines = vec![identity
+        .version
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .to_string()];
+    lines.extend(identity.configured_with.clone());
+    lines.push(format!("sha256: {}", identity.sha256));
+    lines.join("\n")
+}
+
+pub fn compiler_identity<'a>(
+    fail_info: &'a IceFailInfo,
+    compiler: &Path,
+) -> Option<&'a BuildIdentity> {
+    let compilers = &fail_info.build_identities.as_ref()?.compilers;
+    // The reduction config may have moved the compiler since
+    compilers
+        .iter()
+        .find(|identity| identity.path == compiler)
+        .or_else(|| {
+            compilers
+                .iter()
+                .find(|identity| identity.path.file_name() == compiler.file_name())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn template_values() {
+        let template = "\
+Summary: {{summary}}
+{{#flags}}
+Flags: {{flags}}
+{{/flags}}
+{{#godbolt}}
+Godbolt: {{godbolt}}
+{{/godbolt}}
+Left alone: {{unknown}} {{
+";
+        let report = render_template(
+            template,
+            &[
+                ("summary", "ICE in {{flags}}".to_string()),
+                ("flags", "-O3".to_string()),
+                ("godbolt", String::new()),
+            ],
+        );
+        assert_eq!(
+            report,
+            "\
+Summary: ICE in {{flags}}
+Flags: -O3
+Left alone: {{unknown}} {{
+"
+        );
+    }
+
+    #[test]
+    fn nested_empty_blocks() {
+        let template = "\
+{{#outer}}
+a
+{{#inner}}
+b
+{{/inner}}
+{{/outer}}
+c";
+        let values = [("outer", String::new()), ("inner", "x".to_string())];
+        assert_eq!(render_template(template, &values), "c\n");
+    }
+
+    #[test]
+    fn llvm_labels() {
+        assert_eq!(llvm_backend_label(&Architecture::X86), "backend:X86");
+        assert_eq!(llvm_backend_label(&Architecture::Riscv), "backend:RISC-V");
+    }
+}
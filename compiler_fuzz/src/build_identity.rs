@@ -0,0 +1,114 @@
+//! Which builds of the compilers, runners and generator a campaign ran with.
+//! Worked out once when the fuzzer starts and copied into every fail_info.yaml
+//! so a find can be tied to a revision long after the binaries were rebuilt.
+
+use std::{
+    path::Path,
+    sync::{Mutex, OnceLock},
+};
+
+use compiler_flags_gen::Compiler;
+use xshell::{cmd, Shell};
+
+use crate::{detect_compiler, BuildIdentities, BuildIdentity, FuzzGenerator, LlvmIrSource, Runner};
+
+static BUILD_IDENTITIES: OnceLock<Mutex<BuildIdentities>> = OnceLock::new();
+
+pub fn set_build_identities(build_identities: BuildIdentities) {
+    *BUILD_IDENTITIES
+        .get_or_init(|| Mutex::new(BuildIdentities::default()))
+        .lock()
+        .unwrap() = build_identities;
+}
+
+/// The identities set for this campaign, if the fuzzer set any
+pub fn current_build_identities() -> Option<BuildIdentities> {
+    BUILD_IDENTITIES
+        .get()
+        .map(|build_identities| build_identities.lock().unwrap().clone())
+}
+
+pub fn compiler_identity(sh: &Shell, compiler_path: &Path) -> anyhow::Result<BuildIdentity> {
+    let mut identity = build_identity(sh, compiler_path, &["--version"])?;
+    if matches!(detect_compiler(compiler_path), Some(Compiler::Gcc)) {
+        let verbose = cmd!(sh, "timeout 10 {compiler_path} -v")
+            .quiet()
+            .ignore_status()
+            .output()?;
+        identity.configured_with = String::from_utf8_lossy(&verbose.stderr)
+            .lines()
+            .find(|line| line.starts_with("Configured with:"))
+            .map(str::to_string);
+        identity.full_version = Some(
+            cmd!(sh, "timeout 10 {compiler_path} -dumpfullversion")
+                .quiet()
+                .read()?,
+        );
+    }
+    Ok(identity)
+}
+
+pub fn runner_identities(sh: &Shell, runner: &Runner) -> anyhow::Result<Vec<BuildIdentity>> {
+    Ok(match runner {
+        Runner::Native => vec![],
+        Runner::Qemu(qemu_config) => vec![
+            build_identity(sh, &qemu_config.rv32path, &["--version"])?,
+            build_identity(sh, &qemu_config.rv64path, &["--version"])?,
+        ],
+        Runner::QemuAarch64(qemu_config) => {
+            vec![build_identity(sh, &qemu_config.path, &["--version"])?]
+        }
+        Runner::Spike(spike_config) => {
+            // Spike has no --version, the help text starts with it
+            let mut identity = build_identity(sh, &spike_config.path, &["--help"])?;
+            identity.version = identity.version.lines().next().unwrap_or_default().into();
+            vec![identity]
+        }
+    })
+}
+
+pub fn generator_identity(
+    sh: &Shell,
+    generator: &FuzzGenerator,
+) -> anyhow::Result<Option<BuildIdentity>> {
+    let generator_path = match generator {
+        FuzzGenerator::Csmith(csmith_config) => &csmith_config.path,
+        FuzzGenerator::Yarpgen(yarpgen_config) => &yarpgen_config.path,
+        FuzzGenerator::Rustsmith(rustsmith_config) => &rustsmith_config.path,
+        FuzzGenerator::LlvmIr(llvm_ir_config) => match &llvm_ir_config.source {
+            LlvmIrSource::LlvmStress(llvm_stress_config) => &llvm_stress_config.path,
+            LlvmIrSource::Csmith(csmith_ir_config) => &csmith_ir_config.csmith.path,
+        },
+        FuzzGenerator::Fixed(_) | FuzzGenerator::Corpus(_) | FuzzGenerator::Mutate(_) => {
+            return Ok(None)
+        }
+    };
+    Ok(Some(build_identity(sh, generator_path, &["--version"])?))
+}
+
+/// Hash the binary and keep what it prints for `version_args`
+fn build_identity(sh: &Shell, path: &Path, version_args: &[&str]) -> anyhow::Result<BuildIdentity> {
+    let sha256 = cmd!(sh, "sha256sum {path}").quiet().read()?;
+    let output = cmd!(sh, "timeout 10 {path} {version_args...}")
+        .quiet()
+        .ignore_status()
+        .output()?;
+    // Some tools print their version to stderr
+    let version = if output.stdout.is_empty() {
+        String::from_utf8_lossy(&output.stderr)
+    } else {
+        String::from_utf8_lossy(&output.stdout)
+    };
+
+    Ok(BuildIdentity {
+        path: path.to_path_buf(),
+        sha256: sha256
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_string(),
+        version: version.trim().to_string(),
+        configured_with: None,
+        full_version: None,
+    })
+}
@@ -1,17 +1,26 @@
 use std::{
+    collections::HashMap,
     os::unix::process::ExitStatusExt,
     path::{Path, PathBuf},
     process::{Command, Output},
+    sync::{Mutex, OnceLock},
     time::Instant,
 };
 
 use anyhow::Context;
-use compiler_flags_gen::{arbitrary_flags_compatible, Action, Compiler};
+use compiler_flags_gen::{
+    arbitrary_flags_compatible_from_seed, arbitrary_target_flags_from_seed, catalog::FlagCatalog,
+    flag_value::FlagSetValue, random_flag_seed, Action, Compiler, Target,
+};
 use xshell::{cmd, Shell};
 
 use crate::{
-    Architecture, CompilerArguments, FailInfo, FlagsGenerator, FuzzCompiler, FuzzGenerator,
-    IceFailInfo, IceFailType, Stats,
+    build_identity::current_build_identities,
+    detect_compiler,
+    finds_db::record_find,
+    generate::{current_corpus_testcase, current_generator_args},
+    is_llvm_ir, llvm_tool, Architecture, CompilerArguments, FailInfo, FlagsGenerator, FuzzCompiler,
+    FuzzGenerator, IceFailInfo, IceFailType, LlvmFailType, Stats,
 };
 
 pub fn get_compile_flags(
@@ -20,39 +29,146 @@ pub fn get_compile_flags(
     action: &Action,
     base_flags: Vec<&str>,
     count: usize,
-) -> Vec<Vec<String>> {
+) -> anyhow::Result<Vec<Vec<String>>> {
+    let mut seed = random_flag_seed();
+    get_seeded_compile_flags(compiler, generator, action, base_flags, count, &mut seed)
+}
+
+/// Same as get_compile_flags but generated flags come from the given seed
+pub fn get_seeded_compile_flags(
+    compiler: &FuzzCompiler,
+    generator: &FuzzGenerator,
+    action: &Action,
+    base_flags: Vec<&str>,
+    count: usize,
+    seed: &mut Vec<u8>,
+) -> anyhow::Result<Vec<Vec<String>>> {
+    let flag_values = get_seeded_flag_values(compiler, generator, action, count, seed)?;
+    Ok(render_compile_flags(compiler, base_flags, &flag_values))
+}
+
+pub fn get_flag_values(
+    compiler: &FuzzCompiler,
+    generator: &FuzzGenerator,
+    action: &Action,
+    count: usize,
+) -> anyhow::Result<Vec<FlagSetValue>> {
+    let mut seed = random_flag_seed();
+    get_seeded_flag_values(compiler, generator, action, count, &mut seed)
+}
+
+/// Generate (or parse fixed) flags without rendering them for a compiler
+pub fn get_seeded_flag_values(
+    compiler: &FuzzCompiler,
+    generator: &FuzzGenerator,
+    action: &Action,
+    count: usize,
+    seed: &mut Vec<u8>,
+) -> anyhow::Result<Vec<FlagSetValue>> {
     let rv64_only = match generator {
         FuzzGenerator::Csmith(_) => false,
         FuzzGenerator::Yarpgen(_) => true,
         FuzzGenerator::Rustsmith(_) => false,
         FuzzGenerator::Fixed(_) => false,
+        FuzzGenerator::Corpus(_) => false,
+        FuzzGenerator::LlvmIr(_) => false,
+        FuzzGenerator::Mutate(_) => false,
     };
 
-    let compiler_flags = match &compiler.arguments {
-        CompilerArguments::Generated(FlagsGenerator { compiler, flag_set }) => {
-            arbitrary_flags_compatible(compiler, action, flag_set, rv64_only, count)
+    let flag_values = match &compiler.arguments {
+        CompilerArguments::Generated(FlagsGenerator {
+            compiler: flags_compiler,
+            flag_set,
+            catalog_flags,
+        }) => {
+            let mut flags_seed = seed.clone();
+            let mut flag_values = match compiler.architecture {
+                Architecture::Riscv => arbitrary_flags_compatible_from_seed(
+                    flags_compiler,
+                    action,
+                    flag_set,
+                    rv64_only,
+                    count,
+                    &mut flags_seed,
+                ),
+                Architecture::X86 | Architecture::Aarch64 => {
+                    let target = if matches!(compiler.architecture, Architecture::X86) {
+                        Target::X86_64
+                    } else {
+                        Target::Aarch64
+                    };
+                    let flag_value = arbitrary_target_flags_from_seed(
+                        &target,
+                        flags_compiler,
+                        action,
+                        flag_set,
+                        &mut flags_seed,
+                    );
+                    (0..count).map(|_| flag_value.clone()).collect()
+                }
+            };
+            if catalog_flags.unwrap_or(false) {
+                // Catalog flags are drawn from the bytes after the ones the
+                // flag structs consumed so the whole seed stays reproducible
+                let consumed = flags_seed.len();
+                let mut catalog_seed = seed[consumed..].to_vec();
+                let extra_flags = get_catalog(flags_compiler, &compiler.path)?
+                    .arbitrary_flags_from_seed(&mut catalog_seed);
+                for flag_value in flag_values.iter_mut() {
+                    flag_value.push_new_flags(&extra_flags);
+                }
+                flags_seed.append(&mut catalog_seed);
+            }
+            *seed = flags_seed;
+            flag_values
         }
-        CompilerArguments::Fixed(flags) => (0..count).map(|_| flags.clone()).collect(),
+        CompilerArguments::Fixed(flags) => (0..count).map(|_| FlagSetValue::parse(flags)).collect(),
     };
-    assert_eq!(compiler_flags.len(), count);
+    assert_eq!(flag_values.len(), count);
+
+    Ok(flag_values)
+}
 
-    compiler_flags
+/// Render flag values in the syntax of the compiler that will run them
+pub fn render_compile_flags(
+    compiler: &FuzzCompiler,
+    base_flags: Vec<&str>,
+    flag_values: &[FlagSetValue],
+) -> Vec<Vec<String>> {
+    let flag_syntax = detect_compiler(&compiler.path).unwrap_or(Compiler::Gcc);
+
+    flag_values
         .iter()
-        .map(|f| {
-            let mut compiler_flags = base_flags.clone();
-            let mut other_flags = f.split_whitespace().collect::<Vec<_>>();
-            compiler_flags.append(&mut other_flags);
-            compiler_flags.iter().map(|x| x.to_string()).collect()
+        .map(|flag_value| {
+            let mut compiler_flags = base_flags.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+            compiler_flags.append(&mut flag_value.render(&flag_syntax));
+            compiler_flags
         })
         .collect::<Vec<Vec<String>>>()
 }
 
+/// Flag catalogs are built once per compiler since building one runs the
+/// compiler. They live for the rest of the run.
+fn get_catalog(compiler: &Compiler, path: &Path) -> anyhow::Result<&'static FlagCatalog> {
+    static CATALOGS: OnceLock<Mutex<HashMap<PathBuf, &'static FlagCatalog>>> = OnceLock::new();
+    let mut catalogs = CATALOGS.get_or_init(Default::default).lock().unwrap();
+    if let Some(catalog) = catalogs.get(path) {
+        return Ok(catalog);
+    }
+    let catalog = FlagCatalog::from_compiler(compiler, path)
+        .with_context(|| format!("Failed to build flag catalog for {path:?}"))?;
+    let catalog = &*Box::leak(Box::new(catalog));
+    catalogs.insert(path.to_path_buf(), catalog);
+    Ok(catalog)
+}
+
 pub fn run_compiler(
     sh: &Shell,
     compiler_ms: &mut u128,
     stats: &mut Stats,
     temp_dir: &Path,
     finds_dir: &Path,
+    runner_id: Option<u32>,
     generator: &FuzzGenerator,
     action: &Action,
     compilers: &[&FuzzCompiler],
@@ -65,7 +181,72 @@ pub fn run_compiler(
 
     assert!(!testcase_paths.is_empty());
 
-    let result = if testcase_paths.len() == 1 {
+    let result = if is_llvm_ir(testcase_paths) {
+        // Generated IR skips the frontend: opt -O3, then llc with flags
+        // rendered for the target
+        assert!(
+            testcase_paths.len() == 1 && all_flags.len() == 1 && compilers.len() == 1,
+            "LLVM IR testcases are always a single file"
+        );
+
+        let opt = llvm_tool(&compilers[0].path, "opt");
+        let llc = llvm_tool(&compilers[0].path, "llc");
+        let testcase_path = testcase_paths[0].clone();
+        let optimized_path = testcase_path.with_extension("opt.ll");
+
+        let mut opt_command: std::process::Command = cmd!(
+            sh,
+            "timeout -k 0.1 5 {opt} -O3 -S {testcase_path} -o {optimized_path}"
+        )
+        .quiet()
+        .into();
+
+        let command_output = opt_command.output()?;
+        if !command_output.status.success() {
+            let triage_info = IceTriageInfo {
+                command_output: &command_output,
+                temp_dir,
+                finds_dir,
+                runner_id,
+                compiler: Compiler::Llvm,
+                llvm_fail_type: Some(LlvmFailType::OptCrash),
+                compiler_paths: &[opt],
+                testcase_paths: &[testcase_path],
+                flags: &vec![vec!["-O3".to_string(), "-S".to_string()]],
+                architecture: &compilers[0].architecture,
+                action: action.clone(),
+                generator,
+            };
+
+            triage_compile_command(sh, opt_command, &triage_info, stats)?
+        } else {
+            let flags = &all_flags[0];
+            let mut llc_command: std::process::Command = cmd!(
+                sh,
+                "timeout -k 0.1 5 {llc} {flags...} {optimized_path} -o {output_file}"
+            )
+            .quiet()
+            .into();
+
+            let command_output = llc_command.output()?;
+            let triage_info = IceTriageInfo {
+                command_output: &command_output,
+                temp_dir,
+                finds_dir,
+                runner_id,
+                compiler: Compiler::Llvm,
+                llvm_fail_type: Some(LlvmFailType::LlcCrash),
+                compiler_paths: &[llc],
+                testcase_paths: &[optimized_path],
+                flags: &vec![flags.iter().map(|s| s.to_string()).collect()],
+                architecture: &compilers[0].architecture,
+                action: action.clone(),
+                generator,
+            };
+
+            triage_compile_command(sh, llc_command, &triage_info, stats)?
+        }
+    } else if testcase_paths.len() == 1 {
         assert!(
             all_flags.len() == 1,
             "Must be exactly one set of flags specified {all_flags:?}"
@@ -86,15 +267,9 @@ pub fn run_compiler(
             command_output: &command_output,
             temp_dir,
             finds_dir,
-            compiler: if compiler_path.contains("gcc") {
-                Compiler::Gcc
-            } else if compiler_path.contains("clang") {
-                Compiler::Llvm
-            } else if compiler_path.contains("rustc") {
-                Compiler::Rustc
-            } else {
-                todo!();
-            },
+            runner_id,
+            compiler: detect_compiler(Path::new(compiler_path)).expect("Unknown compiler"),
+            llvm_fail_type: None,
             compiler_paths: &[compilers[0].path.clone()],
             testcase_paths: &[testcase_path],
             flags: &vec![flags.iter().map(|s| s.to_string()).collect()],
@@ -137,13 +312,9 @@ pub fn run_compiler(
                 command_output: &command_output,
                 temp_dir,
                 finds_dir,
-                compiler: if compiler_path.contains("gcc") {
-                    Compiler::Gcc
-                } else if compiler_path.contains("clang") {
-                    Compiler::Llvm
-                } else {
-                    todo!()
-                },
+                runner_id,
+                compiler: detect_compiler(Path::new(compiler_path)).expect("Unknown compiler"),
+                llvm_fail_type: None,
                 compiler_paths: &[compilers[i].path.clone()],
                 testcase_paths: &[testcase_path.to_path_buf()],
                 flags: &vec![flags.iter().map(|s| s.to_string()).collect()],
@@ -191,13 +362,9 @@ pub fn run_compiler(
             command_output: &command_output,
             temp_dir,
             finds_dir,
-            compiler: if compiler_path.contains("gcc") {
-                Compiler::Gcc
-            } else if compiler_path.contains("clang") {
-                Compiler::Llvm
-            } else {
-                todo!()
-            },
+            runner_id,
+            compiler: detect_compiler(Path::new(compiler_path)).expect("Unknown compiler"),
+            llvm_fail_type: None,
             compiler_paths: &compiler_paths,
             testcase_paths: &testcase_paths,
             flags: &vec![flags.iter().map(|s| s.to_string()).collect()],
@@ -216,7 +383,9 @@ pub struct IceTriageInfo<'a> {
     command_output: &'a Output, // The exit code/signal/stderr being considered
     temp_dir: &'a Path,         // Where the potential failure is stored
     finds_dir: &'a Path,        // Where to copy this to if it's interesting
+    runner_id: Option<u32>,     // Which fuzzer found it, for the finds database
     compiler: Compiler,         // The type of compiler that produced command_output
+    llvm_fail_type: Option<LlvmFailType>, // Set when the LLVM tool that failed is known
     compiler_paths: &'a [PathBuf],
     testcase_paths: &'a [PathBuf],
     flags: &'a Vec<Vec<String>>,
@@ -348,6 +517,7 @@ fn log_error(sh: &Shell, triage_info: &IceTriageInfo) -> anyhow::Result<()> {
     let command_output = triage_info.command_output;
     let temp_dir = triage_info.temp_dir;
     let finds_dir = triage_info.finds_dir;
+    let runner_id = triage_info.runner_id;
     let flags = triage_info.flags;
     let compiler_paths = triage_info.compiler_paths;
     let testcase_paths = triage_info.testcase_paths;
@@ -379,23 +549,43 @@ fn log_error(sh: &Shell, triage_info: &IceTriageInfo) -> anyhow::Result<()> {
         .context("When attempting to save stderr to output directory")?;
     sh.write_file(dump_dir.join("stdout.txt"), &command_output.stdout)
         .context("When attempting to save stdout to output directory")?;
+    let fail_info = FailInfo::Ice(IceFailInfo {
+        compilers: compiler_paths.to_vec(),
+        architecture: architecture.clone(),
+        testcases: testcase_paths.to_vec(),
+        action: action.clone(),
+        generator: generator.clone(),
+        generator_args: current_generator_args(generator),
+        corpus_testcase: current_corpus_testcase(generator).map(|testcase| testcase.path),
+        build_identities: current_build_identities(),
+        first_bad_commit: None,
+        fail_type: match compiler {
+            Compiler::Gcc => IceFailType::Gcc(None),
+            Compiler::Llvm => IceFailType::Llvm(triage_info.llvm_fail_type.clone()),
+            Compiler::Rustc => IceFailType::Llvm(None),
+        },
+    });
     sh.write_file(
         dump_dir.join("fail_info.yaml"),
-        serde_yaml::to_string(&FailInfo::Ice(IceFailInfo {
-            compilers: compiler_paths.to_vec(),
-            architecture: architecture.clone(),
-            testcases: testcase_paths.to_vec(),
-            action: action.clone(),
-            generator: generator.clone(),
-            fail_type: match compiler {
-                Compiler::Gcc => IceFailType::Gcc(None),
-                Compiler::Llvm => IceFailType::Llvm(None),
-                Compiler::Rustc => IceFailType::Llvm(None),
-            },
-        }))
-        .unwrap(),
+        serde_yaml::to_string(&fail_info).unwrap(),
     )
     .context("When attempting to save run info to output directory")?;
 
+    let flags = flags
+        .iter()
+        .map(|flags| flags.join(" "))
+        .collect::<Vec<_>>()
+        .join(" ; ");
+    record_find(
+        sh,
+        finds_dir,
+        runner_id,
+        &dump_dir,
+        &fail_info,
+        &flags,
+        &String::from_utf8_lossy(&command_output.stderr),
+    )
+    .context("When attempting to record the find")?;
+
     Ok(())
 }
@@ -0,0 +1,405 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashSet},
+    fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use rand::Rng;
+use xshell::{cmd, Shell};
+
+use crate::CoverageConfig;
+
+const GCDA_MAGIC: u32 = 0x67636461; // "gcda"
+const GCOV_TAG_FUNCTION: u32 = 0x01000000;
+const GCOV_TAG_ARC_COUNTS: u32 = 0x01a10000;
+/// Corpus entries to keep. Once full, the entry that reached the fewest new
+/// edges makes room.
+const MAX_CORPUS_ENTRIES: usize = 1024;
+
+/// A covered counter. Identified by the file/function it belongs to and its
+/// index so different runs of the same compiler can be compared.
+#[derive(Hash, PartialEq, Eq, Clone, Debug)]
+pub struct Edge {
+    pub file: String,
+    pub function: String,
+    pub counter: u32,
+}
+
+/// Flag seed and the testcase it was compiled with that together reached new
+/// edges
+pub struct CorpusEntry {
+    pub seed: Vec<u8>,
+    /// testcase_hash of the testcase
+    pub testcase: u64,
+    pub new_edges: usize,
+}
+
+pub struct FlagCorpus {
+    pub entries: Vec<CorpusEntry>,
+    pub seen_edges: HashSet<Edge>,
+}
+
+impl FlagCorpus {
+    pub fn new() -> Self {
+        FlagCorpus {
+            entries: vec![],
+            seen_edges: HashSet::new(),
+        }
+    }
+
+    /// Pick the seed for the next iteration. Mostly mutations of interesting
+    /// seeds with the occasional fresh seed so we don't get stuck. A seed's
+    /// new edges were found on one testcase, so seeds are preferred on the
+    /// testcase they were found on since that is where they are most likely
+    /// to reach more.
+    pub fn next_seed(&self, testcase: u64) -> Vec<u8> {
+        let mut rng = rand::thread_rng();
+        if self.entries.is_empty() || rng.gen_ratio(1, 10) {
+            return compiler_flags_gen::random_flag_seed();
+        }
+        let same_testcase = self
+            .entries
+            .iter()
+            .filter(|entry| entry.testcase == testcase)
+            .collect::<Vec<_>>();
+        let parent = if !same_testcase.is_empty() && rng.gen_ratio(1, 2) {
+            same_testcase[rng.gen_range(0..same_testcase.len())]
+        } else {
+            &self.entries[rng.gen_range(0..self.entries.len())]
+        };
+        compiler_flags_gen::mutate_flag_seed(&parent.seed)
+    }
+
+    /// Merge edges from a run. Returns the number of edges we hadn't seen before.
+    /// Seeds that reach new edges are added to the corpus along with the
+    /// testcase they were compiled with.
+    pub fn add(&mut self, seed: Vec<u8>, testcase: u64, edges: HashSet<Edge>) -> usize {
+        let new_edges = edges
+            .into_iter()
+            .filter(|edge| self.seen_edges.insert(edge.clone()))
+            .count();
+
+        if new_edges > 0 {
+            if self.entries.len() >= MAX_CORPUS_ENTRIES {
+                let (weakest, _) = self
+                    .entries
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, entry)| entry.new_edges)
+                    .unwrap();
+                self.entries.swap_remove(weakest);
+            }
+            self.entries.push(CorpusEntry {
+                seed,
+                testcase,
+                new_edges,
+            });
+        }
+
+        new_edges
+    }
+}
+
+impl Default for FlagCorpus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Identifies a testcase by its contents
+pub fn testcase_hash(sh: &Shell, testcase_paths: &[PathBuf]) -> anyhow::Result<u64> {
+    let mut hasher = DefaultHasher::new();
+    for testcase_path in testcase_paths {
+        sh.read_binary_file(testcase_path)?.hash(&mut hasher);
+    }
+    Ok(hasher.finish())
+}
+
+/// Point the instrumented compiler at an empty coverage directory.
+/// The returned guards must be kept alive while the compiler runs.
+pub fn push_coverage_env<'a>(
+    sh: &'a Shell,
+    config: &CoverageConfig,
+    coverage_dir: &Path,
+) -> anyhow::Result<Vec<xshell::PushEnv<'a>>> {
+    if coverage_dir.exists() {
+        sh.remove_path(coverage_dir)?;
+    }
+    sh.create_dir(coverage_dir)?;
+
+    Ok(match config {
+        CoverageConfig::Gcov => vec![
+            sh.push_env("GCOV_PREFIX", coverage_dir),
+            sh.push_env("GCOV_PREFIX_STRIP", "0"),
+        ],
+        CoverageConfig::LlvmProfraw(_) => {
+            vec![sh.push_env("LLVM_PROFILE_FILE", coverage_dir.join("%p.profraw"))]
+        }
+    })
+}
+
+pub fn collect_edges(
+    sh: &Shell,
+    config: &CoverageConfig,
+    coverage_dir: &Path,
+) -> anyhow::Result<HashSet<Edge>> {
+    match config {
+        CoverageConfig::Gcov => {
+            let mut edges = HashSet::new();
+            for gcda in find_files(coverage_dir, "gcda")? {
+                let file = gcda
+                    .strip_prefix(coverage_dir)
+                    .unwrap()
+                    .to_str()
+                    .unwrap()
+                    .to_string();
+                let data = fs::read(&gcda).context(format!("Failed to read {gcda:?}"))?;
+                edges.extend(parse_gcda(&file, &data)?);
+            }
+            Ok(edges)
+        }
+        CoverageConfig::LlvmProfraw(profraw_config) => {
+            let profraws = find_files(coverage_dir, "profraw")?;
+            if profraws.is_empty() {
+                return Ok(HashSet::new());
+            }
+            let llvm_profdata = &profraw_config.llvm_profdata;
+            let profdata = cmd!(sh, "{llvm_profdata} merge --text -o - {profraws...}")
+                .quiet()
+                .read()
+                .context("llvm-profdata failed to merge profraw files")?;
+            Ok(parse_profdata_text(&profdata))
+        }
+    }
+}
+
+fn find_files(dir: &Path, extension: &str) -> anyhow::Result<Vec<PathBuf>> {
+    let mut found = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            found.append(&mut find_files(&path, extension)?);
+        } else if path.extension().is_some_and(|ext| ext == extension) {
+            found.push(path);
+        }
+    }
+    Ok(found)
+}
+
+/// Parse a gcda file and return every arc counter that was hit.
+/// Record lengths are in bytes for gcc >= 12 and in words before that.
+pub fn parse_gcda(file: &str, data: &[u8]) -> anyhow::Result<HashSet<Edge>> {
+    let words = data
+        .chunks_exact(4)
+        .map(|w| u32::from_ne_bytes(w.try_into().unwrap()))
+        .collect::<Vec<_>>();
+
+    if words.len() < 3 || words[0] != GCDA_MAGIC {
+        return Err(anyhow::anyhow!("{file} is not a gcda file"));
+    }
+
+    // Version is stored as 'A'+major/10, '0'+major%10, minor, status
+    let version = words[1].to_be_bytes();
+    let major = (version[0].wrapping_sub(b'A') as u32) * 10 + version[1].wrapping_sub(b'0') as u32;
+    let length_in_bytes = major >= 12;
+
+    let mut edges = HashSet::new();
+    let mut function = String::new();
+    // Skip magic, version and stamp. gcc >= 12 also writes a checksum.
+    let mut idx = if length_in_bytes { 4 } else { 3 };
+    while idx + 1 < words.len() {
+        let tag = words[idx];
+        let length = words[idx + 1] as i32;
+        idx += 2;
+
+        // A negative length means all counters are zero and nothing follows
+        let length_words = if length < 0 {
+            0
+        } else if length_in_bytes {
+            length as usize / 4
+        } else {
+            length as usize
+        };
+        let record = &words[idx..(idx + length_words).min(words.len())];
+
+        match tag {
+            GCOV_TAG_FUNCTION if !record.is_empty() => {
+                function = format!("{:08x}", record[0]);
+            }
+            GCOV_TAG_ARC_COUNTS => {
+                for (counter, count) in record.chunks_exact(2).enumerate() {
+                    if count[0] != 0 || count[1] != 0 {
+                        edges.insert(Edge {
+                            file: file.to_string(),
+                            function: function.clone(),
+                            counter: counter as u32,
+                        });
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        idx += length_words;
+    }
+
+    Ok(edges)
+}
+
+/// Parse the output of `llvm-profdata merge --text` and return every counter
+/// that was hit.
+pub fn parse_profdata_text(profdata: &str) -> HashSet<Edge> {
+    let lines = profdata.lines().map(|l| l.trim()).collect::<Vec<_>>();
+
+    let mut edges = HashSet::new();
+    let mut function = String::new();
+    let mut counters = 0;
+    let mut idx = 0;
+    while idx < lines.len() {
+        match lines[idx] {
+            "# Func Hash:" if idx > 0 => function = lines[idx - 1].to_string(),
+            "# Num Counters:" => {
+                counters = lines.get(idx + 1).and_then(|l| l.parse().ok()).unwrap_or(0);
+            }
+            "# Counter Values:" => {
+                for counter in 0..counters {
+                    let value = lines
+                        .get(idx + 1 + counter)
+                        .and_then(|l| l.parse::<u64>().ok())
+                        .unwrap_or(0);
+                    if value != 0 {
+                        edges.insert(Edge {
+                            file: String::new(),
+                            function: function.clone(),
+                            counter: counter as u32,
+                        });
+                    }
+                }
+                idx += counters;
+            }
+            _ => {}
+        }
+        idx += 1;
+    }
+
+    edges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edge(file: &str, function: &str, counter: u32) -> Edge {
+        Edge {
+            file: file.to_string(),
+            function: function.to_string(),
+            counter,
+        }
+    }
+
+    fn gcda(words: &[u32]) -> Vec<u8> {
+        words.iter().flat_map(|word| word.to_ne_bytes()).collect()
+    }
+
+    #[test]
+    fn gcda_record_lengths_in_bytes() {
+        let version = u32::from_be_bytes(*b"B40*");
+        let data = gcda(&[
+            GCDA_MAGIC,
+            version,
+            0, // stamp
+            0, // checksum
+            GCOV_TAG_FUNCTION,
+            12,
+            0x1234,
+            0,
+            0,
+            GCOV_TAG_ARC_COUNTS,
+            24,
+            5,
+            0,
+            0,
+            0,
+            0,
+            1,
+        ]);
+        assert_eq!(
+            parse_gcda("a.gcda", &data).unwrap(),
+            HashSet::from([edge("a.gcda", "00001234", 0), edge("a.gcda", "00001234", 2)])
+        );
+    }
+
+    #[test]
+    fn gcda_record_lengths_in_words() {
+        let version = u32::from_be_bytes(*b"A93*");
+        let data = gcda(&[
+            GCDA_MAGIC,
+            version,
+            0, // stamp
+            GCOV_TAG_FUNCTION,
+            3,
+            0xabcd,
+            0,
+            0,
+            GCOV_TAG_ARC_COUNTS,
+            4,
+            0,
+            0,
+            3,
+            0,
+            // All zero counters
+            GCOV_TAG_ARC_COUNTS,
+            -4i32 as u32,
+        ]);
+        assert_eq!(
+            parse_gcda("b.gcda", &data).unwrap(),
+            HashSet::from([edge("b.gcda", "0000abcd", 1)])
+        );
+    }
+
+    #[test]
+    fn gcda_bad_magic() {
+        assert!(parse_gcda("c.gcda", &gcda(&[0, 0, 0])).is_err());
+    }
+
+    #[test]
+    fn profdata_text() {
+        let profdata = "\
+main
+# Func Hash:
+1234
+# Num Counters:
+3
+# Counter Values:
+1
+0
+7
+
+foo
+# Func Hash:
+5678
+# Num Counters:
+1
+# Counter Values:
+0
+";
+        assert_eq!(
+            parse_profdata_text(profdata),
+            HashSet::from([edge("", "main", 0), edge("", "main", 2)])
+        );
+    }
+
+    #[test]
+    fn corpus_is_capped() {
+        let mut corpus = FlagCorpus::new();
+        for counter in 0..MAX_CORPUS_ENTRIES as u32 + 10 {
+            let edges = HashSet::from([edge("", "f", counter)]);
+            assert_eq!(corpus.add(vec![], 0, edges), 1);
+        }
+        assert_eq!(corpus.entries.len(), MAX_CORPUS_ENTRIES);
+        // Seen edges aren't new a second time
+        assert_eq!(corpus.add(vec![], 0, HashSet::from([edge("", "f", 0)])), 0);
+    }
+}
@@ -9,8 +9,12 @@ use anyhow::Context;
 use xshell::{cmd, Shell};
 
 use crate::{
-    Architecture, ExecFailInfo, ExecFailType, FailInfo, FuzzGenerator, Runner, RunnerArguments,
-    Stats,
+    build_identity::current_build_identities,
+    finds_db::record_find,
+    generate::{current_corpus_testcase, current_generator_args},
+    qemu::{riscv_arch, riscv_qemu_cpu, strip_extension_version, QemuCpu},
+    Architecture, ExecFailInfo, ExecFailType, FailInfo, FuzzGenerator, QemuConfig, Runner,
+    RunnerArguments, SpikeConfig, SpikeFailType, Stats,
 };
 
 pub fn execute_program(
@@ -19,6 +23,7 @@ pub fn execute_program(
     stats: &mut Stats,
     temp_dir: &Path,
     finds_dir: &Path,
+    runner_id: Option<u32>,
     compiler_paths: &[PathBuf],
     compile_flags: &[String],
     testcase_paths: &[PathBuf],
@@ -40,9 +45,10 @@ pub fn execute_program(
             let run_args = sh.read_file("run_input.txt")?;
             run_args.split_whitespace().map(|s| s.to_string()).collect()
         }
-        FuzzGenerator::Fixed(_) => {
+        FuzzGenerator::Fixed(_) | FuzzGenerator::Corpus(_) | FuzzGenerator::Mutate(_) => {
             vec![]
         }
+        FuzzGenerator::LlvmIr(_) => unreachable!("LLVM IR testcases are only compiled"),
     };
 
     let mut run_command: std::process::Command = match runner {
@@ -51,19 +57,16 @@ pub fn execute_program(
             .into(),
         Runner::Qemu(qemu_config) => {
             let qemu_cpu = match &qemu_config.cpu_flags {
-                RunnerArguments::Fixed(flags) => flags,
-                RunnerArguments::Generated(generation_script) => {
-                    assert!(cmd!(sh, "/scratch/tc-testing/tc-compiler-fuzz-trunk/build-gcv/bin/riscv64-unknown-linux-gnu-readelf -a {program}").read()?.contains("risc"));
-                    &cmd!(
-                        sh,
-                        "{generation_script} --elf-file-path {program} --print-qemu-cpu"
-                    )
-                    .read()?
-                }
+                RunnerArguments::Fixed(flags) => flags.clone(),
+                RunnerArguments::Generated => match riscv_qemu_cpu(program)? {
+                    QemuCpu::Cpu(cpu) => cpu,
+                    QemuCpu::Unsupported(_) => {
+                        stats.execute_unsupported += 1;
+                        return Ok(None);
+                    }
+                },
             };
 
-            // TODO: Catch qemu flag generator failures
-
             let qemu = if qemu_cpu.starts_with("rv32") {
                 &qemu_config.rv32path
             } else {
@@ -75,6 +78,24 @@ pub fn execute_program(
                 .quiet()
                 .into()
         }
+        Runner::QemuAarch64(qemu_config) => {
+            let qemu = &qemu_config.path;
+            cmd!(sh, "timeout -k 0.1 2 {qemu} {program} {input...}")
+                .env("QEMU_CPU", qemu_aarch64_cpu(compile_flags))
+                .quiet()
+                .into()
+        }
+        Runner::Spike(spike_config) => {
+            let spike = &spike_config.path;
+            let isa = spike_isa(program, compile_flags)?;
+            let pk = spike_pk(spike_config, &isa);
+            cmd!(
+                sh,
+                "timeout -k 0.1 10 {spike} --isa={isa} {pk} {program} {input...}"
+            )
+            .quiet()
+            .into()
+        }
     };
 
     let command_output = run_command.output()?;
@@ -84,6 +105,7 @@ pub fn execute_program(
         command_output: &command_output,
         temp_dir,
         finds_dir,
+        runner_id,
         compiler_paths,
         testcase_paths,
         flags: compile_flags,
@@ -95,10 +117,163 @@ pub fn execute_program(
     triage_execution_command(sh, triage_info, stats)
 }
 
+/// Runners with the other vector configs of a QEMU runner's vector_sweep.
+/// Empty when there's no sweep or the program doesn't use vectors.
+pub fn vector_sweep_runners(runner: &Runner, program: &Path) -> anyhow::Result<Vec<Runner>> {
+    let Runner::Qemu(qemu_config) = runner else {
+        return Ok(vec![]);
+    };
+    let Some(sweep) = &qemu_config.vector_sweep else {
+        return Ok(vec![]);
+    };
+
+    let qemu_cpu = match &qemu_config.cpu_flags {
+        RunnerArguments::Fixed(flags) => flags.clone(),
+        RunnerArguments::Generated => riscv_qemu_cpu(program)?.cpu()?,
+    };
+    let (vector_properties, properties): (Vec<_>, Vec<_>) =
+        qemu_cpu.split(',').partition(|property| {
+            [
+                "vlen=",
+                "elen=",
+                "vext_spec=",
+                "rvv_ta_all_1s=",
+                "rvv_ma_all_1s=",
+            ]
+            .iter()
+            .any(|prefix| property.starts_with(prefix))
+        });
+    let uses_vectors = properties
+        .iter()
+        .any(|property| *property == "v=true" || property.starts_with("zve"));
+    if !uses_vectors {
+        return Ok(vec![]);
+    }
+
+    let min_vlen = vector_properties
+        .iter()
+        .find_map(|property| property.strip_prefix("vlen="))
+        .and_then(|vlen| vlen.parse::<u32>().ok())
+        .unwrap_or(128);
+    let elen = vector_properties
+        .iter()
+        .find(|property| property.starts_with("elen="));
+    let vext_specs = match &sweep.vext_specs {
+        Some(vext_specs) => vext_specs.iter().map(Some).collect(),
+        None => vec![None],
+    };
+
+    let mut runners = vec![];
+    let mut vlen = min_vlen;
+    while vlen <= sweep.max_vlen.unwrap_or(1024) {
+        for agnostic_all_1s in [false, true] {
+            for vext_spec in &vext_specs {
+                let mut sweep_properties = properties.clone();
+                let vlen_property = format!("vlen={vlen}");
+                sweep_properties.push(&vlen_property);
+                sweep_properties.extend(elen);
+                if agnostic_all_1s {
+                    sweep_properties.extend(["rvv_ta_all_1s=true", "rvv_ma_all_1s=true"]);
+                }
+                let vext_spec_property = vext_spec.map(|spec| format!("vext_spec={spec}"));
+                sweep_properties.extend(vext_spec_property.as_deref());
+
+                let sweep_cpu = sweep_properties.join(",");
+                if sweep_cpu != qemu_cpu {
+                    runners.push(Runner::Qemu(QemuConfig {
+                        cpu_flags: RunnerArguments::Fixed(sweep_cpu),
+                        vector_sweep: None,
+                        ..qemu_config.clone()
+                    }));
+                }
+            }
+        }
+        vlen *= 2;
+    }
+    Ok(runners)
+}
+
+/// QEMU cpu for an AArch64 binary. Code compiled with a fixed
+/// -msve-vector-bits only works when the vector length matches.
+pub fn qemu_aarch64_cpu(compile_flags: &[String]) -> String {
+    let sve_bits = compile_flags
+        .iter()
+        .flat_map(|flags| flags.split_whitespace())
+        .rev()
+        .filter_map(|flag| flag.strip_prefix("-msve-vector-bits="))
+        .filter_map(|bits| bits.parse::<u32>().ok())
+        .next();
+
+    match sve_bits {
+        // sve-default-vector-length is in bytes
+        Some(bits) => format!("max,sve-default-vector-length={}", bits / 8),
+        None => "max".to_string(),
+    }
+}
+
+/// ISA string for spike. Taken from the last -march in the flags, or from the
+/// .riscv.attributes of the program when the march was generated.
+pub fn spike_isa(program: &Path, compile_flags: &[String]) -> anyhow::Result<String> {
+    let march = compile_flags
+        .iter()
+        .flat_map(|flags| flags.split_whitespace())
+        .rev()
+        .find_map(|flag| flag.strip_prefix("-march="))
+        .map(str::to_string);
+
+    let march = match march {
+        Some(march) => march,
+        None => riscv_arch(program)?,
+    };
+
+    // Spike doesn't accept the extension versions and underscore separated
+    // single letter extensions from the attributes (rv64i2p1_m2p0_...)
+    let march = march.to_lowercase();
+    let mut isa = String::new();
+    for extension in march.split('_').map(strip_extension_version) {
+        if !isa.is_empty() && extension.len() > 1 {
+            isa.push('_');
+        }
+        isa.push_str(extension);
+    }
+    Ok(isa)
+}
+
+pub fn spike_pk<'a>(spike_config: &'a SpikeConfig, isa: &str) -> &'a Path {
+    if isa.starts_with("rv32") {
+        &spike_config.rv32_pk
+    } else {
+        &spike_config.rv64_pk
+    }
+}
+
+/// Spike aborts (or exits, in older versions) when it can't parse the --isa
+/// string, e.g. for an extension it doesn't implement yet
+pub fn spike_rejected_isa(stderr: &str) -> bool {
+    stderr.contains("bad --isa option")
+}
+
+/// Categorize a failed spike run. pk prints to stdout, so anything on stderr
+/// comes from spike itself.
+pub fn spike_fail_type(stdout: &str, stderr: &str) -> Option<SpikeFailType> {
+    if spike_rejected_isa(stderr) {
+        None
+    } else if stdout.contains("An illegal instruction was executed") {
+        Some(SpikeFailType::IllegalInsn)
+    } else if stdout.contains("segfault @") {
+        Some(SpikeFailType::Segfault)
+    } else if !stderr.is_empty() {
+        Some(SpikeFailType::ErrorMsg)
+    } else {
+        None
+    }
+}
+
 pub struct ExecTriageInfo<'a> {
     command_output: &'a Output, // The exit code/signal/stderr being considered
     temp_dir: &'a Path,         // Where the potential failure is stored
     finds_dir: &'a Path,        // Where to copy this to if it's interesting
+    runner_id: Option<u32>,     // Which fuzzer found it, for the finds database
     compiler_paths: &'a [PathBuf],
     testcase_paths: &'a [PathBuf],
     flags: &'a [String],
@@ -118,6 +293,13 @@ fn triage_execution_command(
 
     let dump_dir = finds_dir.join(temp_dir.file_name().unwrap());
 
+    if matches!(triage_info.runner, Runner::Spike(_))
+        && spike_rejected_isa(&String::from_utf8_lossy(&command_output.stderr))
+    {
+        stats.execute_unsupported += 1;
+        return Ok(None);
+    }
+
     match command_output.status.code() {
         Some(0) => {
             stats.execute_success += 1;
@@ -178,6 +360,7 @@ fn log_error(sh: &Shell, triage_info: &ExecTriageInfo) -> anyhow::Result<()> {
     let command_output = triage_info.command_output;
     let temp_dir = triage_info.temp_dir;
     let finds_dir = triage_info.finds_dir;
+    let runner_id = triage_info.runner_id;
     let flags = triage_info.flags;
     let compiler_paths = triage_info.compiler_paths;
     let testcase_paths = triage_info.testcase_paths;
@@ -202,23 +385,38 @@ fn log_error(sh: &Shell, triage_info: &ExecTriageInfo) -> anyhow::Result<()> {
         .context("When attempting to save stderr to output directory")?;
     sh.write_file(dump_dir.join("stdout.txt"), &command_output.stdout)
         .context("When attempting to save stdout to output directory")?;
+    let fail_info = FailInfo::Execution(ExecFailInfo {
+        compiler: compiler_paths.to_vec(),
+        architecture: architecture.clone(),
+        testcase: testcase_paths.to_vec(),
+        generator: generator.clone(),
+        generator_args: current_generator_args(generator),
+        corpus_testcase: current_corpus_testcase(generator).map(|testcase| testcase.path),
+        runner: runner.clone(),
+        build_identities: current_build_identities(),
+        first_bad_commit: None,
+        fail_type: match runner {
+            Runner::Qemu(..) | Runner::QemuAarch64(..) => ExecFailType::Qemu(None),
+            Runner::Spike(..) => ExecFailType::Spike(None),
+            Runner::Native => ExecFailType::Native(None),
+        },
+    });
     sh.write_file(
         dump_dir.join("fail_info.yaml"),
-        serde_yaml::to_string(&FailInfo::Execution(ExecFailInfo {
-            compiler: compiler_paths.to_vec(),
-            architecture: architecture.clone(),
-            testcase: testcase_paths.to_vec(),
-            generator: generator.clone(),
-            runner: runner.clone(),
-            fail_type: if matches!(runner, Runner::Qemu(..)) {
-                ExecFailType::Qemu(None)
-            } else {
-                ExecFailType::Native(None)
-            },
-        }))
-        .unwrap(),
+        serde_yaml::to_string(&fail_info).unwrap(),
     )
     .context("When attempting to save run info to output directory")?;
 
+    record_find(
+        sh,
+        finds_dir,
+        runner_id,
+        &dump_dir,
+        &fail_info,
+        &flags.join(" ; "),
+        &String::from_utf8_lossy(&command_output.stderr),
+    )
+    .context("When attempting to record the find")?;
+
     Ok(())
 }
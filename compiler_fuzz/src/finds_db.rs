@@ -0,0 +1,215 @@
+//! Single file record of every find the fuzzers saved, so finds can be listed
+//! and grouped without walking the finds directories. Each find is appended
+//! as its own YAML document, which keeps concurrent fuzzers sharing a finds
+//! directory from clobbering each other. Updates are appended the same way and
+//! replace the earlier record for their dump directory when read.
+
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use xshell::{cmd, Shell};
+
+use crate::FailInfo;
+
+pub const FINDS_DB: &str = "finds.yaml";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum FindKind {
+    Ice,
+    Execution,
+    Runtime,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum ReductionStatus {
+    Unreduced,
+    Reduced(PathBuf),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FindRecord {
+    pub time: String,
+    pub runner_id: Option<u32>,
+    pub dump_dir: PathBuf,
+    pub kind: FindKind,
+    pub compiler: PathBuf,
+    /// First line of the compiler's --version output
+    pub compiler_version: Option<String>,
+    pub flags: String,
+    pub signature: String,
+    pub fail_type: String,
+    pub reduction_status: ReductionStatus,
+}
+
+/// Append a find that was just dumped to `dump_dir` to the finds directory's
+/// database. `runner_id` is the --id of the fuzzer that found it.
+pub fn record_find(
+    sh: &Shell,
+    finds_dir: &Path,
+    runner_id: Option<u32>,
+    dump_dir: &Path,
+    fail_info: &FailInfo,
+    flags: &str,
+    stderr: &str,
+) -> anyhow::Result<()> {
+    let (kind, compiler, build_identities) = match fail_info {
+        FailInfo::Ice(ice_fail_info) => (
+            FindKind::Ice,
+            ice_fail_info.compilers[0].clone(),
+            &ice_fail_info.build_identities,
+        ),
+        FailInfo::Execution(exec_fail_info) => (
+            FindKind::Execution,
+            exec_fail_info.compiler[0].clone(),
+            &exec_fail_info.build_identities,
+        ),
+        FailInfo::Runtime(runtime_fail_info) => (
+            FindKind::Runtime,
+            runtime_fail_info.slow_compiler[0].clone(),
+            &runtime_fail_info.build_identities,
+        ),
+    };
+
+    let compiler_version = build_identities.as_ref().and_then(|build_identities| {
+        build_identities
+            .compilers
+            .iter()
+            .find(|identity| identity.path == compiler)
+            .and_then(|identity| identity.version.lines().next())
+            .map(str::to_string)
+    });
+
+    let record = FindRecord {
+        time: cmd!(sh, "date -Iseconds").quiet().read()?,
+        runner_id,
+        dump_dir: fs::canonicalize(dump_dir)?,
+        kind,
+        compiler,
+        compiler_version,
+        flags: flags.to_string(),
+        signature: find_signature(stderr),
+        fail_type: fail_type_name(fail_info),
+        reduction_status: ReductionStatus::Unreduced,
+    };
+
+    append_find(&finds_dir.join(FINDS_DB), &record)
+}
+
+fn append_find(db_path: &Path, record: &FindRecord) -> anyhow::Result<()> {
+    // One write per record so appends from other fuzzers don't interleave
+    let document = format!("---\n{}", serde_yaml::to_string(record)?);
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(db_path)?
+        .write_all(document.as_bytes())?;
+
+    Ok(())
+}
+
+/// Every find in the database, in the order they were found, with their latest
+/// updates
+pub fn read_finds(db_path: &Path) -> anyhow::Result<Vec<FindRecord>> {
+    let data = fs::read_to_string(db_path)?;
+    let mut records: Vec<FindRecord> = vec![];
+    for document in serde_yaml::Deserializer::from_str(&data) {
+        let record = FindRecord::deserialize(document)?;
+        match records
+            .iter_mut()
+            .find(|earlier| earlier.dump_dir == record.dump_dir)
+        {
+            Some(earlier) => *earlier = record,
+            None => records.push(record),
+        }
+    }
+    Ok(records)
+}
+
+/// Mark a find as reduced, with the fail type reduce categorized it as. Finds
+/// that were never recorded are left alone.
+pub fn record_reduction(
+    dump_dir: &Path,
+    reduction_dir: &Path,
+    fail_info: &FailInfo,
+) -> anyhow::Result<()> {
+    let Some(db_path) = dump_dir.parent().map(|finds_dir| finds_dir.join(FINDS_DB)) else {
+        return Ok(());
+    };
+    if !db_path.exists() {
+        return Ok(());
+    }
+
+    // Appended rather than rewritten, other reductions may be recording theirs
+    let Some(mut record) = read_finds(&db_path)?
+        .into_iter()
+        .find(|record| record.dump_dir == dump_dir)
+    else {
+        return Ok(());
+    };
+    record.fail_type = fail_type_name(fail_info);
+    record.reduction_status = ReductionStatus::Reduced(reduction_dir.to_path_buf());
+    append_find(&db_path, &record)
+}
+
+fn fail_type_name(fail_info: &FailInfo) -> String {
+    match fail_info {
+        FailInfo::Ice(ice_fail_info) => format!("{:?}", ice_fail_info.fail_type),
+        FailInfo::Execution(exec_fail_info) => format!("{:?}", exec_fail_info.fail_type),
+        FailInfo::Runtime(runtime_fail_info) => format!("{:?}", runtime_fail_info.fail_type),
+    }
+}
+
+/// The line of stderr that identifies a failure: the assertion, LLVM ERROR,
+/// ICE or error message, otherwise the first line
+pub fn find_signature(stderr: &str) -> String {
+    for marker in [
+        "Assertion `",
+        "LLVM ERROR:",
+        "internal compiler error:",
+        "error:",
+    ] {
+        if let Some(line) = stderr.lines().find(|line| line.contains(marker)) {
+            return line[line.find(marker).unwrap()..].trim().to_string();
+        }
+    }
+    stderr
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or_default()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signatures() {
+        assert_eq!(
+            find_signature(
+                "clang: /src/llvm/lib/CodeGen/SelectionDAG.cpp:123: void foo(): Assertion `N && \"bad\"' failed.\nPLEASE submit a bug report"
+            ),
+            "Assertion `N && \"bad\"' failed."
+        );
+        assert_eq!(
+            find_signature(
+                "foo.c: In function 'main':\nfoo.c:3:1: internal compiler error: in bar, at baz.cc:12\n0x1234 bar\n"
+            ),
+            "internal compiler error: in bar, at baz.cc:12"
+        );
+        assert_eq!(
+            find_signature("LLVM ERROR: Cannot select: 0x1234\n"),
+            "LLVM ERROR: Cannot select: 0x1234"
+        );
+        assert_eq!(
+            find_signature("\n  Segmentation fault\n"),
+            "Segmentation fault"
+        );
+        assert_eq!(find_signature(""), "");
+    }
+}
@@ -1,35 +1,79 @@
-use std::path::PathBuf;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+};
 
 use anyhow::Context;
+use compiler_flags_gen::Action;
+use rand::Rng;
 use xshell::{cmd, Shell};
 
-use crate::{Architecture, FuzzGenerator};
+use crate::{
+    mutate::mutate, reduction::ub_check, Architecture, CorpusConfig, CorpusOrder, CsmithConfig,
+    CsmithOptions, FuzzGenerator, LlvmIrSource, MutateConfig, UbCheckResult, YarpgenOptions,
+};
 
-pub fn run_generator(sh: &Shell, generator: &FuzzGenerator) -> anyhow::Result<Vec<PathBuf>> {
-    let generator_path: &PathBuf = match &generator {
-        FuzzGenerator::Csmith(csmith_config) => &csmith_config.path,
-        FuzzGenerator::Yarpgen(yarpgen_config) => &yarpgen_config.path,
-        FuzzGenerator::Rustsmith(rustsmith_config) => &rustsmith_config.path,
-        FuzzGenerator::Fixed(fixed_config) => &fixed_config.path,
-    };
+/// A testcase from a corpus directory
+#[derive(Clone, Debug)]
+pub struct CorpusTestcase {
+    pub path: PathBuf,
+    /// dg-do run, or a main function when there is no dg-do
+    pub run: bool,
+    /// Options from dg-options
+    pub options: String,
+}
+
+struct CorpusState {
+    testcases: Vec<CorpusTestcase>,
+    next: usize,
+    current: Option<CorpusTestcase>,
+}
+
+/// Corpus directories are only walked once. The state also tracks the
+/// round-robin position and the testcase that was picked last.
+static CORPORA: OnceLock<Mutex<HashMap<PathBuf, CorpusState>>> = OnceLock::new();
+
+/// Options the last csmith/yarpgen/llvm-stress testcase was generated with,
+/// or the seed and mutations for the Mutate generator
+static GENERATOR_ARGS: Mutex<Vec<String>> = Mutex::new(vec![]);
 
+/// Seed testcases for each Mutate generator config
+static MUTATION_SEEDS: OnceLock<Mutex<HashMap<Vec<PathBuf>, Vec<PathBuf>>>> = OnceLock::new();
+
+/// Mutants to try before falling back to an unmutated seed
+const MUTATION_ATTEMPTS: u32 = 10;
+
+/// The action decides which testcases can be used: linking and running need
+/// testcases that are meant to be run.
+pub fn run_generator(
+    sh: &Shell,
+    generator: &FuzzGenerator,
+    action: &Action,
+) -> anyhow::Result<Vec<PathBuf>> {
     match &generator {
-        FuzzGenerator::Csmith(_) => {
-            let csmith_testcase = cmd!(sh, "{generator_path}")
-                .quiet()
-                .read()
-                .context("Generator failed to run")?;
-            sh.write_file("csmith_testcase.c", csmith_testcase)?;
-            Ok(vec!["csmith_testcase.c".into()])
-        }
-        FuzzGenerator::Yarpgen(_) => {
-            let _ = cmd!(sh, "{generator_path} --std=c")
+        FuzzGenerator::Csmith(csmith_config) => Ok(vec![run_csmith(sh, csmith_config)?]),
+        FuzzGenerator::Yarpgen(yarpgen_config) => {
+            let args = match &yarpgen_config.options {
+                Some(options) => random_yarpgen_args(options),
+                None => vec!["--std=c".to_string()],
+            };
+            *GENERATOR_ARGS.lock().unwrap() = args.clone();
+            let cpp = args.iter().any(|arg| arg == "--std=c++");
+            let generator_path = &yarpgen_config.path;
+            let _ = cmd!(sh, "{generator_path} {args...}")
                 .quiet()
                 .read()
                 .context("Generator failed to run")?;
-            Ok(vec!["func.c".into(), "driver.c".into()])
+            if cpp {
+                Ok(vec!["func.cpp".into(), "driver.cpp".into()])
+            } else {
+                Ok(vec!["func.c".into(), "driver.c".into()])
+            }
         }
-        FuzzGenerator::Rustsmith(_) => {
+        FuzzGenerator::Rustsmith(rustsmith_config) => {
+            let generator_path = &rustsmith_config.path;
             let _ = cmd!(sh, "{generator_path} -n 1 --directory rustsmith")
                 .quiet()
                 .read_stderr()
@@ -42,13 +86,390 @@ pub fn run_generator(sh: &Shell, generator: &FuzzGenerator) -> anyhow::Result<Ve
             cmd!(sh, "mv {run_input} run_input.txt").quiet().run()?;
             Ok(vec!["rustsmith_testcase.rs".into()])
         }
-        FuzzGenerator::Fixed(_) => {
-            sh.copy_file(generator_path, "fixed_testcase.c")?;
+        FuzzGenerator::Fixed(fixed_config) => {
+            sh.copy_file(&fixed_config.path, "fixed_testcase.c")?;
             Ok(vec!["fixed_testcase.c".into()])
         }
+        FuzzGenerator::Corpus(corpus_config) => {
+            let testcase = next_corpus_testcase(corpus_config, action)?;
+            sh.copy_file(&testcase.path, "corpus_testcase.c")?;
+            Ok(vec!["corpus_testcase.c".into()])
+        }
+        FuzzGenerator::LlvmIr(llvm_ir_config) => match &llvm_ir_config.source {
+            LlvmIrSource::LlvmStress(llvm_stress_config) => {
+                let mut rng = rand::thread_rng();
+                let seed = rng.gen::<u32>();
+                let size = rng.gen_range(1..=llvm_stress_config.max_size.unwrap_or(100).max(1));
+                let args = vec![format!("-seed={seed}"), format!("-size={size}")];
+                *GENERATOR_ARGS.lock().unwrap() = args.clone();
+                let generator_path = &llvm_stress_config.path;
+                cmd!(sh, "{generator_path} {args...} -o llvm_testcase.ll")
+                    .quiet()
+                    .run()
+                    .context("Generator failed to run")?;
+                Ok(vec!["llvm_testcase.ll".into()])
+            }
+            LlvmIrSource::Csmith(csmith_ir_config) => {
+                let testcase = run_csmith(sh, &csmith_ir_config.csmith)?;
+                let clang = &csmith_ir_config.clang;
+                let target = format!("--target={}", csmith_ir_config.triple);
+                let include_dir = format!(
+                    "-I{}",
+                    csmith_ir_config.csmith.include_dir.to_str().unwrap()
+                );
+                // optnone would stop opt from doing anything with the IR
+                cmd!(
+                    sh,
+                    "{clang} {target} {include_dir} -w -O0 -Xclang -disable-O0-optnone -S -emit-llvm {testcase} -o llvm_testcase.ll"
+                )
+                .quiet()
+                .run()
+                .context("Failed to emit LLVM IR from csmith testcase")?;
+                let llvm_ir = sh.read_file("llvm_testcase.ll")?;
+                sh.write_file("llvm_testcase.ll", strip_target_attributes(&llvm_ir))?;
+                Ok(vec!["llvm_testcase.ll".into()])
+            }
+        },
+        FuzzGenerator::Mutate(mutate_config) => {
+            let seeds = mutation_seeds(mutate_config)?;
+            let mut rng = rand::thread_rng();
+            for _ in 0..MUTATION_ATTEMPTS {
+                let seed = &seeds[rng.gen_range(0..seeds.len())];
+                let count = rng.gen_range(1..=mutate_config.max_mutations.unwrap_or(3).max(1));
+                let (mutated, mutations) = mutate(&fs::read_to_string(seed)?, count, &mut rng);
+                if mutations.is_empty() {
+                    continue;
+                }
+                sh.write_file("mutated_testcase.c", mutated)?;
+
+                if matches!(
+                    ub_check(sh, generator, &["mutated_testcase.c".into()], false),
+                    Ok(UbCheckResult::Clean)
+                ) {
+                    let mut args = vec![format!("--seed={}", seed.to_str().unwrap())];
+                    args.extend(mutations);
+                    *GENERATOR_ARGS.lock().unwrap() = args;
+                    return Ok(vec!["mutated_testcase.c".into()]);
+                }
+            }
+
+            // Nothing UB-free came out, use a seed as is
+            let seed = &seeds[rng.gen_range(0..seeds.len())];
+            println!(
+                "No UB-free mutant in {MUTATION_ATTEMPTS} attempts, using seed {} unmutated",
+                seed.display()
+            );
+            *GENERATOR_ARGS.lock().unwrap() = vec![format!("--seed={}", seed.to_str().unwrap())];
+            sh.copy_file(seed, "mutated_testcase.c")?;
+            Ok(vec!["mutated_testcase.c".into()])
+        }
     }
 }
 
+/// Seeds are only searched for once. Seeds need a main so mutants can be run
+/// by check_for_ub.
+fn mutation_seeds(mutate_config: &MutateConfig) -> anyhow::Result<Vec<PathBuf>> {
+    let mut all_seeds = MUTATION_SEEDS.get_or_init(Default::default).lock().unwrap();
+    if !all_seeds.contains_key(&mutate_config.seeds) {
+        let mut seeds = vec![];
+        for path in &mutate_config.seeds {
+            let testcases = if path.is_dir() {
+                find_testcases(path)?
+            } else {
+                vec![path.clone()]
+            };
+            seeds.extend(testcases.into_iter().filter(|testcase| {
+                fs::read_to_string(testcase)
+                    .is_ok_and(|source| source.contains("main (") || source.contains("main("))
+            }));
+        }
+        all_seeds.insert(mutate_config.seeds.clone(), seeds);
+    }
+    let seeds = all_seeds[&mutate_config.seeds].clone();
+    if seeds.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Seeds({:?}) have no testcases with a main function",
+            mutate_config.seeds
+        ));
+    }
+    Ok(seeds)
+}
+
+fn run_csmith(sh: &Shell, csmith_config: &CsmithConfig) -> anyhow::Result<PathBuf> {
+    let generator_path = &csmith_config.path;
+    let args = match &csmith_config.options {
+        Some(options) => random_csmith_args(options),
+        None => vec![],
+    };
+    *GENERATOR_ARGS.lock().unwrap() = args.clone();
+    let testcase = if args.iter().any(|arg| arg == "--lang-cpp") {
+        "csmith_testcase.cpp"
+    } else {
+        "csmith_testcase.c"
+    };
+    let csmith_testcase = cmd!(sh, "{generator_path} {args...}")
+        .quiet()
+        .read()
+        .context("Generator failed to run")?;
+    sh.write_file(testcase, csmith_testcase)?;
+    Ok(testcase.into())
+}
+
+/// Function attributes take precedence over llc's -mcpu/-mattr, so drop the
+/// ones clang picked for the triple's default cpu
+fn strip_target_attributes(llvm_ir: &str) -> String {
+    llvm_ir
+        .lines()
+        .map(|line| {
+            if !line.starts_with("attributes #") {
+                return line.to_string();
+            }
+            let mut line = line.to_string();
+            for attribute in [
+                "\"target-cpu\"=\"",
+                "\"target-features\"=\"",
+                "\"tune-cpu\"=\"",
+            ] {
+                if let Some(start) = line.find(attribute) {
+                    let value_start = start + attribute.len();
+                    if let Some(len) = line[value_start..].find('"') {
+                        line.replace_range(start..value_start + len + 1, "");
+                    }
+                }
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+        + "\n"
+}
+
+fn random_csmith_args(options: &CsmithOptions) -> Vec<String> {
+    let mut rng = rand::thread_rng();
+    let mut args = vec![];
+    if let Some(max_funcs) = options.max_funcs {
+        args.push("--max-funcs".to_string());
+        args.push(rng.gen_range(1..=max_funcs.max(1)).to_string());
+    }
+    if let Some(max_array_dim) = options.max_array_dim {
+        args.push("--max-array-dim".to_string());
+        args.push(rng.gen_range(1..=max_array_dim.max(1)).to_string());
+    }
+    for (enabled, arg) in [
+        (options.no_pointers, "--no-pointers"),
+        (options.float, "--float"),
+        (options.no_volatiles, "--no-volatiles"),
+        (options.lang_cpp, "--lang-cpp"),
+    ] {
+        if enabled.unwrap_or(false) && rng.gen() {
+            args.push(arg.to_string());
+        }
+    }
+    args
+}
+
+fn random_yarpgen_args(options: &YarpgenOptions) -> Vec<String> {
+    let mut rng = rand::thread_rng();
+    let levels = ["none", "some", "all"];
+    let mut args = vec![];
+    if options.cpp.unwrap_or(false) && rng.gen() {
+        args.push("--std=c++".to_string());
+    } else {
+        args.push("--std=c".to_string());
+    }
+    if options.mutate.unwrap_or(false) {
+        args.push(format!(
+            "--mutate={}",
+            levels[rng.gen_range(0..levels.len())]
+        ));
+    }
+    if options.emit_pragmas.unwrap_or(false) {
+        args.push(format!(
+            "--emit-pragmas={}",
+            levels[rng.gen_range(0..levels.len())]
+        ));
+    }
+    args
+}
+
+/// The randomized options run_generator passed to csmith/yarpgen/llvm-stress last
+pub fn current_generator_args(generator: &FuzzGenerator) -> Option<Vec<String>> {
+    match generator {
+        FuzzGenerator::Csmith(_)
+        | FuzzGenerator::Yarpgen(_)
+        | FuzzGenerator::LlvmIr(_)
+        | FuzzGenerator::Mutate(_) => Some(GENERATOR_ARGS.lock().unwrap().clone()),
+        FuzzGenerator::Rustsmith(_) | FuzzGenerator::Fixed(_) | FuzzGenerator::Corpus(_) => None,
+    }
+}
+
+fn next_corpus_testcase(
+    corpus_config: &CorpusConfig,
+    action: &Action,
+) -> anyhow::Result<CorpusTestcase> {
+    let mut corpora = CORPORA.get_or_init(Default::default).lock().unwrap();
+    if !corpora.contains_key(&corpus_config.path) {
+        let testcases = find_testcases(&corpus_config.path)?
+            .into_iter()
+            .filter_map(parse_corpus_testcase)
+            .collect();
+        corpora.insert(
+            corpus_config.path.clone(),
+            CorpusState {
+                testcases,
+                next: 0,
+                current: None,
+            },
+        );
+    }
+    let state = corpora.get_mut(&corpus_config.path).unwrap();
+
+    let needs_run = matches!(action, Action::Link | Action::Execute);
+    let candidates = (0..state.testcases.len())
+        .filter(|&idx| state.testcases[idx].run || !needs_run)
+        .collect::<Vec<_>>();
+    if candidates.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Corpus({:?}) has no testcases for {:?}",
+            corpus_config.path,
+            action
+        ));
+    }
+
+    let idx = match corpus_config.order {
+        CorpusOrder::RoundRobin => {
+            let idx = candidates
+                .iter()
+                .find(|&&idx| idx >= state.next)
+                .copied()
+                .unwrap_or(candidates[0]);
+            state.next = idx + 1;
+            idx
+        }
+        CorpusOrder::Random => candidates[rand::thread_rng().gen_range(0..candidates.len())],
+    };
+
+    let testcase = state.testcases[idx].clone();
+    state.current = Some(testcase.clone());
+    Ok(testcase)
+}
+
+/// The corpus testcase that run_generator picked last
+pub fn current_corpus_testcase(generator: &FuzzGenerator) -> Option<CorpusTestcase> {
+    let FuzzGenerator::Corpus(corpus_config) = generator else {
+        return None;
+    };
+    CORPORA
+        .get()?
+        .lock()
+        .unwrap()
+        .get(&corpus_config.path)?
+        .current
+        .clone()
+}
+
+fn find_testcases(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut found = vec![];
+    for entry in fs::read_dir(dir).context(format!("Failed to read corpus {dir:?}"))? {
+        let path = entry?.path();
+        if path.is_dir() {
+            found.append(&mut find_testcases(&path)?);
+        } else if path.extension().is_some_and(|ext| ext == "c") {
+            found.push(path);
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+/// Read the dejagnu annotations used by the gcc testsuite. Testcases that are
+/// expected to fail to compile are skipped, and so are testcases that depend
+/// on target selectors or effective targets, which can't be evaluated here.
+fn parse_corpus_testcase(path: PathBuf) -> Option<CorpusTestcase> {
+    let source = fs::read_to_string(&path).ok()?;
+    if source.contains("dg-error") {
+        return None;
+    }
+
+    let mut run = None;
+    let mut options = vec![];
+    for (name, args) in dg_directives(&source) {
+        match name {
+            "dg-do" | "dg-options" | "dg-additional-options" => {
+                // { dg-do run { target riscv_v } }, { dg-options "-O2" { xfail *-*-* } }
+                if args.iter().any(|arg| arg.starts_with('{')) {
+                    return None;
+                }
+            }
+            "dg-skip-if" => return None,
+            name if name.starts_with("dg-require-") => return None,
+            _ => continue,
+        }
+        match name {
+            "dg-do" => run = Some(args.first() == Some(&"run")),
+            "dg-options" => options = args.first().into_iter().copied().collect(),
+            _ => options.extend(args.first()),
+        }
+    }
+
+    let run = run.unwrap_or_else(|| source.contains("main (") || source.contains("main("));
+    let options = options.join(" ");
+    Some(CorpusTestcase { path, run, options })
+}
+
+/// Every directive in the source with its arguments. Quotes are stripped and
+/// brace groups are kept whole:
+///   { dg-options "-O2 -w" { target riscv*-*-* } }
+///   -> ("dg-options", ["-O2 -w", "{ target riscv*-*-* }"])
+fn dg_directives(source: &str) -> Vec<(&str, Vec<&str>)> {
+    let mut directives = vec![];
+    for (start, _) in source.match_indices("{ dg-") {
+        let body = &source[start + 2..];
+        let mut words = vec![];
+        let mut chars = body.char_indices();
+        while let Some((word_start, c)) = chars.next() {
+            match c {
+                '}' => break,
+                '\n' => break,
+                c if c.is_whitespace() => {}
+                '"' => {
+                    let Some((end, _)) = chars.find(|&(_, c)| c == '"') else {
+                        break;
+                    };
+                    words.push(&body[word_start + 1..end]);
+                }
+                '{' => {
+                    let mut depth = 1;
+                    let Some((end, _)) = chars.find(|&(_, c)| {
+                        depth += match c {
+                            '{' => 1,
+                            '}' => -1,
+                            _ => 0,
+                        };
+                        depth == 0
+                    }) else {
+                        break;
+                    };
+                    words.push(&body[word_start..=end]);
+                }
+                _ => {
+                    let end = body[word_start..]
+                        .find(|c: char| c.is_whitespace() || c == '}')
+                        .map_or(body.len(), |offset| word_start + offset);
+                    words.push(&body[word_start..end]);
+                    while chars.offset() < end {
+                        chars.next();
+                    }
+                }
+            }
+        }
+        if let Some((name, args)) = words.split_first() {
+            directives.push((*name, args.to_vec()));
+        }
+    }
+    directives
+}
+
 pub fn get_generator_flags(
     generator: &FuzzGenerator,
     architecture: &Architecture,
@@ -59,10 +480,80 @@ pub fn get_generator_flags(
             csmith_config.include_dir.to_str().unwrap()
         )]),
         FuzzGenerator::Yarpgen(_) => match architecture {
-            Architecture::X86 => Ok(vec!["-mcmodel=large".to_string(), "-fno-pic".to_string()]),
+            Architecture::X86 | Architecture::Aarch64 => {
+                Ok(vec!["-mcmodel=large".to_string(), "-fno-pic".to_string()])
+            }
             Architecture::Riscv => Ok(vec!["-mcmodel=medany".to_string()]),
         },
         FuzzGenerator::Rustsmith(_) => Ok(vec![]),
         FuzzGenerator::Fixed(_) => Ok(vec![]),
+        FuzzGenerator::Corpus(_) => Ok(vec![]),
+        FuzzGenerator::LlvmIr(_) => Ok(vec![]),
+        FuzzGenerator::Mutate(mutate_config) => Ok(mutate_config
+            .include_dir
+            .iter()
+            .map(|include_dir| format!("-I{}", include_dir.to_str().unwrap()))
+            .collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dg_directives_keep_selectors_whole() {
+        let source = r#"/* { dg-do run { target { riscv_v && rv64 } } } */
+/* { dg-options "-O2 -w" } */
+/* { dg-final { scan-assembler-times "vsetvli" 1 } } */
+int main () { return 0; }
+"#;
+        assert_eq!(
+            dg_directives(source),
+            vec![
+                ("dg-do", vec!["run", "{ target { riscv_v && rv64 } }"]),
+                ("dg-options", vec!["-O2 -w"]),
+                ("dg-final", vec!["{ scan-assembler-times \"vsetvli\" 1 }"]),
+            ]
+        );
+    }
+
+    fn parse_source(source: &str) -> Option<CorpusTestcase> {
+        let temp_dir = Shell::new().unwrap().create_temp_dir().unwrap();
+        let path = temp_dir.path().join("testcase.c");
+        fs::write(&path, source).unwrap();
+        parse_corpus_testcase(path)
+    }
+
+    #[test]
+    fn corpus_testcase_options() {
+        let testcase = parse_source(
+            r#"/* { dg-do run } */
+/* { dg-options "-O3" } */
+/* { dg-additional-options "-fno-inline" } */
+int main () { return 0; }
+"#,
+        )
+        .unwrap();
+        assert!(testcase.run);
+        assert_eq!(testcase.options, "-O3 -fno-inline");
+
+        let testcase =
+            parse_source("/* { dg-do compile } */\nint main () { return 0; }\n").unwrap();
+        assert!(!testcase.run);
+        assert_eq!(testcase.options, "");
+    }
+
+    #[test]
+    fn corpus_testcase_with_selectors_is_skipped() {
+        for source in [
+            "/* { dg-do run { target riscv_v } } */\n",
+            "/* { dg-options \"-O2\" { target riscv*-*-* } } */\n",
+            "/* { dg-additional-options \"-mrvv\" { target { ! rv32 } } } */\n",
+            "/* { dg-require-effective-target riscv_v_ok } */\n",
+            "/* { dg-skip-if \"\" { *-*-* } { \"-O0\" } } */\n",
+        ] {
+            assert!(parse_source(source).is_none(), "{source}");
+        }
     }
 }
@@ -0,0 +1,174 @@
+//! Health of a generator's testcases. A generator that starts producing tiny
+//! programs, programs that all print the same checksum or the same testcase
+//! over and over still looks fine from the iteration times alone.
+
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap},
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+use xshell::Shell;
+
+/// Warn once the same testcase has been generated this many times in a row
+const STUCK_REPEATS: u32 = 5;
+/// Testcases to see before warning about a dominant checksum
+const MIN_CHECKSUM_SAMPLES: u128 = 20;
+/// Checksums to keep counts for. Once full, checksums only seen once are
+/// dropped to make room.
+const MAX_CHECKSUMS: usize = 1024;
+
+#[derive(Serialize, Default)]
+pub struct GeneratorStats {
+    pub testcases: u128,
+    /// Testcase count by size in bytes, keyed by the power of two bucket the
+    /// size falls under
+    pub size_histogram: BTreeMap<u64, u128>,
+    /// Testcases the reference compiler was run on
+    pub reference_compiles: u128,
+    /// Testcases the reference compiler timed out or hit an ignored error on
+    pub reference_compile_fails: u128,
+    /// Testcases the reference binary was run for
+    pub executions: u128,
+    pub execution_timeouts: u128,
+    /// Checksums printed by more than one testcase and how many printed each
+    #[serde(serialize_with = "serialize_repeated_checksums")]
+    pub checksums: HashMap<String, u128>,
+    /// Distinct checksums seen. Approximate once checksums have been dropped
+    /// from `checksums`.
+    pub distinct_checksums: u128,
+    /// Times in a row the last testcase was generated
+    pub repeats: u32,
+    #[serde(skip)]
+    last_testcase_hash: Option<u64>,
+    #[serde(skip)]
+    warned_degenerate: bool,
+}
+
+impl GeneratorStats {
+    /// Record a freshly generated testcase. Returns a warning when the
+    /// generator keeps producing the same testcase.
+    pub fn record_testcase(
+        &mut self,
+        sh: &Shell,
+        testcase_paths: &[PathBuf],
+    ) -> anyhow::Result<Option<String>> {
+        let mut size = 0;
+        let mut hasher = DefaultHasher::new();
+        for testcase_path in testcase_paths {
+            let contents = sh.read_binary_file(testcase_path)?;
+            size += contents.len() as u64;
+            contents.hash(&mut hasher);
+        }
+        let hash = hasher.finish();
+
+        self.testcases += 1;
+        *self
+            .size_histogram
+            .entry(size.next_power_of_two())
+            .or_default() += 1;
+
+        if self.last_testcase_hash == Some(hash) {
+            self.repeats += 1;
+        } else {
+            self.repeats = 1;
+            self.last_testcase_hash = Some(hash);
+        }
+
+        Ok((self.repeats == STUCK_REPEATS).then(|| {
+            format!(
+                "WARNING: Generator produced the same testcase {} times in a row, it may be stuck (leftover output from a previous run?)",
+                self.repeats
+            )
+        }))
+    }
+
+    pub fn record_reference_compile(&mut self, failed: bool) {
+        self.reference_compiles += 1;
+        if failed {
+            self.reference_compile_fails += 1;
+        }
+    }
+
+    /// Record the reference run. `stdout` is None when it timed out.
+    /// Returns a warning when most testcases print the same checksum.
+    pub fn record_execution(&mut self, stdout: Option<&str>) -> Option<String> {
+        self.executions += 1;
+        let Some(stdout) = stdout else {
+            self.execution_timeouts += 1;
+            return None;
+        };
+
+        // csmith and yarpgen print the checksum last
+        let checksum = stdout.trim().lines().last().unwrap_or_default().to_string();
+        if !self.checksums.contains_key(&checksum) {
+            self.distinct_checksums += 1;
+            if self.checksums.len() >= MAX_CHECKSUMS {
+                self.checksums.retain(|_, count| *count > 1);
+            }
+            if self.checksums.len() >= MAX_CHECKSUMS {
+                return None;
+            }
+        }
+        let count = self.checksums.entry(checksum.clone()).or_default();
+        *count += 1;
+
+        let checksum_samples = self.executions - self.execution_timeouts;
+        if self.warned_degenerate
+            || checksum_samples < MIN_CHECKSUM_SAMPLES
+            || *count * 2 <= checksum_samples
+        {
+            return None;
+        }
+        self.warned_degenerate = true;
+        Some(format!(
+            "WARNING: Over half of the testcases print `{checksum}', the generator may be producing degenerate programs"
+        ))
+    }
+
+    /// Short summary for the progress line
+    pub fn summary(&self) -> String {
+        let median_size = self.median_size_bucket();
+        let top_checksum = self.checksums.values().max().copied().unwrap_or(0);
+        let checksum_samples = self.executions - self.execution_timeouts;
+        format!(
+            "testcase size p50 <= {median_size} B, ref compile fail %: {:>3.3}, ref exec timeout %: {:>3.3}, checksums: {} distinct (top {:>3.3}%)",
+            percent(self.reference_compile_fails, self.reference_compiles),
+            percent(self.execution_timeouts, self.executions),
+            self.distinct_checksums,
+            percent(top_checksum, checksum_samples),
+        )
+    }
+
+    pub fn write(&self, sh: &Shell, path: &Path) -> anyhow::Result<()> {
+        sh.write_file(path, serde_yaml::to_string(self)?)?;
+        Ok(())
+    }
+
+    fn median_size_bucket(&self) -> u64 {
+        let mut seen = 0;
+        for (bucket, count) in &self.size_histogram {
+            seen += count;
+            if seen * 2 >= self.testcases {
+                return *bucket;
+            }
+        }
+        0
+    }
+}
+
+fn serialize_repeated_checksums<S: serde::Serializer>(
+    checksums: &HashMap<String, u128>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(checksums.iter().filter(|(_, count)| **count > 1))
+}
+
+fn percent(part: u128, total: u128) -> f64 {
+    if total == 0 {
+        0.
+    } else {
+        (part as f64 / total as f64) * 100.
+    }
+}
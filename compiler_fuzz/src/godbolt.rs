@@ -0,0 +1,157 @@
+//! Compiler Explorer links for reduced testcases. The ClientState JSON is
+//! base64 encoded into a /clientstate/ link, so no request to godbolt.org is
+//! needed to build it.
+
+use std::path::Path;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use compiler_flags_gen::Compiler;
+use serde::Serialize;
+
+use crate::{bug_report::march, detect_compiler, Architecture};
+
+#[derive(Serialize)]
+pub struct ClientState {
+    pub sessions: Vec<Session>,
+}
+
+#[derive(Serialize)]
+pub struct Session {
+    pub id: u32,
+    pub language: String,
+    pub source: String,
+    pub compilers: Vec<SessionCompiler>,
+}
+
+#[derive(Serialize)]
+pub struct SessionCompiler {
+    pub id: String,
+    pub options: String,
+}
+
+impl ClientState {
+    /// One source pane compiled by one compiler
+    pub fn new(language: &str, source: &str, compiler_id: &str, options: &str) -> Self {
+        ClientState {
+            sessions: vec![Session {
+                id: 1,
+                language: language.to_string(),
+                source: source.to_string(),
+                compilers: vec![SessionCompiler {
+                    id: compiler_id.to_string(),
+                    options: options.to_string(),
+                }],
+            }],
+        }
+    }
+
+    pub fn link(&self) -> anyhow::Result<String> {
+        let json = serde_json::to_string(self)?;
+        Ok(format!(
+            "https://godbolt.org/clientstate/{}",
+            URL_SAFE_NO_PAD.encode(json)
+        ))
+    }
+}
+
+/// Compiler Explorer's id for the compiler. Development builds map to the
+/// trunk compilers. Releases only have predictable ids on x86, elsewhere they
+/// fall back to trunk too.
+pub fn compiler_id(
+    compiler: &Path,
+    architecture: &Architecture,
+    flags: &str,
+    version: Option<&str>,
+) -> String {
+    let tool = compiler.file_name().unwrap().to_str().unwrap();
+    if tool.starts_with("opt") {
+        return "opttrunk".to_string();
+    } else if tool.starts_with("llc") {
+        return "llctrunk".to_string();
+    }
+
+    let compiler = detect_compiler(compiler);
+    let release = version.and_then(release_version);
+    match (architecture, compiler, release) {
+        (Architecture::X86, Some(Compiler::Gcc), Some((major, minor, _))) => {
+            format!("g{major}{minor}")
+        }
+        (Architecture::X86, Some(Compiler::Llvm), Some((major, minor, patch))) => {
+            format!("clang{major}{minor}{patch}")
+        }
+        (Architecture::X86, Some(Compiler::Llvm), None) => "clang_trunk".to_string(),
+        (Architecture::X86, _, _) => "gsnapshot".to_string(),
+        (Architecture::Riscv, compiler, _) => {
+            let xlen = if march(flags).starts_with("rv32") {
+                "rv32"
+            } else {
+                "rv64"
+            };
+            match compiler {
+                Some(Compiler::Llvm) => format!("{xlen}-clang"),
+                _ => format!("{xlen}-gcctrunk"),
+            }
+        }
+        (Architecture::Aarch64, Some(Compiler::Llvm), _) => "armv8-clang-trunk".to_string(),
+        (Architecture::Aarch64, _, _) => "arm64gtrunk".to_string(),
+    }
+}
+
+/// The major.minor.patch of a release compiler's --version, None for
+/// development builds
+fn release_version(version: &str) -> Option<(u32, u32, u32)> {
+    let first_line = version.lines().next()?;
+    if ["experimental", "git", "trunk", "prerelease"]
+        .iter()
+        .any(|marker| first_line.contains(marker))
+    {
+        return None;
+    }
+    first_line.split_whitespace().find_map(|word| {
+        let mut parts = word.split('.').map(|part| part.parse::<u32>().ok());
+        let major = parts.next()??;
+        let minor = parts.next()??;
+        let patch = parts.next().flatten().unwrap_or(0);
+        Some((major, minor, patch))
+    })
+}
+
+/// Compiler Explorer's language id for the testcase
+pub fn language(testcase: &Path) -> &'static str {
+    match testcase
+        .extension()
+        .and_then(|extension| extension.to_str())
+    {
+        Some("ll") => "llvm",
+        Some("cpp" | "cc" | "cxx") => "c++",
+        Some("rs") => "rust",
+        _ => "c",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn release_versions() {
+        assert_eq!(
+            release_version("gcc (GCC) 14.2.0\nCopyright"),
+            Some((14, 2, 0))
+        );
+        assert_eq!(
+            release_version("Ubuntu clang version 19.1.1 (1ubuntu1)"),
+            Some((19, 1, 1))
+        );
+        assert_eq!(release_version("clang version 18.1"), Some((18, 1, 0)));
+        assert_eq!(
+            release_version("gcc (GCC) 15.0.0 20240101 (experimental)"),
+            None
+        );
+        assert_eq!(
+            release_version("clang version 20.0.0git (https://github.com/llvm/llvm-project abc)"),
+            None
+        );
+        assert_eq!(release_version("rustc"), None);
+    }
+}
@@ -1,11 +1,22 @@
 use compiler_flags_gen::{Action, Compiler, FlagSet};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
+pub mod bug_report;
+pub mod build_identity;
 pub mod compile;
+pub mod coverage;
 pub mod execute;
+pub mod finds_db;
 pub mod generate;
+pub mod generator_stats;
+pub mod godbolt;
+pub mod mutate;
+pub mod qemu;
 pub mod reduction;
+pub mod reduction_state;
+pub mod reproduce;
 
 pub struct Stats {
     pub compile_success: u128,
@@ -14,6 +25,8 @@ pub struct Stats {
     pub execute_success: u128,
     pub execute_timeout: u128,
     pub execute_error: u128,
+    /// Programs skipped because the runner doesn't support their extensions
+    pub execute_unsupported: u128,
 }
 
 /// Config structs/enums
@@ -21,7 +34,8 @@ pub struct Stats {
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub enum RunnerArguments {
     Fixed(String),
-    Generated(String),
+    /// Computed from the .riscv.attributes of the program
+    Generated,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -29,18 +43,48 @@ pub struct QemuConfig {
     pub rv32path: PathBuf,
     pub rv64path: PathBuf,
     pub cpu_flags: RunnerArguments,
+    /// Also run vector binaries under other VLENs and agnostic policies
+    pub vector_sweep: Option<QemuVectorSweep>,
+}
+
+/// Vector configs to rerun a binary under. VLENs go from the program's zvl
+/// minimum up to max_vlen, each with the default and all 1s agnostic policies.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct QemuVectorSweep {
+    /// Defaults to 1024
+    pub max_vlen: Option<u32>,
+    /// vext_spec values to try. QEMU's default is used when unset.
+    pub vext_specs: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct QemuAarch64Config {
+    pub path: PathBuf,
+}
+
+/// riscv-isa-sim running the program under the proxy kernel. Spike takes VLEN
+/// and ELEN from the zvl*b and zve* extensions in its --isa string.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SpikeConfig {
+    pub path: PathBuf,
+    pub rv32_pk: PathBuf,
+    pub rv64_pk: PathBuf,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub enum Runner {
     Native,
     Qemu(QemuConfig),
+    QemuAarch64(QemuAarch64Config),
+    Spike(SpikeConfig),
 }
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct FlagsGenerator {
     pub compiler: Compiler,
     pub flag_set: FlagSet,
+    /// Also draw flags from the help output of the configured compiler
+    pub catalog_flags: Option<bool>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -53,6 +97,7 @@ pub enum CompilerArguments {
 pub enum Architecture {
     X86,
     Riscv,
+    Aarch64,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -67,6 +112,25 @@ pub struct FuzzCompiler {
 pub struct CsmithConfig {
     pub path: PathBuf,
     pub include_dir: PathBuf,
+    pub options: Option<CsmithOptions>,
+}
+
+/// Csmith options that are randomized per testcase. Options that aren't set
+/// are left at csmith's defaults.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct CsmithOptions {
+    /// Upper bound for --max-funcs
+    pub max_funcs: Option<u32>,
+    /// Upper bound for --max-array-dim
+    pub max_array_dim: Option<u32>,
+    /// Randomly pass --no-pointers
+    pub no_pointers: Option<bool>,
+    /// Randomly pass --float
+    pub float: Option<bool>,
+    /// Randomly pass --no-volatiles
+    pub no_volatiles: Option<bool>,
+    /// Randomly generate C++ (--lang-cpp) instead of C
+    pub lang_cpp: Option<bool>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
@@ -77,6 +141,18 @@ pub struct RustsmithConfig {
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct YarpgenConfig {
     pub path: PathBuf,
+    pub options: Option<YarpgenOptions>,
+}
+
+/// Yarpgen options that are randomized per testcase
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct YarpgenOptions {
+    /// Randomly generate C++ (--std=c++) instead of C
+    pub cpp: Option<bool>,
+    /// Randomly pick --mutate=none/some/all
+    pub mutate: Option<bool>,
+    /// Randomly pick --emit-pragmas=none/some/all
+    pub emit_pragmas: Option<bool>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
@@ -84,12 +160,85 @@ pub struct FixedTestcaseConfig {
     pub path: PathBuf,
 }
 
+/// Order that corpus testcases are picked in
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub enum CorpusOrder {
+    RoundRobin,
+    Random,
+}
+
+/// Directory of existing testcases, e.g. gcc.c-torture/execute or
+/// llvm-test-suite's SingleSource
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct CorpusConfig {
+    pub path: PathBuf,
+    pub order: CorpusOrder,
+}
+
+/// Verifier-clean LLVM IR that is fed straight to opt and llc so backend bugs
+/// can be reached without going through clang's frontend
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct LlvmIrConfig {
+    pub source: LlvmIrSource,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub enum LlvmIrSource {
+    /// Random IR from llvm-stress
+    LlvmStress(LlvmStressConfig),
+    /// IR emitted by clang from csmith output
+    Csmith(CsmithIrConfig),
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct LlvmStressConfig {
+    pub path: PathBuf,
+    /// Upper bound for -size
+    pub max_size: Option<u32>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct CsmithIrConfig {
+    pub csmith: CsmithConfig,
+    /// clang used to emit the IR. It doesn't need to be the clang under test.
+    pub clang: PathBuf,
+    /// Triple the IR is emitted for, e.g. riscv64-unknown-linux-gnu
+    pub triple: String,
+}
+
+/// Mutates testcases that were already interesting, e.g. earlier finds or a
+/// corpus, to explore around the shapes that found bugs
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct MutateConfig {
+    /// Testcases to mutate. Directories are searched for .c files.
+    pub seeds: Vec<PathBuf>,
+    /// Include dir the seeds need, e.g. csmith's
+    pub include_dir: Option<PathBuf>,
+    /// Upper bound for the number of mutations applied to a seed
+    pub max_mutations: Option<u32>,
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub enum FuzzGenerator {
     Csmith(CsmithConfig),
     Yarpgen(YarpgenConfig),
     Rustsmith(RustsmithConfig),
     Fixed(FixedTestcaseConfig),
+    Corpus(CorpusConfig),
+    LlvmIr(LlvmIrConfig),
+    Mutate(MutateConfig),
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct LlvmProfrawConfig {
+    pub llvm_profdata: PathBuf,
+}
+
+/// Coverage emitted by an instrumented compiler
+#[derive(Deserialize, Debug, Clone)]
+pub enum CoverageConfig {
+    Gcov,
+    LlvmProfraw(LlvmProfrawConfig),
 }
 
 #[derive(Deserialize, Clone)]
@@ -97,6 +246,7 @@ pub struct CompileConfig {
     pub action: Action,
     pub compiler: FuzzCompiler,
     pub generator: FuzzGenerator,
+    pub coverage: Option<CoverageConfig>,
 }
 
 #[derive(Deserialize)]
@@ -104,6 +254,8 @@ pub struct RunConfig {
     pub fast_compiler: FuzzCompiler,
     pub slow_compiler: FuzzCompiler,
     pub generator: FuzzGenerator,
+    /// Check mismatches for UB with ubsan and asan before saving them
+    pub ub_check: Option<bool>,
 }
 
 #[derive(Deserialize)]
@@ -114,14 +266,22 @@ pub enum FuzzConfig {
 
 /// Fail info structs/enums
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum LlvmFailType {
     Frontend,
     Llc,
+    /// clang crashed without a backend error, e.g. in the middle end. Found
+    /// from C and categorized from stderr.
     Opt,
     UnrecognizedOpcode,
     UnrecognizedFileFormat,
     ReservedRequiredRegister,
+    /// opt crashed on generated IR. Unlike Opt this is set by the fuzzer,
+    /// which ran opt itself, and the IR is reduced with llvm-reduce.
+    OptCrash,
+    /// llc crashed on the IR opt produced from generated IR. Set by the
+    /// fuzzer like OptCrash.
+    LlcCrash,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -139,6 +299,15 @@ pub enum QemuFailType {
     Segfault,
 }
 
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub enum SpikeFailType {
+    IllegalInsn,
+    /// pk reported a load/store fault
+    Segfault,
+    /// spike itself failed, usually on an --isa it doesn't support
+    ErrorMsg,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub enum NativeFailType {}
 
@@ -152,11 +321,49 @@ pub enum IceFailType {
 pub enum ExecFailType {
     Qemu(Option<QemuFailType>),
     Native(Option<NativeFailType>),
+    Spike(Option<SpikeFailType>),
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub enum RuntimeFailType {
     Mismatch,
+    /// The slow binary agreed with the fast one under the configured QEMU cpu
+    /// but not under the other vector config in sweep_runner
+    VectorConfigMismatch,
+}
+
+/// Result of building and running a testcase with ubsan and asan
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum UbCheckResult {
+    Clean,
+    /// clang warned about the testcase, which usually points at UB
+    Warning(String),
+    Ubsan(String),
+    Asan(String),
+    /// The testcase didn't build or exited with an error under a sanitizer
+    Failed(String),
+}
+
+/// Enough about a tool binary to tell which build it was weeks later
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BuildIdentity {
+    pub path: PathBuf,
+    pub sha256: String,
+    /// Full --version output
+    pub version: String,
+    /// GCC's "Configured with:" line from -v
+    pub configured_with: Option<String>,
+    /// GCC's -dumpfullversion
+    pub full_version: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct BuildIdentities {
+    pub compilers: Vec<BuildIdentity>,
+    /// QEMU/Spike binaries. Native runs have none.
+    pub runners: Vec<BuildIdentity>,
+    /// None for generators that only copy testcases
+    pub generator: Option<BuildIdentity>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -166,6 +373,14 @@ pub struct IceFailInfo {
     pub testcases: Vec<PathBuf>,
     pub action: Action,
     pub generator: FuzzGenerator,
+    /// Randomized generator options the testcase was generated with
+    pub generator_args: Option<Vec<String>>,
+    /// Corpus file the testcase was copied from
+    pub corpus_testcase: Option<PathBuf>,
+    /// Builds of the tools the campaign ran with
+    pub build_identities: Option<BuildIdentities>,
+    /// Compiler commit that introduced the failure, found by bisect
+    pub first_bad_commit: Option<String>,
     pub fail_type: IceFailType,
 }
 
@@ -175,7 +390,15 @@ pub struct ExecFailInfo {
     pub architecture: Architecture,
     pub testcase: Vec<PathBuf>,
     pub generator: FuzzGenerator,
+    /// Randomized generator options the testcase was generated with
+    pub generator_args: Option<Vec<String>>,
+    /// Corpus file the testcase was copied from
+    pub corpus_testcase: Option<PathBuf>,
     pub runner: Runner,
+    /// Builds of the tools the campaign ran with
+    pub build_identities: Option<BuildIdentities>,
+    /// Compiler commit that introduced the failure, found by bisect
+    pub first_bad_commit: Option<String>,
     pub fail_type: ExecFailType,
 }
 
@@ -187,8 +410,21 @@ pub struct RuntimeFailInfo {
     pub slow_compiler: Vec<PathBuf>,
     pub slow_architecture: Architecture,
     pub slow_runner: Runner,
+    /// slow_runner with the vector config the slow binary printed something
+    /// else under, for VectorConfigMismatch
+    pub sweep_runner: Option<Box<Runner>>,
     pub testcase: Vec<PathBuf>,
     pub generator: FuzzGenerator,
+    /// Randomized generator options the testcase was generated with
+    pub generator_args: Option<Vec<String>>,
+    /// Corpus file the testcase was copied from
+    pub corpus_testcase: Option<PathBuf>,
+    /// ubsan/asan result when the fuzzer ran with ub_check
+    pub ub_check: Option<UbCheckResult>,
+    /// Builds of the tools the campaign ran with
+    pub build_identities: Option<BuildIdentities>,
+    /// Compiler commit that introduced the failure, found by bisect
+    pub first_bad_commit: Option<String>,
     pub fail_type: Option<RuntimeFailType>,
 }
 
@@ -201,7 +437,52 @@ pub enum FailInfo {
 
 /// Reduction config structs/enums
 
-pub struct ReductionConfig {}
+/// Path prefix in fail_info.yaml to replace, given as `old=new` on the command
+/// line
+#[derive(Deserialize, Clone, Debug)]
+pub struct PathRemap {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+impl FromStr for PathRemap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (from, to) = s
+            .split_once('=')
+            .ok_or_else(|| format!("Expected old=new, got {s}"))?;
+        Ok(PathRemap {
+            from: from.into(),
+            to: to.into(),
+        })
+    }
+}
+
+/// Where things are on the machine doing the reduction, when that isn't the
+/// machine that found the fail (e.g. finds pulled out of a CI container)
+#[derive(Deserialize, Default, Debug)]
+pub struct ReductionConfig {
+    /// Applied to every path in fail_info.yaml before the overrides below
+    pub remap: Option<Vec<PathRemap>>,
+    /// Replaces the compilers. For runtime fails only the slow compiler is
+    /// replaced.
+    pub compiler: Option<PathBuf>,
+    pub qemu_rv32: Option<PathBuf>,
+    pub qemu_rv64: Option<PathBuf>,
+    pub qemu_aarch64: Option<PathBuf>,
+    pub csmith_include_dir: Option<PathBuf>,
+    /// Directory with the LLVM tools (llvm-dis, llc, llvm-reduce) used to
+    /// reduce IR. Falls back to $LLVM_TOOLCHAIN.
+    pub toolchain: Option<PathBuf>,
+    /// Bug report templates to use instead of templates/gcc-bugzilla.txt and
+    /// templates/llvm-github.md
+    pub gcc_report_template: Option<PathBuf>,
+    pub llvm_report_template: Option<PathBuf>,
+    /// Compiler Explorer compiler id for the report's link, when the one
+    /// worked out from the compiler path and version is wrong
+    pub godbolt_compiler: Option<String>,
+}
 
 /// Fns
 
@@ -224,6 +505,7 @@ pub fn ignorable_warnings() -> Vec<String> {
         "-Wno-parentheses-equality",
         "-Wno-self-assign",
         "-Wno-implicit-const-int-float-conversion",
+        "-Wno-unknown-pragmas",
         // gcc
         "-Wno-unknown-warning-option",
         "-Wno-bool-compare",
@@ -238,3 +520,81 @@ pub fn ignorable_warnings() -> Vec<String> {
     .map(|x| x.to_string())
     .collect()
 }
+
+/// Testcases with a C++ extension, e.g. yarpgen's --std=c++ or csmith's
+/// --lang-cpp output
+pub fn is_cpp(testcases: &[PathBuf]) -> bool {
+    testcases.iter().any(|testcase| {
+        matches!(
+            testcase.extension().and_then(|ext| ext.to_str()),
+            Some("cpp" | "cc" | "cxx")
+        )
+    })
+}
+
+/// Testcases from the LlvmIr generator, which skip the frontend
+pub fn is_llvm_ir(testcases: &[PathBuf]) -> bool {
+    testcases
+        .iter()
+        .any(|testcase| testcase.extension().is_some_and(|ext| ext == "ll"))
+}
+
+pub fn ignorable_cpp_warnings() -> Vec<String> {
+    // g++ warns about C only options
+    let c_only = ["-Wno-pointer-sign"];
+    let mut warnings = ignorable_warnings()
+        .into_iter()
+        .filter(|warning| !c_only.contains(&warning.as_str()))
+        .collect::<Vec<_>>();
+    warnings.extend(
+        [
+            "-Wno-narrowing",
+            "-Wno-c++11-narrowing",
+            "-Wno-class-memaccess",
+            "-Wno-deprecated",
+            "-Wno-unused-private-field",
+        ]
+        .iter()
+        .map(|x| x.to_string()),
+    );
+    warnings
+}
+
+/// Warnings to ignore for the language the testcases are written in
+pub fn ignorable_warnings_for(testcases: &[PathBuf]) -> Vec<String> {
+    if is_cpp(testcases) {
+        ignorable_cpp_warnings()
+    } else {
+        ignorable_warnings()
+    }
+}
+
+/// Guess the compiler from its file name. clang++ and g++ are the same compilers
+/// as clang and gcc.
+pub fn detect_compiler(compiler_path: &Path) -> Option<Compiler> {
+    // Only the file name, the directories may be named after another compiler
+    let name = compiler_path.file_name()?.to_str()?;
+    if name.contains("clang") {
+        Some(Compiler::Llvm)
+    } else if name.contains("gcc") || name.contains("g++") {
+        Some(Compiler::Gcc)
+    } else if name.contains("rustc") {
+        Some(Compiler::Rustc)
+    } else {
+        None
+    }
+}
+
+/// Another tool from the same LLVM build as clang, e.g. opt, llc or llvm-reduce
+pub fn llvm_tool(compiler_path: &Path, tool: &str) -> PathBuf {
+    compiler_path.with_file_name(tool)
+}
+
+/// Quote a field for a CSV summary if it needs it
+pub fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
@@ -0,0 +1,322 @@
+//! Source mutations for the Mutate generator. The mutations only touch
+//! constants, integer types and unroll pragmas so they usually keep a program
+//! UB-free, but every mutant still has to pass check_for_ub before use.
+
+use std::ops::Range;
+
+use rand::{seq::SliceRandom, Rng};
+
+const INT_TYPES: [&str; 4] = ["int8_t", "int16_t", "int32_t", "int64_t"];
+const UINT_TYPES: [&str; 4] = ["uint8_t", "uint16_t", "uint32_t", "uint64_t"];
+
+/// Apply up to `count` random mutations. Returns the mutated source and a
+/// description of each mutation that was applied.
+pub fn mutate(source: &str, count: u32, rng: &mut impl Rng) -> (String, Vec<String>) {
+    let mut source = source.to_string();
+    let mut applied = vec![];
+    for _ in 0..count {
+        let mutation = match rng.gen_range(0..4) {
+            0 => perturb_constant(&source, rng),
+            1 => change_loop_bound(&source, rng),
+            2 => swap_type_width(&source, rng),
+            _ => insert_loop_pragma(&source, rng),
+        };
+        if let Some((mutated, description)) = mutation {
+            source = mutated;
+            applied.push(description);
+        }
+    }
+    (source, applied)
+}
+
+/// Replace an integer literal with a nearby or boundary value
+fn perturb_constant(source: &str, rng: &mut impl Rng) -> Option<(String, String)> {
+    let literals = integer_literals(source, 0..source.len());
+    let literal = literals.choose(rng)?;
+    let value = parse_literal(&source[literal.clone()])?;
+    let new_value = *[
+        value.wrapping_add(1),
+        value.saturating_sub(1),
+        0,
+        1,
+        value.wrapping_mul(2),
+        i8::MAX as u64,
+        u8::MAX as u64,
+        i16::MAX as u64,
+        u16::MAX as u64,
+        i32::MAX as u64,
+    ]
+    .choose(rng)?;
+    replace_literal(source, literal, new_value, "constant")
+}
+
+/// Change the bound in the condition of a for loop, which changes the trip
+/// count the vectorizer sees
+fn change_loop_bound(source: &str, rng: &mut impl Rng) -> Option<(String, String)> {
+    let conditions = for_loop_conditions(source);
+    let condition = conditions.choose(rng)?;
+    let literals = integer_literals(source, condition.clone());
+    let literal = literals.choose(rng)?;
+    let value = parse_literal(&source[literal.clone()])?;
+    let new_value = *[
+        value.wrapping_add(1),
+        value.saturating_sub(1),
+        value.wrapping_mul(2),
+        value / 2,
+        value.wrapping_add(rng.gen_range(2..=16)),
+    ]
+    .choose(rng)?;
+    replace_literal(source, literal, new_value, "loop-bound")
+}
+
+/// Swap a fixed width integer type for another width of the same signedness
+fn swap_type_width(source: &str, rng: &mut impl Rng) -> Option<(String, String)> {
+    let mut occurrences = vec![];
+    for types in [INT_TYPES, UINT_TYPES] {
+        for ty in types {
+            for (start, _) in source.match_indices(ty) {
+                let end = start + ty.len();
+                if is_word_boundary(source, start, end) {
+                    occurrences.push((start..end, types));
+                }
+            }
+        }
+    }
+    let (range, types) = occurrences.choose(rng)?;
+    let old_type = &source[range.clone()];
+    let new_type = *types
+        .iter()
+        .filter(|&&ty| ty != old_type)
+        .collect::<Vec<_>>()
+        .choose(rng)?;
+
+    let mut mutated = source.to_string();
+    mutated.replace_range(range.clone(), new_type);
+    Some((mutated, format!("type-width:{old_type}->{new_type}")))
+}
+
+/// Put an unroll pragma in front of a for loop. ivdep is left out: it
+/// promises the loop has no loop-carried dependencies, which the mutated
+/// program can break without any UB check noticing, and a wrong-code find
+/// from a broken promise isn't a compiler bug.
+fn insert_loop_pragma(source: &str, rng: &mut impl Rng) -> Option<(String, String)> {
+    let mut offset = 0;
+    let mut loops = vec![];
+    let mut previous_line = "";
+    for line in source.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        // Loops only get one pragma
+        if (trimmed.starts_with("for (") || trimmed.starts_with("for("))
+            && !previous_line.starts_with("#pragma")
+        {
+            loops.push((offset, &line[..line.len() - trimmed.len()]));
+        }
+        previous_line = trimmed;
+        offset += line.len();
+    }
+    let (start, indent) = loops.choose(rng)?;
+
+    let pragma = format!(
+        "#pragma GCC unroll {}",
+        [0, 1, 2, 4, 8, 16].choose(rng).unwrap()
+    );
+
+    let mut mutated = source.to_string();
+    mutated.insert_str(*start, &format!("{indent}{pragma}\n"));
+    Some((mutated, format!("pragma:{pragma}")))
+}
+
+fn replace_literal(
+    source: &str,
+    literal: &Range<usize>,
+    new_value: u64,
+    kind: &str,
+) -> Option<(String, String)> {
+    let old_literal = &source[literal.clone()];
+    let digits_end = old_literal
+        .find(['u', 'U', 'l', 'L'])
+        .unwrap_or(old_literal.len());
+    let suffix = &old_literal[digits_end..];
+    let new_literal = if old_literal.starts_with("0x") || old_literal.starts_with("0X") {
+        format!("0x{new_value:X}{suffix}")
+    } else {
+        format!("{new_value}{suffix}")
+    };
+    if new_literal == old_literal {
+        return None;
+    }
+
+    let mut mutated = source.to_string();
+    mutated.replace_range(literal.clone(), &new_literal);
+    Some((mutated, format!("{kind}:{old_literal}->{new_literal}")))
+}
+
+fn parse_literal(literal: &str) -> Option<u64> {
+    let digits = literal.trim_end_matches(['u', 'U', 'l', 'L']);
+    if let Some(hex) = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        u64::from_str_radix(hex, 16).ok()
+    } else if digits.len() > 1 && digits.starts_with('0') {
+        // Leave octal alone
+        None
+    } else {
+        digits.parse().ok()
+    }
+}
+
+/// Integer literals in `range`, skipping comments, strings, character
+/// literals and preprocessor lines
+fn integer_literals(source: &str, range: Range<usize>) -> Vec<Range<usize>> {
+    let bytes = source.as_bytes();
+    let mut literals = vec![];
+    let mut i = 0;
+    let mut line_start = true;
+    while i < range.end {
+        let c = bytes[i];
+        if line_start && c == b'#' {
+            i = find_from(bytes, i, b"\n").unwrap_or(bytes.len());
+            continue;
+        }
+        if !c.is_ascii_whitespace() {
+            line_start = false;
+        }
+        if bytes[i..].starts_with(b"//") {
+            i = find_from(bytes, i, b"\n").unwrap_or(bytes.len());
+        } else if bytes[i..].starts_with(b"/*") {
+            i = find_from(bytes, i, b"*/").map_or(bytes.len(), |end| end + 2);
+        } else if c == b'"' || c == b'\'' {
+            i += 1;
+            while i < bytes.len() && bytes[i] != c {
+                i += if bytes[i] == b'\\' { 2 } else { 1 };
+            }
+            i += 1;
+        } else if c.is_ascii_digit()
+            && (i == 0
+                || !(bytes[i - 1].is_ascii_alphanumeric() || matches!(bytes[i - 1], b'_' | b'.')))
+        {
+            let start = i;
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                i += 1;
+            }
+            let is_float = i < bytes.len() && bytes[i] == b'.';
+            if !is_float && start >= range.start {
+                literals.push(start..i);
+            }
+        } else {
+            if c == b'\n' {
+                line_start = true;
+            }
+            i += 1;
+        }
+    }
+    literals
+}
+
+/// The condition of each `for (init; condition; step)`
+fn for_loop_conditions(source: &str) -> Vec<Range<usize>> {
+    let mut conditions = vec![];
+    for (start, _) in source.match_indices("for") {
+        if !is_word_boundary(source, start, start + 3) {
+            continue;
+        }
+        let Some(open) = source[start + 3..]
+            .find(|c: char| !c.is_whitespace())
+            .map(|offset| start + 3 + offset)
+            .filter(|&open| source.as_bytes()[open] == b'(')
+        else {
+            continue;
+        };
+        let Some(first) = source[open..].find(';').map(|offset| open + offset + 1) else {
+            continue;
+        };
+        let Some(second) = source[first..].find(';').map(|offset| first + offset) else {
+            continue;
+        };
+        conditions.push(first..second);
+    }
+    conditions
+}
+
+fn find_from(bytes: &[u8], from: usize, pattern: &[u8]) -> Option<usize> {
+    bytes[from..]
+        .windows(pattern.len())
+        .position(|window| window == pattern)
+        .map(|offset| from + offset)
+}
+
+fn is_word_boundary(source: &str, start: usize, end: usize) -> bool {
+    let is_ident = |c: u8| c.is_ascii_alphanumeric() || c == b'_';
+    let bytes = source.as_bytes();
+    (start == 0 || !is_ident(bytes[start - 1])) && (end == bytes.len() || !is_ident(bytes[end]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    const PROGRAM: &str = "\
+#include <stdint.h>
+#define N 16
+// 42 in a comment
+int32_t a[N];
+int main(void) {
+  const char *s = \"7\";
+  for (int32_t i = 0; i < 16; i++)
+    a[i] = i * 3;
+  return a[0x2];
+}
+";
+
+    #[test]
+    fn deterministic_for_a_seed() {
+        for seed in 0..20 {
+            let first = mutate(PROGRAM, 5, &mut StdRng::seed_from_u64(seed));
+            let second = mutate(PROGRAM, 5, &mut StdRng::seed_from_u64(seed));
+            assert_eq!(first, second);
+            assert!(first.1.len() <= 5);
+        }
+    }
+
+    #[test]
+    fn leaves_comments_strings_and_directives_alone() {
+        for seed in 0..50 {
+            let (mutated, _) = mutate(PROGRAM, 10, &mut StdRng::seed_from_u64(seed));
+            assert!(mutated.contains("#include <stdint.h>\n#define N 16\n// 42 in a comment\n"));
+            assert!(mutated.contains("\"7\""));
+        }
+    }
+
+    #[test]
+    fn nothing_to_mutate() {
+        let source = "// 1 2 3\nchar *s = \"4\";\n";
+        let (mutated, applied) = mutate(source, 10, &mut StdRng::seed_from_u64(0));
+        assert_eq!(mutated, source);
+        assert!(applied.is_empty());
+    }
+
+    #[test]
+    fn one_pragma_per_loop() {
+        let source = "void f(void) {\n  for (;;) {}\n}\n";
+        let (mutated, applied) = mutate(source, 20, &mut StdRng::seed_from_u64(0));
+        assert_eq!(applied.len(), 1);
+        assert_eq!(mutated.matches("  #pragma GCC unroll ").count(), 1);
+    }
+
+    #[test]
+    fn literals() {
+        assert_eq!(parse_literal("16"), Some(16));
+        assert_eq!(parse_literal("0x1Fu"), Some(31));
+        assert_eq!(parse_literal("42UL"), Some(42));
+        assert_eq!(parse_literal("017"), None);
+        assert_eq!(
+            replace_literal("x = 0x10u;", &(4..9), 255, "constant"),
+            Some((
+                "x = 0xFFu;".to_string(),
+                "constant:0x10u->0xFFu".to_string()
+            ))
+        );
+    }
+}
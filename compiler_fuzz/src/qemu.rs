@@ -0,0 +1,342 @@
+//! QEMU_CPU for RISC-V programs, computed from the ISA string the compiler
+//! recorded in the .riscv.attributes section of the ELF.
+
+use std::path::Path;
+
+use anyhow::Context;
+use object::{
+    elf,
+    read::elf::{AttributesSection, ElfFile, FileHeader},
+    Object, ObjectSection,
+};
+
+const TAG_RISCV_ARCH: u64 = 5;
+
+/// Extensions QEMU has a cpu property for, as of QEMU 9.2. zvl*b and zve* are
+/// handled separately since they also set vlen and elen.
+const QEMU_EXTENSIONS: [&str; 96] = [
+    "zic64b",
+    "zicbom",
+    "zicbop",
+    "zicboz",
+    "ziccamoa",
+    "ziccif",
+    "zicclsm",
+    "ziccrse",
+    "zicfilp",
+    "zicfiss",
+    "zicond",
+    "zicntr",
+    "zicsr",
+    "zifencei",
+    "zihintntl",
+    "zihintpause",
+    "zihpm",
+    "zimop",
+    "zmmul",
+    "za64rs",
+    "zaamo",
+    "zabha",
+    "zacas",
+    "zalrsc",
+    "zama16b",
+    "zawrs",
+    "zfa",
+    "zfbfmin",
+    "zfh",
+    "zfhmin",
+    "zfinx",
+    "zdinx",
+    "zhinx",
+    "zhinxmin",
+    "zca",
+    "zcb",
+    "zcd",
+    "zce",
+    "zcf",
+    "zcmop",
+    "zcmp",
+    "zcmt",
+    "zba",
+    "zbb",
+    "zbc",
+    "zbkb",
+    "zbkc",
+    "zbkx",
+    "zbs",
+    "zk",
+    "zkn",
+    "zknd",
+    "zkne",
+    "zknh",
+    "zkr",
+    "zks",
+    "zksed",
+    "zksh",
+    "zkt",
+    "ztso",
+    "zvbb",
+    "zvbc",
+    "zvfbfmin",
+    "zvfbfwma",
+    "zvfh",
+    "zvfhmin",
+    "zvkb",
+    "zvkg",
+    "zvkn",
+    "zvknc",
+    "zvkned",
+    "zvkng",
+    "zvknha",
+    "zvknhb",
+    "zvks",
+    "zvksc",
+    "zvksed",
+    "zvksg",
+    "zvksh",
+    "zvkt",
+    "svade",
+    "svadu",
+    "svinval",
+    "svnapot",
+    "svpbmt",
+    "xtheadba",
+    "xtheadbb",
+    "xtheadbs",
+    "xtheadcmo",
+    "xtheadcondmov",
+    "xtheadfmemidx",
+    "xtheadfmv",
+    "xtheadmac",
+    "xtheadmemidx",
+    "xtheadmempair",
+    "xtheadsync",
+];
+
+const QEMU_SINGLE_LETTER_EXTENSIONS: &str = "iemafdcbvh";
+
+pub enum QemuCpu {
+    Cpu(String),
+    /// Extensions in the ISA string that QEMU has no property for
+    Unsupported(Vec<String>),
+}
+
+impl QemuCpu {
+    pub fn cpu(self) -> anyhow::Result<String> {
+        match self {
+            QemuCpu::Cpu(cpu) => Ok(cpu),
+            QemuCpu::Unsupported(extensions) => {
+                anyhow::bail!(
+                    "QEMU does not support extensions: {}",
+                    extensions.join(", ")
+                )
+            }
+        }
+    }
+}
+
+/// QEMU_CPU for a RISC-V program. Programs using extensions QEMU doesn't
+/// implement come back as Unsupported rather than an error so the fuzzer can
+/// skip them.
+pub fn riscv_qemu_cpu(program: &Path) -> anyhow::Result<QemuCpu> {
+    Ok(qemu_cpu(&riscv_arch(program)?))
+}
+
+/// The Tag_RISCV_arch attribute of an ELF, e.g. rv64i2p1_m2p0_..._zvl256b1p0
+pub fn riscv_arch(program: &Path) -> anyhow::Result<String> {
+    let data = std::fs::read(program).with_context(|| format!("Reading {program:?}"))?;
+    let arch = match object::File::parse(&*data)? {
+        object::File::Elf32(elf) => elf_arch(&elf)?,
+        object::File::Elf64(elf) => elf_arch(&elf)?,
+        _ => anyhow::bail!("{program:?} is not an ELF file"),
+    };
+    arch.with_context(|| format!("{program:?} has no Tag_RISCV_arch attribute"))
+}
+
+fn elf_arch<Elf: FileHeader>(elf: &ElfFile<Elf>) -> anyhow::Result<Option<String>> {
+    let endian = elf.endian();
+    anyhow::ensure!(
+        elf.elf_header().e_machine(endian) == elf::EM_RISCV,
+        "Not a RISC-V ELF"
+    );
+    let Some(section) = elf.section_by_name(".riscv.attributes") else {
+        return Ok(None);
+    };
+
+    let attributes = AttributesSection::<Elf>::new(endian, section.data()?)?;
+    let mut subsections = attributes.subsections()?;
+    while let Some(subsection) = subsections.next()? {
+        if subsection.vendor() != b"riscv" {
+            continue;
+        }
+        let mut subsubsections = subsection.subsubsections();
+        while let Some(subsubsection) = subsubsections.next()? {
+            if subsubsection.tag() != elf::Tag_File {
+                continue;
+            }
+            let mut reader = subsubsection.attributes();
+            while let Some(tag) = reader.read_tag()? {
+                // Odd tags are strings, even tags are integers
+                if tag == TAG_RISCV_ARCH {
+                    let arch = reader.read_string()?;
+                    return Ok(Some(String::from_utf8_lossy(arch).into_owned()));
+                } else if tag % 2 == 1 {
+                    reader.read_string()?;
+                } else {
+                    reader.read_integer()?;
+                }
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Map an ISA string to a QEMU cpu property list
+/// (`rv64,v=true,vlen=256,elen=64,zba=true,...`)
+pub fn qemu_cpu(arch: &str) -> QemuCpu {
+    let arch = arch.to_lowercase();
+    let mut extensions = arch.split('_').map(strip_extension_version);
+    let base = extensions.next().unwrap_or_default();
+    let (xlen, base_letters) = if let Some(base_letters) = base.strip_prefix("rv32") {
+        ("rv32", base_letters)
+    } else if let Some(base_letters) = base.strip_prefix("rv64") {
+        ("rv64", base_letters)
+    } else {
+        return QemuCpu::Unsupported(vec![base.to_string()]);
+    };
+    // The attributes separate single letter extensions too (rv64i2p1_m2p0)
+    let (single_letter_extensions, extensions): (Vec<_>, Vec<_>) =
+        extensions.partition(|extension| extension.len() == 1);
+    let single_letters = base_letters.to_string() + &single_letter_extensions.concat();
+
+    let mut properties = vec![xlen.to_string()];
+    let mut unsupported = vec![];
+    let mut vlen = None;
+    let mut elen = None;
+
+    for letter in single_letters.chars() {
+        match letter {
+            'g' => properties.extend(
+                ["i", "m", "a", "f", "d", "zicsr", "zifencei"].map(|ext| format!("{ext}=true")),
+            ),
+            letter if QEMU_SINGLE_LETTER_EXTENSIONS.contains(letter) => {
+                properties.push(format!("{letter}=true"))
+            }
+            letter => unsupported.push(letter.to_string()),
+        }
+        if letter == 'v' {
+            vlen = vlen.max(Some(128));
+            elen = Some(64);
+        }
+    }
+
+    for extension in extensions {
+        if let Some(bits) = extension
+            .strip_prefix("zvl")
+            .and_then(|bits| bits.strip_suffix('b'))
+            .and_then(|bits| bits.parse::<u32>().ok())
+        {
+            // QEMU doesn't go below the VLEN of V
+            vlen = vlen.max(Some(bits.max(128)));
+        } else if let Some(zve) = extension.strip_prefix("zve") {
+            vlen = vlen.max(Some(128));
+            elen = elen.max(Some(if zve.starts_with("64") { 64 } else { 32 }));
+            if ["32x", "32f", "64x", "64f", "64d"].contains(&zve) {
+                properties.push(format!("{extension}=true"));
+            } else {
+                unsupported.push(extension.to_string());
+            }
+        } else if QEMU_EXTENSIONS.contains(&extension) {
+            properties.push(format!("{extension}=true"));
+        } else {
+            unsupported.push(extension.to_string());
+        }
+    }
+
+    if !unsupported.is_empty() {
+        return QemuCpu::Unsupported(unsupported);
+    }
+
+    // The generic cpus have F and D on, which QEMU won't combine with zfinx
+    if properties.iter().any(|property| property == "zfinx=true") {
+        properties.extend(["f=false", "d=false"].map(String::from));
+    }
+    if let Some(vlen) = vlen {
+        properties.push(format!("vlen={vlen}"));
+    }
+    if let Some(elen) = elen {
+        properties.push(format!("elen={elen}"));
+    }
+    QemuCpu::Cpu(properties.join(","))
+}
+
+/// Drop the version from an extension in an ISA string (m2p0 -> m)
+pub fn strip_extension_version(extension: &str) -> &str {
+    let extension = extension.trim_end_matches(|c: char| c.is_ascii_digit());
+    match extension.strip_suffix('p') {
+        Some(major) if major.ends_with(|c: char| c.is_ascii_digit()) => {
+            major.trim_end_matches(|c: char| c.is_ascii_digit())
+        }
+        _ => extension,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cpu(arch: &str) -> String {
+        match qemu_cpu(arch) {
+            QemuCpu::Cpu(cpu) => cpu,
+            QemuCpu::Unsupported(extensions) => panic!("{arch} unsupported: {extensions:?}"),
+        }
+    }
+
+    fn unsupported(arch: &str) -> Vec<String> {
+        match qemu_cpu(arch) {
+            QemuCpu::Cpu(cpu) => panic!("{arch} supported: {cpu}"),
+            QemuCpu::Unsupported(extensions) => extensions,
+        }
+    }
+
+    #[test]
+    fn vector_extensions() {
+        assert_eq!(
+            cpu("rv64i2p1_m2p0_a2p1_f2p2_d2p2_c2p0_v1p0_zicsr2p0_zifencei2p0_zba1p0_zve32f1p0_zve32x1p0_zve64d1p0_zve64f1p0_zve64x1p0_zvl128b1p0_zvl256b1p0_zvl32b1p0_zvl64b1p0"),
+            "rv64,i=true,m=true,a=true,f=true,d=true,c=true,v=true,zicsr=true,zifencei=true,zba=true,zve32f=true,zve32x=true,zve64d=true,zve64f=true,zve64x=true,vlen=256,elen=64"
+        );
+    }
+
+    #[test]
+    fn embedded_vector_without_v() {
+        assert_eq!(
+            cpu("rv32i2p1_zicsr2p0_zve32x1p0_zvl64b1p0"),
+            "rv32,i=true,zicsr=true,zve32x=true,vlen=128,elen=32"
+        );
+    }
+
+    #[test]
+    fn g_and_zfinx() {
+        assert_eq!(
+            cpu("rv64gc_zfinx"),
+            "rv64,i=true,m=true,a=true,f=true,d=true,zicsr=true,zifencei=true,c=true,zfinx=true,f=false,d=false"
+        );
+    }
+
+    #[test]
+    fn unsupported_extensions() {
+        assert_eq!(unsupported("rv64gc_xfoo1p0_zve32d"), vec!["xfoo", "zve32d"]);
+        assert_eq!(unsupported("rv64iq"), vec!["q"]);
+        assert_eq!(unsupported("foo"), vec!["foo"]);
+    }
+
+    #[test]
+    fn extension_versions() {
+        assert_eq!(strip_extension_version("m2p0"), "m");
+        assert_eq!(strip_extension_version("rv64i2p1"), "rv64i");
+        assert_eq!(strip_extension_version("zvl256b1p0"), "zvl256b");
+        assert_eq!(strip_extension_version("zve64x"), "zve64x");
+        assert_eq!(strip_extension_version("zba"), "zba");
+        assert_eq!(strip_extension_version("v1"), "v");
+    }
+}
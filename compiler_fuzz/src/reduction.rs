@@ -1,15 +1,171 @@
-use std::{path::PathBuf, process::exit};
-
+use std::{
+    fmt,
+    os::unix::process::ExitStatusExt,
+    path::{Path, PathBuf},
+    process::exit,
+};
+
+use serde_yaml::Value;
 use xshell::{cmd, Shell};
 
-use crate::{generate::get_generator_flags, ignorable_warnings, Architecture, FuzzGenerator};
+use crate::{
+    generate::get_generator_flags, ignorable_warnings_for, is_cpp, is_llvm_ir, llvm_tool,
+    Architecture, FailInfo, FuzzGenerator, LlvmIrConfig, LlvmIrSource, PathRemap, ReductionConfig,
+    Runner, UbCheckResult,
+};
+
+#[derive(Debug)]
+pub enum Sanitizer {
+    Ubsan,
+    Asan,
+}
+
+impl fmt::Display for Sanitizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Sanitizer::Ubsan => "ubsan",
+            Sanitizer::Asan => "asan",
+        })
+    }
+}
+
+/// Why a testcase failed one of the checks an interestingness test makes
+/// before looking for the fail itself
+#[derive(Debug)]
+pub enum ReductionCheckError {
+    UnexpectedWarning {
+        stderr: String,
+    },
+    SanitizerError {
+        kind: Sanitizer,
+        report: String,
+    },
+    /// The testcase didn't build or exited with an error under a sanitizer
+    SanitizerFailed {
+        stderr: String,
+    },
+    Timeout {
+        output: String,
+    },
+    CompileFailed {
+        output: String,
+        stderr: String,
+    },
+    /// Running the check itself went wrong, the testcase may be fine
+    Other(anyhow::Error),
+}
+
+impl fmt::Display for ReductionCheckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReductionCheckError::UnexpectedWarning { stderr } => {
+                write!(f, "Unexpected warning! {stderr}")
+            }
+            ReductionCheckError::SanitizerError { kind, report } => {
+                write!(f, "{kind} found error: {report}")
+            }
+            ReductionCheckError::SanitizerFailed { stderr } => {
+                write!(f, "Sanitizer build/run failed! {stderr}")
+            }
+            ReductionCheckError::Timeout { output } => write!(f, "{output} compilation timeout!"),
+            ReductionCheckError::CompileFailed { output, stderr } => {
+                write!(f, "Unrecognized {output} compilation failure: {stderr}")
+            }
+            ReductionCheckError::Other(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for ReductionCheckError {}
+
+/// Exit code of a reduce_* interestingness test when the testcase no longer
+/// fails the way it should. creduce only looks for 0, but `reproduces` has to
+/// tell a testcase that stopped failing from a test that broke, and main
+/// returning an error exits with 1 (a panic with 101).
+pub const NOT_INTERESTING: i32 = 10;
+
+impl ReductionCheckError {
+    /// Every check failure but Other means the testcase isn't interesting,
+    /// each with its own code so the reducer's logs say which check it was
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ReductionCheckError::UnexpectedWarning { .. } => NOT_INTERESTING + 1,
+            ReductionCheckError::SanitizerError { .. } => NOT_INTERESTING + 2,
+            ReductionCheckError::SanitizerFailed { .. } => NOT_INTERESTING + 3,
+            ReductionCheckError::Timeout { .. } => NOT_INTERESTING + 4,
+            ReductionCheckError::CompileFailed { .. } => NOT_INTERESTING + 5,
+            ReductionCheckError::Other(_) => 2,
+        }
+    }
+}
+
+/// Whether an interestingness test exiting with `code` rejected the testcase,
+/// rather than failing to test it
+pub fn is_not_interesting(code: i32) -> bool {
+    (NOT_INTERESTING..=NOT_INTERESTING + 5).contains(&code)
+}
+
+/// Exit an interestingness test with the check's exit code if the testcase
+/// failed one of the reduction checks
+pub fn exit_on_check_error(result: Result<(), ReductionCheckError>) {
+    if let Err(error) = result {
+        println!("{error}");
+        exit(error.exit_code());
+    }
+}
+
+impl From<anyhow::Error> for ReductionCheckError {
+    fn from(error: anyhow::Error) -> Self {
+        ReductionCheckError::Other(error)
+    }
+}
+
+impl From<xshell::Error> for ReductionCheckError {
+    fn from(error: xshell::Error) -> Self {
+        ReductionCheckError::Other(error.into())
+    }
+}
+
+impl From<std::io::Error> for ReductionCheckError {
+    fn from(error: std::io::Error) -> Self {
+        ReductionCheckError::Other(error.into())
+    }
+}
+
+impl From<std::string::FromUtf8Error> for ReductionCheckError {
+    fn from(error: std::string::FromUtf8Error) -> Self {
+        ReductionCheckError::Other(error.into())
+    }
+}
 
 pub fn check_for_ub(
     sh: &Shell,
     generator: &FuzzGenerator,
     testcases: &[PathBuf],
     rv32: bool,
-) -> anyhow::Result<()> {
+) -> Result<(), ReductionCheckError> {
+    match ub_check(sh, generator, testcases, rv32)? {
+        UbCheckResult::Clean => Ok(()),
+        UbCheckResult::Warning(stderr) => Err(ReductionCheckError::UnexpectedWarning { stderr }),
+        UbCheckResult::Ubsan(report) => Err(ReductionCheckError::SanitizerError {
+            kind: Sanitizer::Ubsan,
+            report,
+        }),
+        UbCheckResult::Asan(report) => Err(ReductionCheckError::SanitizerError {
+            kind: Sanitizer::Asan,
+            report,
+        }),
+        UbCheckResult::Failed(stderr) => Err(ReductionCheckError::SanitizerFailed { stderr }),
+    }
+}
+
+/// Compile and run the testcase with clang's ubsan and gcc's asan (on x86)
+pub fn ub_check(
+    sh: &Shell,
+    generator: &FuzzGenerator,
+    testcases: &[PathBuf],
+    rv32: bool,
+) -> anyhow::Result<UbCheckResult> {
     assert!(!testcases.is_empty());
 
     let rv32_flags = &if rv32 {
@@ -20,36 +176,26 @@ pub fn check_for_ub(
 
     let generator_flags = &get_generator_flags(generator, &Architecture::X86).unwrap();
 
-    let ignorable_warnings = &ignorable_warnings();
+    let ignorable_warnings = &ignorable_warnings_for(testcases);
+    let (clang, gcc) = if is_cpp(testcases) {
+        ("clang++", "g++")
+    } else {
+        ("clang", "gcc")
+    };
 
     if testcases.len() == 1 {
         let testcase = &testcases[0];
 
-        let stderr = cmd!(sh, "timeout -k 1 4 clang -fsanitize=undefined {testcase} -Wall -Wzero-length-array {ignorable_warnings...} -o clang-ubsan.out -fsigned-char -fno-strict-aliasing -fwrapv {rv32_flags...} {generator_flags...}").quiet().read_stderr()?;
+        let mut compile_command: std::process::Command = cmd!(sh, "timeout -k 1 4 {clang} -fsanitize=undefined {testcase} -Wall -Wzero-length-array {ignorable_warnings...} -o clang-ubsan.out -fsigned-char -fno-strict-aliasing -fwrapv {rv32_flags...} {generator_flags...}").quiet().into();
+        let command_output = compile_command.output()?;
+        let stderr = String::from_utf8(command_output.stderr)?;
 
-        if stderr.contains("warning:") {
-            println!("Unexpected warning! {stderr}");
-            exit(1);
+        if !command_output.status.success() {
+            return Ok(UbCheckResult::Failed(stderr));
         }
 
-        let stderr = cmd!(sh, "timeout -k 1 4 ./clang-ubsan.out")
-            .quiet()
-            .read_stderr()?;
-
-        if stderr.contains("Error") {
-            println!("ubsan found error: {stderr}");
-            exit(1);
-        }
-
-        cmd!(sh, "timeout -k 1 4 gcc -fsanitize=address {testcase} -w -o gcc-asan.out -fsigned-char -fno-strict-aliasing -fwrapv {ignorable_warnings...} {rv32_flags...} {generator_flags...}").quiet().read()?;
-
-        let stderr = cmd!(sh, "timeout -k 1 4 ./gcc-asan.out")
-            .quiet()
-            .read_stderr()?;
-
-        if stderr.contains("Error") {
-            println!("asan found error: {stderr}");
-            exit(1);
+        if stderr.contains("warning:") {
+            return Ok(UbCheckResult::Warning(stderr));
         }
     } else {
         // multiple
@@ -62,7 +208,7 @@ pub fn check_for_ub(
 
             let mut compile_command: std::process::Command = cmd!(
 			    sh,
-			    "timeout -k 0.1 5 clang {generator_flags...} -fsanitize=undefined -fsigned-char -fno-strict-aliasing -fwrapv  -Wall -Wzero-length-array {ignorable_warnings...} {testcase} -c -o {object_output_file}"
+			    "timeout -k 0.1 5 {clang} {generator_flags...} -fsanitize=undefined -fsigned-char -fno-strict-aliasing -fwrapv  -Wall -Wzero-length-array {ignorable_warnings...} {testcase} -c -o {object_output_file}"
 			)
 		.quiet()
 		.into();
@@ -70,52 +216,84 @@ pub fn check_for_ub(
             object_output_files.append(&mut vec![object_output_file]);
 
             let command_output = compile_command.output()?;
+            let stderr = String::from_utf8(command_output.stderr)?;
 
             if !command_output.status.success() {
-                println!(
+                return Ok(UbCheckResult::Failed(format!(
                     "Intermediate command failed! `{:?}'\nstderr: {}",
-                    compile_command,
-                    String::from_utf8(command_output.stderr).unwrap()
-                );
-                exit(1)
+                    compile_command, stderr
+                )));
             }
 
-            let stderr = String::from_utf8(command_output.stderr)?;
             if stderr.contains("warning:") {
-                println!("Unexpected warning with intermediate command! {stderr}");
-                exit(1);
+                return Ok(UbCheckResult::Warning(stderr));
             }
         }
 
         // Link together all the files
         assert!(object_output_files.len() == testcases.len());
 
-        cmd!(
+        let mut link_command: std::process::Command = cmd!(
 		sh,
-		"timeout -k 0.1 5 clang {generator_flags...} -fsanitize=undefined -fsigned-char -fno-strict-aliasing -fwrapv -Wall -Wzero-length-array {ignorable_warnings...} {object_output_files...} -o clang-ubsan.o"
+		"timeout -k 0.1 5 {clang} {generator_flags...} -fsanitize=undefined -fsigned-char -fno-strict-aliasing -fwrapv -Wall -Wzero-length-array {ignorable_warnings...} {object_output_files...} -o clang-ubsan.out"
 	    )
 	    .quiet()
-	    .read()?;
+	    .into();
+        let command_output = link_command.output()?;
 
-        let stderr = cmd!(sh, "timeout -k 1 4 ./clang-ubsan.out")
+        if !command_output.status.success() {
+            return Ok(UbCheckResult::Failed(String::from_utf8(
+                command_output.stderr,
+            )?));
+        }
+    }
+
+    let command_output = cmd!(sh, "timeout -k 1 4 ./clang-ubsan.out")
+        .quiet()
+        .ignore_status()
+        .output()?;
+    let stderr = String::from_utf8(command_output.stderr)?;
+
+    // ubsan reports "runtime error:" and keeps running
+    if stderr.contains("Error") || stderr.contains("runtime error:") {
+        return Ok(UbCheckResult::Ubsan(stderr));
+    }
+
+    if !command_output.status.success() {
+        return Ok(UbCheckResult::Failed(stderr));
+    }
+
+    if testcases.len() == 1 {
+        let testcase = &testcases[0];
+
+        let mut compile_command: std::process::Command = cmd!(sh, "timeout -k 1 4 {gcc} -fsanitize=address {testcase} -w -o gcc-asan.out -fsigned-char -fno-strict-aliasing -fwrapv {ignorable_warnings...} {rv32_flags...} {generator_flags...}").quiet().into();
+        let command_output = compile_command.output()?;
+
+        if !command_output.status.success() {
+            return Ok(UbCheckResult::Failed(String::from_utf8(
+                command_output.stderr,
+            )?));
+        }
+
+        let command_output = cmd!(sh, "timeout -k 1 4 ./gcc-asan.out")
             .quiet()
-            .read_stderr()?;
+            .ignore_status()
+            .output()?;
+        let stderr = String::from_utf8(command_output.stderr)?;
 
-        if stderr.contains("warning:") {
-            println!("Unexpected warning with intermediate command! {stderr}");
-            exit(1);
+        if stderr.contains("Error") || stderr.contains("ERROR: AddressSanitizer") {
+            return Ok(UbCheckResult::Asan(stderr));
         }
 
-        if stderr.contains("Error") {
-            println!("ubsan found error: {stderr}");
-            exit(1);
+        if !command_output.status.success() {
+            return Ok(UbCheckResult::Failed(stderr));
         }
     }
 
-    Ok(())
+    Ok(UbCheckResult::Clean)
 }
 
-/// Compile code and assert that it succeeds
+/// Compile code and check that it succeeds without warnings
 pub fn compile_clean_code(
     sh: &Shell,
     compilers: &Vec<PathBuf>,
@@ -123,10 +301,19 @@ pub fn compile_clean_code(
     compiler_flags: &Vec<Vec<String>>,
     output_file: PathBuf,
     ignorable_warnings: &Vec<String>,
-) -> anyhow::Result<()> {
+) -> Result<(), ReductionCheckError> {
+    let output = output_file.to_str().unwrap().to_string();
     let command_output = if compilers.len() == 1 {
-        assert_eq!(testcases.len(), 1);
-        assert_eq!(compiler_flags.len(), 1);
+        if testcases.len() != 1 || compiler_flags.len() != 1 {
+            return Err(ReductionCheckError::CompileFailed {
+                output,
+                stderr: format!(
+                    "{} testcases and {} sets of flags for one compiler",
+                    testcases.len(),
+                    compiler_flags.len()
+                ),
+            });
+        }
 
         let compiler = &compilers[0];
         let testcase = &testcases[0];
@@ -143,16 +330,17 @@ pub fn compile_clean_code(
         compile_command.output()?
     } else {
         // multiple files
-        assert!(compilers.len() > 1);
-        assert!(testcases.len() > 1);
-        assert!(
-		    compiler_flags.len() == testcases.len() + 1,
-		    "{} != {} Must be exactly n+1 sets of flags specified. The last set is used when linking all object files together.", compiler_flags.len(), testcases.len() + 1
-		);
-        assert!(
-			compilers.len() == testcases.len() + 1,
-			"Must be exactly n+1 compilers specified. The last compiler is used when linking all object files together."
-		    );
+        if compilers.len() != testcases.len() + 1 || compiler_flags.len() != testcases.len() + 1 {
+            return Err(ReductionCheckError::CompileFailed {
+                output,
+                stderr: format!(
+                    "{} compilers and {} sets of flags for {} testcases. Must be exactly n+1 of each, the last is used when linking all object files together.",
+                    compilers.len(),
+                    compiler_flags.len(),
+                    testcases.len()
+                ),
+            });
+        }
 
         let mut object_output_files = vec![];
         // Compile each of the specified files
@@ -176,18 +364,22 @@ pub fn compile_clean_code(
             let stderr = String::from_utf8(command_output.stderr)?;
 
             if !command_output.status.success() {
-                println!("Intermediate command failed! stderr: {stderr}");
-                exit(1)
+                return Err(ReductionCheckError::CompileFailed {
+                    output: object_output_files
+                        .last()
+                        .unwrap()
+                        .to_str()
+                        .unwrap()
+                        .to_string(),
+                    stderr,
+                });
             }
             if stderr.contains("warning:") {
-                println!("Unexpected warning with intermediate command! {stderr}");
-                exit(1);
+                return Err(ReductionCheckError::UnexpectedWarning { stderr });
             }
         }
 
         // Link together all the files
-        assert!(object_output_files.len() == testcases.len());
-
         let flags = &compiler_flags[compiler_flags.len() - 1];
         let compiler_path: &str = compilers[compilers.len() - 1].to_str().unwrap();
 
@@ -226,8 +418,11 @@ pub fn compile_clean_code(
     sh.write_file(output_filename.clone() + "_stdout.txt", stdout)?;
     sh.write_file(output_filename.clone() + "_stderr.txt", &stderr)?;
 
-    match command_output.status.code().unwrap() {
-        0 => {
+    // timeout exits with 124, or the compiler is killed by timeout -k
+    let timed_out =
+        command_output.status.code() == Some(124) || command_output.status.signal() == Some(9);
+    match command_output.status.code() {
+        Some(0) => {
             // Compilation is expected to pass
             // Make sure there aren't warnings
             if stderr
@@ -235,22 +430,186 @@ pub fn compile_clean_code(
                 .replace("warning: relocation in read-only section", "")
                 .contains("warning:")
             {
-                println!("Unexpected {output_filename} compile warning! {stderr}");
-                exit(1);
+                return Err(ReductionCheckError::UnexpectedWarning { stderr });
             }
         }
-        124 => {
-            println!("{output_filename} compilation timeout!");
-            exit(1);
+        _ if timed_out => {
+            return Err(ReductionCheckError::Timeout {
+                output: output_filename.clone(),
+            });
         }
         _ => {
-            println!(
-                "Unrecognized {output_filename} compilation failure: {}",
-                stderr
-            );
-            exit(1);
+            return Err(ReductionCheckError::CompileFailed {
+                output: output_filename.clone(),
+                stderr,
+            });
+        }
+    };
+
+    Ok(())
+}
+
+/// The part of a crash that identifies it: the failed assertion or LLVM
+/// ERROR message, otherwise just the fact that it crashed
+pub fn crash_signature(stderr: &str) -> String {
+    for marker in ["Assertion `", "LLVM ERROR:"] {
+        if let Some(line) = stderr.lines().find(|line| line.contains(marker)) {
+            return line[line.find(marker).unwrap()..].to_string();
+        }
+    }
+    "PLEASE submit a bug report".to_string()
+}
+
+/// Point a fail found on another machine at this machine's tools
+pub fn apply_reduction_config(
+    config: &ReductionConfig,
+    fail_info: &mut FailInfo,
+) -> anyhow::Result<()> {
+    if let Some(remaps) = &config.remap {
+        // Walk the serialized fail info so every path is remapped, including
+        // the ones nested in runners and generators
+        let mut value = serde_yaml::to_value(&*fail_info)?;
+        remap_value(remaps, &mut value);
+        *fail_info = serde_yaml::from_value(value)?;
+    }
+
+    let (runners, generator) = match fail_info {
+        FailInfo::Ice(ice_fail_info) => {
+            if let Some(compiler) = &config.compiler {
+                if is_llvm_ir(&ice_fail_info.testcases) {
+                    // IR finds record the LLVM tool that crashed (opt or llc),
+                    // so swap in the same tool from the compiler's build
+                    for tool in ice_fail_info.compilers.iter_mut() {
+                        let name = tool.file_name().unwrap().to_string_lossy().into_owned();
+                        *tool = llvm_tool(compiler, &name);
+                    }
+                } else {
+                    ice_fail_info.compilers.fill(compiler.clone());
+                }
+            }
+            (vec![], &mut ice_fail_info.generator)
+        }
+        FailInfo::Execution(exec_fail_info) => {
+            if let Some(compiler) = &config.compiler {
+                exec_fail_info.compiler.fill(compiler.clone());
+            }
+            (
+                vec![&mut exec_fail_info.runner],
+                &mut exec_fail_info.generator,
+            )
+        }
+        FailInfo::Runtime(runtime_fail_info) => {
+            if let Some(compiler) = &config.compiler {
+                runtime_fail_info.slow_compiler.fill(compiler.clone());
+            }
+            let mut runners = vec![
+                &mut runtime_fail_info.fast_runner,
+                &mut runtime_fail_info.slow_runner,
+            ];
+            runners.extend(runtime_fail_info.sweep_runner.as_deref_mut());
+            (runners, &mut runtime_fail_info.generator)
         }
     };
 
+    for runner in runners {
+        match runner {
+            Runner::Qemu(qemu_config) => {
+                if let Some(qemu_rv32) = &config.qemu_rv32 {
+                    qemu_config.rv32path = qemu_rv32.clone();
+                }
+                if let Some(qemu_rv64) = &config.qemu_rv64 {
+                    qemu_config.rv64path = qemu_rv64.clone();
+                }
+            }
+            Runner::QemuAarch64(qemu_config) => {
+                if let Some(qemu_aarch64) = &config.qemu_aarch64 {
+                    qemu_config.path = qemu_aarch64.clone();
+                }
+            }
+            Runner::Native | Runner::Spike(_) => (),
+        }
+    }
+
+    if let Some(include_dir) = &config.csmith_include_dir {
+        match generator {
+            FuzzGenerator::Csmith(csmith_config) => {
+                csmith_config.include_dir = include_dir.clone();
+            }
+            FuzzGenerator::LlvmIr(LlvmIrConfig {
+                source: LlvmIrSource::Csmith(csmith_ir_config),
+            }) => {
+                csmith_ir_config.csmith.include_dir = include_dir.clone();
+            }
+            FuzzGenerator::Mutate(mutate_config) => {
+                mutate_config.include_dir = Some(include_dir.clone());
+            }
+            _ => (),
+        }
+    }
+
     Ok(())
 }
+
+/// Apply the first matching remap to a path
+pub fn remap_path(remaps: &[PathRemap], path: &Path) -> PathBuf {
+    for remap in remaps {
+        match path.strip_prefix(&remap.from) {
+            Ok(rest) if rest.as_os_str().is_empty() => return remap.to.clone(),
+            Ok(rest) => return remap.to.join(rest),
+            Err(_) => (),
+        }
+    }
+    path.to_path_buf()
+}
+
+fn remap_value(remaps: &[PathRemap], value: &mut Value) {
+    match value {
+        Value::String(string) => {
+            let remapped = remap_path(remaps, Path::new(string.as_str()));
+            *string = remapped.to_string_lossy().into_owned();
+        }
+        Value::Sequence(sequence) => {
+            for value in sequence {
+                remap_value(remaps, value);
+            }
+        }
+        Value::Mapping(mapping) => {
+            for (_, value) in mapping {
+                remap_value(remaps, value);
+            }
+        }
+        Value::Tagged(tagged) => remap_value(remaps, &mut tagged.value),
+        Value::Null | Value::Bool(_) | Value::Number(_) => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remap_paths() {
+        let remaps = [
+            PathRemap {
+                from: PathBuf::from("/scratch/build"),
+                to: PathBuf::from("/home/user/build"),
+            },
+            PathRemap {
+                from: PathBuf::from("/scratch"),
+                to: PathBuf::from("/data"),
+            },
+        ];
+        let remap = |path: &str| remap_path(&remaps, Path::new(path));
+        assert_eq!(remap("/scratch/build"), Path::new("/home/user/build"));
+        assert_eq!(
+            remap("/scratch/build/bin/gcc"),
+            Path::new("/home/user/build/bin/gcc")
+        );
+        assert_eq!(remap("/scratch/builds/gcc"), Path::new("/data/builds/gcc"));
+        assert_eq!(remap("/scratchy/gcc"), Path::new("/scratchy/gcc"));
+        assert_eq!(
+            remap_path(&[], Path::new("/scratch")),
+            Path::new("/scratch")
+        );
+    }
+}
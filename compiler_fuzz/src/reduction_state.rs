@@ -0,0 +1,117 @@
+//! The phases of a reduction and which of them have finished, kept in the
+//! reduction directory so an interrupted reduction can carry on from the first
+//! unfinished phase instead of starting over.
+
+use std::{fmt, path::Path, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use xshell::Shell;
+
+pub const REDUCTION_STATE: &str = "reduction_state.yaml";
+
+/// Phases in the order reduce runs them, named after the state they leave the
+/// reduction in
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ReductionPhase {
+    Preprocessed,
+    Categorized,
+    FlagsReduced,
+    SourceReduced,
+    IrExtracted,
+    Reported,
+}
+
+impl ReductionPhase {
+    pub const ALL: [ReductionPhase; 6] = [
+        ReductionPhase::Preprocessed,
+        ReductionPhase::Categorized,
+        ReductionPhase::FlagsReduced,
+        ReductionPhase::SourceReduced,
+        ReductionPhase::IrExtracted,
+        ReductionPhase::Reported,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReductionPhase::Preprocessed => "preprocessed",
+            ReductionPhase::Categorized => "categorized",
+            ReductionPhase::FlagsReduced => "flags-reduced",
+            ReductionPhase::SourceReduced => "source-reduced",
+            ReductionPhase::IrExtracted => "ir-extracted",
+            ReductionPhase::Reported => "reported",
+        }
+    }
+}
+
+impl fmt::Display for ReductionPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for ReductionPhase {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ReductionPhase::ALL
+            .into_iter()
+            .find(|phase| phase.name() == s)
+            .ok_or_else(|| {
+                format!(
+                    "Unknown phase {s}, expected one of {}",
+                    ReductionPhase::ALL.map(|phase| phase.name()).join(", ")
+                )
+            })
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct ReductionState {
+    /// Always a prefix of `ReductionPhase::ALL`
+    pub completed_phases: Vec<ReductionPhase>,
+}
+
+impl ReductionState {
+    /// The phase a resumed reduction starts at, None once it has been reported
+    pub fn next_phase(&self) -> Option<ReductionPhase> {
+        ReductionPhase::ALL
+            .into_iter()
+            .find(|phase| !self.completed_phases.contains(phase))
+    }
+
+    pub fn is_completed(&self, phase: ReductionPhase) -> bool {
+        self.completed_phases.contains(&phase)
+    }
+
+    /// Record `phase` as finished. Later phases worked from what it replaced,
+    /// so they have to run again.
+    pub fn complete(&mut self, phase: ReductionPhase) {
+        self.completed_phases.retain(|completed| *completed < phase);
+        self.completed_phases.push(phase);
+    }
+}
+
+/// The reduction's state, None for reductions started before phases were
+/// recorded
+pub fn read_reduction_state(
+    sh: &Shell,
+    reduction_dir: &Path,
+) -> anyhow::Result<Option<ReductionState>> {
+    let state_path = reduction_dir.join(REDUCTION_STATE);
+    if !state_path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(serde_yaml::from_str(&sh.read_file(state_path)?)?))
+}
+
+pub fn write_reduction_state(
+    sh: &Shell,
+    reduction_dir: &Path,
+    state: &ReductionState,
+) -> anyhow::Result<()> {
+    sh.write_file(
+        reduction_dir.join(REDUCTION_STATE),
+        serde_yaml::to_string(state)?,
+    )?;
+    Ok(())
+}
@@ -0,0 +1,124 @@
+//! Rerunning a reduction's interestingness test outside of creduce, for
+//! bisecting and for checking finds against newer compilers.
+
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+use xshell::{cmd, Shell, TempDir};
+
+use crate::{
+    is_llvm_ir,
+    reduction::{apply_reduction_config, crash_signature, is_not_interesting},
+    ExecFailType, FailInfo, IceFailType, ReductionConfig,
+};
+
+/// Copy a reduction directory somewhere the interestingness tests can write
+/// their output without touching the reduction's files, with `config` applied
+/// to the copy's fail_info.yaml
+pub fn copy_reduction(
+    sh: &Shell,
+    reduction_dir: &Path,
+    config: &ReductionConfig,
+) -> anyhow::Result<TempDir> {
+    let work_temp_dir = sh.create_temp_dir()?;
+    let work_dir = work_temp_dir.path();
+    cmd!(sh, "cp -r {reduction_dir}/. {work_dir}")
+        .quiet()
+        .run()?;
+
+    let mut fail_info = read_fail_info(sh, work_dir)?;
+    apply_reduction_config(config, &mut fail_info)?;
+    write_fail_info(sh, work_dir, &fail_info)?;
+
+    Ok(work_temp_dir)
+}
+
+pub fn read_fail_info(sh: &Shell, dir: &Path) -> anyhow::Result<FailInfo> {
+    Ok(serde_yaml::from_str(
+        &sh.read_file(dir.join("fail_info.yaml"))?,
+    )?)
+}
+
+pub fn write_fail_info(sh: &Shell, dir: &Path, fail_info: &FailInfo) -> anyhow::Result<()> {
+    sh.write_file(
+        dir.join("fail_info.yaml"),
+        serde_yaml::to_string(fail_info)?,
+    )?;
+    Ok(())
+}
+
+/// Forget the category of a fail so any failure of the same kind is
+/// interesting. IR fails are always checked against their crash signature.
+pub fn uncategorize(fail_info: &mut FailInfo) {
+    match fail_info {
+        FailInfo::Ice(ice_fail_info) => {
+            ice_fail_info.fail_type = match ice_fail_info.fail_type {
+                IceFailType::Gcc(_) => IceFailType::Gcc(None),
+                IceFailType::Llvm(_) => IceFailType::Llvm(None),
+            }
+        }
+        FailInfo::Execution(exec_fail_info) => {
+            exec_fail_info.fail_type = match exec_fail_info.fail_type {
+                ExecFailType::Qemu(_) => ExecFailType::Qemu(None),
+                ExecFailType::Native(_) => ExecFailType::Native(None),
+                ExecFailType::Spike(_) => ExecFailType::Spike(None),
+            }
+        }
+        FailInfo::Runtime(runtime_fail_info) => {
+            runtime_fail_info.fail_type = None;
+        }
+    }
+}
+
+/// Whether the fail in `work_dir` (made by copy_reduction) still reproduces.
+/// None when the interestingness test itself broke.
+pub fn reproduces(work_dir: &Path) -> anyhow::Result<Option<bool>> {
+    let sh = Shell::new()?;
+    sh.change_dir(work_dir);
+    let _env_var = sh.push_env("REDUCTION_DIR", work_dir);
+
+    let fail_info = read_fail_info(&sh, work_dir)?;
+    let test = match &fail_info {
+        FailInfo::Ice(ice_fail_info) if is_llvm_ir(&ice_fail_info.testcases) => {
+            // Same test llvm-reduce used
+            let tool = &ice_fail_info.compilers[0];
+            let flags = sh.read_file("compiler_opts.txt")?;
+            let flags = flags.split_whitespace();
+            let signature = crash_signature(&sh.read_file("stderr.txt")?);
+            let output = cmd!(
+                sh,
+                "timeout -k 0.1 60 {tool} {flags...} reduced.ll -o /dev/null"
+            )
+            .quiet()
+            .ignore_status()
+            .output()?;
+            return Ok(Some(
+                String::from_utf8_lossy(&output.stderr).contains(&signature),
+            ));
+        }
+        FailInfo::Ice(_) => test_binary("reduce_ice")?,
+        FailInfo::Execution(_) => test_binary("reduce_exec")?,
+        FailInfo::Runtime(_) => test_binary("reduce_runtime")?,
+    };
+
+    let output = cmd!(sh, "{test}").quiet().ignore_status().output()?;
+    Ok(match output.status.code() {
+        Some(0) => Some(true),
+        Some(code) if is_not_interesting(code) => Some(false),
+        _ => {
+            println!(
+                "Interestingness test failed:\n{}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+            None
+        }
+    })
+}
+
+/// The reduce_* binaries are built next to whichever binary is running
+fn test_binary(name: &str) -> anyhow::Result<PathBuf> {
+    let current_exe = env::current_exe()?;
+    Ok(current_exe.parent().unwrap().join(name))
+}